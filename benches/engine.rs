@@ -0,0 +1,228 @@
+// Micro-benchmarks of the hot paths of the engine.
+//
+// Run with `cargo bench`. The processes are state machines, so the
+// benchmarks do not need the nightly toolchain, except the ones of the
+// generator backends.
+#![cfg_attr(feature = "nightly", feature(generators, generator_trait))]
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use desim::network::{BatchSize as Batch, Delay, NetworkBuilder, Router, Source, Station};
+use desim::typed::ResourceId;
+use desim::{
+    Effect, EndCondition, JitterSpec, SimContext, Simulation, StateMachineProcess, StaticProcess,
+    Step,
+};
+
+#[cfg(feature = "nightly")]
+#[path = "engine/generators.rs"]
+mod generators;
+
+const PROCESSES: usize = 100;
+const STEPS: usize = 100_000;
+
+/// Processes that only wait, over and over.
+fn timeout_churn(log: bool) -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    if !log {
+        s.set_log_filter(|_, _| false);
+    }
+    for i in 0..PROCESSES {
+        let delay = 1.0 + (i % 7) as f64;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            Step::Yield(Effect::TimeOut(delay))
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+    }
+    s
+}
+
+/// Processes contending for a resource with fewer units than them.
+fn contention(log: bool) -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    if !log {
+        s.set_log_filter(|_, _| false);
+    }
+    let r = s.create_resource(PROCESSES / 10);
+    for i in 0..PROCESSES {
+        let service = 1.0 + (i % 3) as f64;
+        let cycle = [
+            Effect::Request(r),
+            Effect::TimeOut(service),
+            Effect::Release(r),
+            Effect::TimeOut(1.0),
+        ];
+        let mut step = 0;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            step += 1;
+            Step::Yield(cycle[(step - 1) % cycle.len()])
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+    }
+    s
+}
+
+/// Processes contending for a resource whose capacity follows shifts.
+fn shifts() -> Simulation<Effect> {
+    let mut s = contention(false);
+    s.set_resource_schedule(
+        0,
+        vec![(0.0, PROCESSES / 10), (8.0, PROCESSES / 20)],
+        Some(16.0),
+    );
+    s
+}
+
+/// Processes served in batches.
+fn batches() -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    s.set_log_filter(|_, _| false);
+    let r = s.create_batch_resource(8, 4, 2.0);
+    for _ in 0..PROCESSES {
+        let cycle = [
+            Effect::Request(r),
+            Effect::TimeOut(1.0),
+            Effect::Release(r),
+            Effect::TimeOut(3.0),
+        ];
+        let mut step = 0;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            step += 1;
+            Step::Yield(cycle[(step - 1) % cycle.len()])
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+    }
+    s
+}
+
+/// A customer using a counter over and over, the same type for all the
+/// processes of the dispatch benchmarks.
+struct Customer {
+    counter: ResourceId,
+    service: f64,
+    step: usize,
+}
+
+impl Customer {
+    fn new(counter: ResourceId, i: usize) -> Customer {
+        Customer {
+            counter,
+            service: 1.0 + (i % 7) as f64,
+            step: 0,
+        }
+    }
+
+    fn next(&mut self) -> Step<Effect> {
+        self.step += 1;
+        Step::Yield(match self.step % 4 {
+            1 => self.counter.request(),
+            2 => Effect::TimeOut(self.service),
+            3 => self.counter.release(),
+            _ => Effect::TimeOut(10.0),
+        })
+    }
+}
+
+impl StateMachineProcess<Effect> for Customer {
+    fn resume(&mut self, _: SimContext<Effect>) -> Step<Effect> {
+        self.next()
+    }
+}
+
+impl StaticProcess<Effect> for Customer {
+    fn resume(&mut self, _: SimContext<Effect>) -> Step<Effect> {
+        self.next()
+    }
+}
+
+/// Schedule the customers of the dispatch benchmarks, without logging.
+fn start_customers<G: StaticProcess<Effect>>(s: &mut Simulation<Effect, G>) {
+    s.set_log_filter(|_, _| false);
+    for p in 0..PROCESSES {
+        s.schedule_event((p % 10) as f64, p, Effect::TimeOut(0.0));
+    }
+}
+
+/// Customers boxed one by one.
+fn boxed_customers() -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    let counter = s.create_typed_resource(PROCESSES / 10);
+    for i in 0..PROCESSES {
+        s.create_sm_process(Box::new(Customer::new(counter, i)));
+    }
+    start_customers(&mut s);
+    s
+}
+
+/// Customers stored inline and resumed with static dispatch.
+fn static_customers() -> Simulation<Effect, Customer> {
+    let mut s = Simulation::with_static_processes();
+    let counter = s.create_typed_resource(PROCESSES / 10);
+    for i in 0..PROCESSES {
+        s.create_static_process(Customer::new(counter, i));
+    }
+    start_customers(&mut s);
+    s
+}
+
+/// Entities flowing through a Jackson network of two stations.
+fn network() -> Simulation<Effect> {
+    let exponential = |mean| Delay::Jitter {
+        base: 0.0,
+        dist: JitterSpec::Exponential(mean),
+    };
+    let mut s = Simulation::new();
+    s.set_log_filter(|_, _| false);
+    let mut net = NetworkBuilder::new();
+    let a = net.station(Station {
+        servers: 1,
+        service: exponential(0.5),
+    });
+    let b = net.station(Station {
+        servers: 2,
+        service: exponential(0.8),
+    });
+    net.route(a, Router::Probabilities(vec![(b, 0.5)]));
+    net.route(b, Router::Probabilities(vec![(a, 0.2)]));
+    net.source(Source {
+        interarrival: exponential(1.0),
+        batch: Batch::Fixed(1),
+        router: Router::to(a),
+        entities: STEPS,
+    });
+    net.build(&mut s);
+    s
+}
+
+fn bench<G: StaticProcess<Effect>>(
+    c: &mut Criterion,
+    name: &str,
+    build: fn() -> Simulation<Effect, G>,
+) {
+    c.bench_function(name, |b| {
+        b.iter_batched(
+            build,
+            |s| black_box(s.run(EndCondition::NSteps(STEPS))),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn engine(c: &mut Criterion) {
+    bench(c, "timeout churn, logging", || timeout_churn(true));
+    bench(c, "timeout churn, no logging", || timeout_churn(false));
+    bench(c, "contention, logging", || contention(true));
+    bench(c, "contention, no logging", || contention(false));
+    bench(c, "contention, shifts", shifts);
+    bench(c, "batch service", batches);
+    bench(c, "jackson network", network);
+    bench(c, "dispatch, boxed state machines", boxed_customers);
+    bench(c, "dispatch, static state machines", static_customers);
+    #[cfg(feature = "nightly")]
+    {
+        bench(c, "dispatch, boxed generators", generators::boxed);
+        bench(c, "dispatch, pooled generators", generators::pooled);
+        bench(c, "dispatch, static generators", generators::inline);
+    }
+}
+
+criterion_group!(benches, engine);
+criterion_main!(benches);
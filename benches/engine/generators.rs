@@ -0,0 +1,54 @@
+// The dispatch benchmarks of the generator backends, that need the
+// nightly toolchain.
+use super::{start_customers, PROCESSES};
+use desim::typed::ResourceId;
+use desim::{Effect, SimContext, Simulation};
+use std::ops::Generator;
+
+/// A customer using a counter over and over. Every call returns a
+/// generator of the same type.
+fn customer(
+    counter: ResourceId,
+    i: usize,
+) -> impl Generator<SimContext<Effect>, Yield = Effect, Return = ()> + Unpin {
+    let service = 1.0 + (i % 7) as f64;
+    move |_| loop {
+        yield counter.request();
+        yield Effect::TimeOut(service);
+        yield counter.release();
+        yield Effect::TimeOut(10.0);
+    }
+}
+
+/// Customers boxed one by one.
+pub fn boxed() -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    let counter = s.create_typed_resource(PROCESSES / 10);
+    for i in 0..PROCESSES {
+        s.create_process(Box::new(customer(counter, i)));
+    }
+    start_customers(&mut s);
+    s
+}
+
+/// Customers stored together in a pool, resumed with a dynamic call to
+/// the pool.
+pub fn pooled() -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    let counter = s.create_typed_resource(PROCESSES / 10);
+    s.create_process_pool((0..PROCESSES).map(|i| customer(counter, i)));
+    start_customers(&mut s);
+    s
+}
+
+/// Customers stored inline and resumed with static dispatch.
+pub fn inline(
+) -> Simulation<Effect, impl Generator<SimContext<Effect>, Yield = Effect, Return = ()> + Unpin> {
+    let mut s = Simulation::with_static_processes();
+    let counter = s.create_typed_resource(PROCESSES / 10);
+    for i in 0..PROCESSES {
+        s.create_static_process(customer(counter, i));
+    }
+    start_customers(&mut s);
+    s
+}
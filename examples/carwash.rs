@@ -1,5 +1,6 @@
 // Simulate cars arriving and being served at a carwash
 #![feature(generators, generator_trait)]
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter, Result};
 use std::collections::HashMap;
 
@@ -28,13 +29,14 @@ enum CarState {
 }
 
 impl SimState for CarState {
-    fn get_effect(&self) -> Effect {
-        match self {
+    fn effect(&self) -> Cow<'_, Effect> {
+        // the effect is computed from the state
+        Cow::Owned(match self {
             Drive(t) => Effect::TimeOut(*t as f64),
             WaitMachine(r) => Effect::Request(*r),
             Wash(t) => Effect::TimeOut(*t as f64),
             Leave(r) => Effect::Release(*r),
-        }
+        })
     }
     fn set_effect(&mut self, _: Effect) {
         //
@@ -0,0 +1,63 @@
+// An open Jackson network of two stations, compared with the analytical
+// result. Entities arrive at A, half of them then go to B, and a fifth
+// of the ones served by B go back to A.
+use desim::network::{BatchSize, Delay, NetworkBuilder, Router, Source, Station};
+use desim::{Effect, EndCondition, JitterSpec, Simulation};
+
+fn exponential(mean: f64) -> Delay {
+    Delay::Jitter {
+        base: 0.0,
+        dist: JitterSpec::Exponential(mean),
+    }
+}
+
+fn main() {
+    let (arrival, mean_a, mean_b) = (1.0, 0.5, 0.4);
+    let mut sim = Simulation::<Effect>::new();
+    sim.set_seed(42);
+    let mut net = NetworkBuilder::new();
+    net.seed(42);
+    let a = net.station(Station {
+        servers: 1,
+        service: exponential(mean_a),
+    });
+    let b = net.station(Station {
+        servers: 1,
+        service: exponential(mean_b),
+    });
+    net.route(a, Router::Probabilities(vec![(b, 0.5)]));
+    net.route(b, Router::Probabilities(vec![(a, 0.2)]));
+    net.source(Source {
+        interarrival: exponential(1.0 / arrival),
+        batch: BatchSize::Fixed(1),
+        router: Router::to(a),
+        entities: 100_000,
+    });
+    let net = net.build(&mut sim);
+    let sim = sim.run(EndCondition::NoEvents);
+
+    // solve the traffic equations la = arrival + 0.2 lb, lb = 0.5 la
+    let la = arrival / 0.9;
+    let lb = 0.5 * la;
+    println!("station  throughput (exp.)  utilization (exp.)  wait (exp.)");
+    for (name, station, rate, mean) in [("A", a, la, mean_a), ("B", b, lb, mean_b)] {
+        let stats = net.station_stats(&sim, station);
+        let rho = rate * mean;
+        println!(
+            "{:7}  {:.3} ({:.3})      {:.3} ({:.3})       {:.3} ({:.3})",
+            name,
+            stats.throughput,
+            rate,
+            stats.utilization,
+            rho,
+            stats.mean_wait,
+            rho * mean / (1.0 - rho)
+        );
+    }
+    let sojourn = (la * mean_a / (1.0 - la * mean_a) + lb * mean_b / (1.0 - lb * mean_b)) / arrival;
+    println!(
+        "sojourn time {:.3} ({:.3})",
+        net.sink().mean_sojourn_time(),
+        sojourn
+    );
+}
@@ -13,6 +13,7 @@
 //
 #![feature(generators, generator_trait)]
 use desim::{Effect, EndCondition, ResourceId, SimGen, SimState, Simulation};
+use std::borrow::Cow;
 use rand::rngs::SmallRng as Rng;
 use rand::{RngCore as RngT, SeedableRng};
 
@@ -43,8 +44,8 @@ struct PCBState {
 }
 
 impl SimState for PCBState {
-    fn get_effect(&self) -> Effect {
-        self.effect
+    fn effect(&self) -> Cow<'_, Effect> {
+        Cow::Borrowed(&self.effect)
     }
     fn set_effect(&mut self, e: Effect) {
         self.effect = e;
@@ -1,4 +1,5 @@
-// Simulate the scheduling of two processes on one CPU
+// Simulate the scheduling of two processes on one CPU, labeling each step
+// with a payload attached to its effect
 #![feature(generators, generator_trait)]
 use rand::{rngs::SmallRng as Rng, RngCore as RngT, SeedableRng};
 
@@ -10,28 +11,28 @@ fn main() {
     let p1 = s.create_process(Box::new(move |_| {
         for _ in 0..10 {
             // wait for the cpu to be available
-            yield Effect::Request(cpu);
+            yield (Effect::Request(cpu), "p1 waits");
             // do some job that requires a fixed amount of 5 time units
-            yield Effect::TimeOut(5.0);
+            yield (Effect::TimeOut(5.0), "p1 computes");
             // release the CPU
-            yield Effect::Release(cpu);
+            yield (Effect::Release(cpu), "p1 done");
         }
     }));
     let p2 = s.create_process(Box::new(move |_| {
         let mut rng = Rng::from_entropy();
         loop {
             // wait for the CPU
-            yield Effect::Request(cpu);
+            yield (Effect::Request(cpu), "p2 waits");
             // do some job for a random amount of time units between 0 and 10
-            yield Effect::TimeOut((rng.next_u32() % 10) as f64);
+            yield (Effect::TimeOut((rng.next_u32() % 10) as f64), "p2 computes");
             // release the CPU
-            yield Effect::Release(cpu);
+            yield (Effect::Release(cpu), "p2 done");
         }
     }));
     // let p1 to start immediately...
-    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(0.0, p1, (Effect::TimeOut(0.), "start"));
     // ...and p2 after 17 time units
-    s.schedule_event(17.0, p2, Effect::TimeOut(0.));
+    s.schedule_event(17.0, p2, (Effect::TimeOut(0.), "start"));
 
     s = s.run(EndCondition::Time(100.0));
 
@@ -0,0 +1,69 @@
+// Compare running many similar processes boxed one by one, in a pool and
+// stored inline. The benches in benches/engine.rs measure the same.
+#![feature(generators, generator_trait)]
+use std::ops::Generator;
+use std::time::Instant;
+
+use desim::{Effect, EndCondition, ResourceId, SimContext, Simulation, StaticProcess};
+
+const CUSTOMERS: usize = 200_000;
+
+// every call returns a generator of the same type
+fn customer(
+    counter: ResourceId,
+    service: f64,
+) -> impl Generator<SimContext<Effect>, Yield = Effect, Return = ()> + Unpin {
+    move |_| {
+        for _ in 0..5 {
+            yield Effect::Request(counter);
+            yield Effect::TimeOut(service);
+            yield Effect::Release(counter);
+            yield Effect::TimeOut(10.0);
+        }
+    }
+}
+
+fn services() -> impl Iterator<Item = f64> {
+    (0..CUSTOMERS).map(|i| 1.0 + (i % 7) as f64)
+}
+
+// Run the simulation, timing only the run and not building the model
+fn run<G: StaticProcess<Effect>>(mut s: Simulation<Effect, G>) -> f64 {
+    for i in 0..CUSTOMERS {
+        s.schedule_event((i % 1000) as f64, i, Effect::TimeOut(0.));
+    }
+    // only measure the processes, not the log
+    s.set_log_filter(|_, _| false);
+    let start = Instant::now();
+    s.run(EndCondition::NoEvents);
+    start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    // the counter is the first resource of each simulation
+    let counter: ResourceId = 0;
+
+    let mut boxed = Simulation::new();
+    boxed.create_resource(100);
+    for t in services() {
+        boxed.create_process(Box::new(customer(counter, t)));
+    }
+    let mut pooled = Simulation::new();
+    pooled.create_resource(100);
+    pooled.create_process_pool(services().map(|t| customer(counter, t)));
+    let mut inline = Simulation::with_static_processes();
+    inline.create_resource(100);
+    for t in services() {
+        inline.create_static_process(customer(counter, t));
+    }
+
+    for (name, elapsed) in [
+        ("boxed", run(boxed)),
+        ("pooled", run(pooled)),
+        ("inline", run(inline)),
+    ]
+    .iter()
+    {
+        println!("{} customers, {}: {:.3}s", CUSTOMERS, name, elapsed);
+    }
+}
@@ -0,0 +1,116 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Pluggable policies deciding how the units of a resource are granted.
+
+use crate::{ProcessId, Resource, ResourceId, SimState, Simulation, StaticProcess};
+use alloc::boxed::Box;
+
+/// What to do with a request for a unit of a resource.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AllocDecision {
+    /// Grant a unit to the process right away. There must be a unit
+    /// available.
+    Grant,
+    /// Add the process to the queue of the resource.
+    Enqueue,
+    /// Refuse the request: the process is resumed right away, and
+    /// `SimContext::rejected` returns `true`.
+    Reject,
+}
+
+/// A read-only view of a resource, passed to its `Allocator`.
+pub struct ResourceState<'a, T> {
+    resource: &'a Resource<T>,
+}
+
+impl<'a, T> ResourceState<'a, T> {
+    pub(crate) fn new(resource: &'a Resource<T>) -> ResourceState<'a, T> {
+        ResourceState { resource }
+    }
+
+    /// Returns the total number of units of the resource.
+    pub fn allocated(&self) -> usize {
+        self.resource.allocated
+    }
+
+    /// Returns the number of units not in use.
+    pub fn available(&self) -> usize {
+        self.resource.available
+    }
+
+    /// Returns the number of processes waiting for a unit.
+    pub fn queue_len(&self) -> usize {
+        self.resource.queue.len()
+    }
+
+    /// Iterate over the processes waiting for a unit, from the one that
+    /// requested it first.
+    pub fn waiting(&self) -> impl Iterator<Item = ProcessId> + 'a {
+        self.resource.queue.iter().map(|e| e.process)
+    }
+}
+
+/// A policy deciding which processes are granted the units of a resource
+/// requested with `Effect::Request`.
+///
+/// The simulation still keeps the accounting of the units and the queue of
+/// waiting processes: the allocator only takes the decisions. A failed
+/// resource never grants units, so the allocator is not consulted until it
+/// is repaired. Leases are granted by the simulation without consulting it.
+pub trait Allocator<T> {
+    /// Decide whether `process`, requesting a unit, is granted one right
+    /// away, has to wait in the queue or is rejected.
+    fn on_request(&mut self, res: &ResourceState<'_, T>, process: ProcessId) -> AllocDecision;
+
+    /// Called while units are available and processes are waiting, e.g.
+    /// after a unit was released: returns the position in the queue of the
+    /// process to grant a unit to, or `None` to leave the units available.
+    fn on_release(&mut self, res: &ResourceState<'_, T>) -> Option<usize>;
+}
+
+/// The default allocator: a request is granted if a unit is available,
+/// and the units are granted to the waiting processes in FIFO order.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FifoAllocator;
+
+impl<T> Allocator<T> for FifoAllocator {
+    fn on_request(&mut self, res: &ResourceState<'_, T>, _process: ProcessId) -> AllocDecision {
+        if res.available() > 0 {
+            AllocDecision::Grant
+        } else {
+            AllocDecision::Enqueue
+        }
+    }
+
+    fn on_release(&mut self, res: &ResourceState<'_, T>) -> Option<usize> {
+        if res.queue_len() > 0 {
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Set the allocator deciding how the units of resource `id` are
+    /// granted, replacing the default `FifoAllocator`.
+    pub fn set_resource_allocator(&mut self, id: ResourceId, allocator: Box<dyn Allocator<T>>) {
+        self.allocators[id] = allocator;
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,77 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{AllocDecision, Allocator, ProcessId, ResourceState};
+
+/// Keeps one unit for a privileged process.
+struct Reserve {
+    privileged: ProcessId,
+}
+
+impl<T> Allocator<T> for Reserve {
+    fn on_request(&mut self, res: &ResourceState<'_, T>, process: ProcessId) -> AllocDecision {
+        let reserved = if process == self.privileged { 0 } else { 1 };
+        if res.available() > reserved {
+            AllocDecision::Grant
+        } else {
+            AllocDecision::Enqueue
+        }
+    }
+
+    fn on_release(&mut self, res: &ResourceState<'_, T>) -> Option<usize> {
+        let available = res.available();
+        res.waiting()
+            .position(|p| p == self.privileged || available > 1)
+    }
+}
+
+#[test]
+fn reserved_unit() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(2);
+    let mut users = Vec::new();
+    for _ in 0..3 {
+        users.push(s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(10.0)).await;
+            handle.yield_state(Effect::Release(r)).await;
+        }));
+    }
+    let privileged = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(2.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.set_resource_allocator(r, Box::new(Reserve { privileged }));
+    for &user in users.iter() {
+        s.schedule_event(0.0, user, Effect::TimeOut(0.));
+    }
+    s.schedule_event(1.0, privileged, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    // only one unit is ever used by the other processes, even when the
+    // privileged process is not using its own
+    assert_eq!(
+        s.resource_hold_log(r),
+        &[
+            (1.0, 3.0, privileged),
+            (0.0, 10.0, users[0]),
+            (10.0, 20.0, users[1]),
+            (20.0, 30.0, users[2])
+        ]
+    );
+}
@@ -0,0 +1,77 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Requests of resources retried with an exponential backoff.
+
+use crate::{Effect, Event, ResourceId, SimState, Simulation, StaticProcess};
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Handle the `Effect::RequestWithBackoff` of the process of `event`
+    /// after `retries` retries, scheduling the next one if the request
+    /// is rejected.
+    pub(crate) fn request_with_backoff(
+        &mut self,
+        r: ResourceId,
+        base: f64,
+        max_retries: usize,
+        retries: usize,
+        event: Event<T>,
+        state: T,
+    ) {
+        let process = event.process;
+        let state = match self.request_unit(r, event, state) {
+            Some(state) => state,
+            None => return,
+        };
+        if retries < max_retries {
+            let delay = (0..retries).fold(base, |delay, _| delay * 2.0);
+            let seq = self.next_seq;
+            self.schedule_finite(self.time + delay * self.time_scale, process, state);
+            // the retry may have been dropped by the simulation
+            if self.next_seq != seq {
+                self.backoffs.insert(process, (retries + 1, seq));
+            }
+        } else {
+            // give up
+            self.rejected.insert(process);
+            self.schedule_at(self.time, process, state);
+        }
+    }
+
+    /// Returns `true` if `event` is the retry of a request with backoff.
+    pub(crate) fn is_retry(&self, event: &Event<T>) -> bool {
+        matches!(self.backoffs.get(&event.process), Some(&(_, seq)) if seq == event.seq)
+    }
+
+    /// Retry the request of the process of `event`, logging it like the
+    /// original request.
+    pub(crate) fn retry_request(&mut self, event: Event<T>) {
+        let (retries, _) = self.backoffs.remove(&event.process).unwrap();
+        let state = event.state.clone();
+        self.log_processed_event(&event, &state);
+        let effect = state.effect().into_owned();
+        match effect {
+            Effect::RequestWithBackoff {
+                resource,
+                base,
+                max_retries,
+            } => self.request_with_backoff(resource, base, max_retries, retries, event, state),
+            _ => unreachable!("The retry of process {} is not a request", event.process),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,83 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{AllocDecision, Allocator, ProcessId, ResourceState};
+
+/// Rejects all the requests.
+struct Full;
+
+impl<T> Allocator<T> for Full {
+    fn on_request(&mut self, _res: &ResourceState<'_, T>, _process: ProcessId) -> AllocDecision {
+        AllocDecision::Reject
+    }
+
+    fn on_release(&mut self, _res: &ResourceState<'_, T>) -> Option<usize> {
+        None
+    }
+}
+
+#[test]
+fn retries_follow_backoff() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    s.set_resource_allocator(r, Box::new(Full));
+    let p = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle
+            .yield_state(Effect::RequestWithBackoff {
+                resource: r,
+                base: 1.0,
+                max_retries: 3,
+            })
+            .await;
+        assert!(ctx.rejected());
+        assert_eq!(ctx.time(), 7.0);
+        let ctx = handle.yield_state(Effect::Trace).await;
+        assert!(!ctx.rejected());
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    let requests: Vec<f64> = s
+        .processed_events()
+        .iter()
+        .filter(|(_, state)| matches!(state, Effect::RequestWithBackoff { .. }))
+        .map(|(e, _)| e.time())
+        .collect();
+    // the request, then its retries after 1, 2 and 4 time units
+    assert_eq!(requests, vec![0.0, 1.0, 3.0, 7.0]);
+    assert_eq!(s.processed_events().last().unwrap().0.time(), 7.0);
+    s.assert_no_pending();
+}
+
+#[test]
+fn rejected_request() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    s.set_resource_allocator(r, Box::new(Full));
+    let p = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Request(r)).await;
+        assert!(ctx.rejected());
+        assert_eq!(ctx.time(), 0.0);
+        handle.yield_state(Effect::Trace).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    assert_eq!(s.processed_events().len(), 2);
+}
@@ -0,0 +1,196 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Resources serving their requests in batches.
+
+use crate::{Event, ProcessId, ResourceId, SimState, Simulation, StaticProcess, WakeReason};
+use alloc::vec::Vec;
+
+/// How a batch resource groups its requests.
+#[derive(Debug, Clone)]
+pub(crate) struct Batch {
+    /// Number of waiting requests that start a batch
+    min: usize,
+    /// Time after which a partial batch is started
+    timeout: f64,
+    /// Sequence number of the event of the pending timeout, scheduled for
+    /// the process that was first in the queue when it was armed
+    timer: Option<(u64, ProcessId)>,
+    /// Whether the timeout elapsed while the resource was in use, so that
+    /// the next batch starts as soon as it is free
+    expired: bool,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a resource serving up to `max_batch` requests at a time,
+    /// granted all together, like an oven baking several items at once.
+    ///
+    /// A batch starts when the resource is free and at least `min_batch`
+    /// requests are waiting, granting up to `max_batch` of them in the order
+    /// they were made. If fewer requests are waiting `timeout` time units
+    /// after the first of them, a partial batch with the waiting ones starts
+    /// instead, as soon as the resource is free. Requests left waiting when
+    /// a batch starts wait for the next one, whose timeout starts then.
+    ///
+    /// The resource is free when all the processes of the previous batch
+    /// released it. Its allocator is not used, and the timeouts are events
+    /// of the first waiting process, that take a step without resuming it.
+    pub fn create_batch_resource(
+        &mut self,
+        max_batch: usize,
+        min_batch: usize,
+        timeout: f64,
+    ) -> ResourceId {
+        assert!(
+            0 < min_batch && min_batch <= max_batch,
+            "The minimum size of a batch is {}, not between 1 and the maximum size {}",
+            min_batch,
+            max_batch
+        );
+        assert!(
+            timeout >= 0.0,
+            "The timeout of a batch {} is negative",
+            timeout
+        );
+        let id = self.create_resource(max_batch);
+        self.resources[id].batch = Some(Batch {
+            min: min_batch,
+            timeout,
+            timer: None,
+            expired: false,
+        });
+        id
+    }
+
+    /// Queue the request of a unit of batch resource `r` by the process
+    /// of `event`, starting a batch if it is complete.
+    pub(crate) fn request_batch(&mut self, r: ResourceId, event: Event<T>, state: T) {
+        self.resources[r].queue.push_back(Event { state, ..event });
+        if !self.grant_batch(r) {
+            self.arm_batch(r);
+        }
+    }
+
+    /// Start a batch on resource `r`, if it is free and enough requests
+    /// are waiting or the timeout elapsed.
+    ///
+    /// Returns `false` if no batch was started.
+    pub(crate) fn grant_batch(&mut self, r: ResourceId) -> bool {
+        let res = &mut self.resources[r];
+        let batch = res.batch.as_mut().unwrap();
+        if res.failed
+            || res.available < res.allocated
+            || res.queue.is_empty()
+            || (res.queue.len() < batch.min && !batch.expired)
+        {
+            return false;
+        }
+        batch.expired = false;
+        let timer = batch.timer.take();
+        let size = res.queue.len().min(res.allocated);
+        for request_event in res.queue.drain(..size).collect::<Vec<_>>() {
+            let res = &mut self.resources[r];
+            res.available -= 1;
+            let waited = self.time - request_event.time;
+            res.record_grant(waited);
+            res.holders.push((request_event.process, self.time));
+            let wake = WakeReason::ResourceAcquired {
+                resource: r,
+                unit: res.take_unit(request_event.process),
+                waited,
+            };
+            let Event { process, state, .. } = request_event;
+            self.schedule_woken(self.time, process, state, wake);
+        }
+        if let Some((seq, process)) = timer {
+            self.disarm_batch(seq, process);
+        }
+        // the requests left start forming the next batch
+        self.arm_batch(r);
+        true
+    }
+
+    /// Returns `true` if `event` is the timeout of a batch.
+    pub(crate) fn is_batch_timeout(&self, event: &Event<T>) -> bool {
+        self.batch_timeouts.contains_key(&event.seq)
+    }
+
+    /// Start the partial batch whose timeout is `event`, now or as soon
+    /// as its resource is free.
+    pub(crate) fn expire_batch(&mut self, event: Event<T>) {
+        let r = self.batch_timeouts.remove(&event.seq).flatten().unwrap();
+        let batch = self.resources[r].batch.as_mut().unwrap();
+        batch.timer = None;
+        batch.expired = true;
+        self.grant_batch(r);
+    }
+
+    /// Forget the timeout of batch resource `r` scheduled for the killed
+    /// process `id`, arming it again for the first process still waiting.
+    pub(crate) fn forget_batch_waiter(&mut self, r: ResourceId, id: ProcessId) {
+        let batch = match self.resources[r].batch.as_mut() {
+            Some(batch) => batch,
+            None => return,
+        };
+        if let Some((seq, process)) = batch.timer {
+            if process == id {
+                batch.timer = None;
+                self.batch_timeouts.remove(&seq);
+                self.arm_batch(r);
+            }
+        }
+    }
+
+    /// Schedule the timeout of the batch being formed by the requests
+    /// waiting for resource `r`, if it is not scheduled yet.
+    fn arm_batch(&mut self, r: ResourceId) {
+        let res = &self.resources[r];
+        let batch = res.batch.as_ref().unwrap();
+        let first = match res.queue.front() {
+            Some(first) if batch.timer.is_none() && !batch.expired => first,
+            _ => return,
+        };
+        let (process, state) = (first.process, first.state.clone());
+        let time = self.time + batch.timeout * self.time_scale;
+        let seq = self.next_seq;
+        self.push_event(time, process, state, WakeReason::Scheduled, None);
+        // the timeout may have been dropped by the simulation
+        if self.next_seq != seq {
+            self.batch_timeouts.insert(seq, Some(r));
+            self.resources[r].batch.as_mut().unwrap().timer = Some((seq, process));
+        }
+    }
+
+    /// Disarm the timeout with sequence number `seq`, scheduled for
+    /// `process`. A queued timeout is not searched for, but left in the
+    /// queue and skipped when it is the earliest event.
+    fn disarm_batch(&mut self, seq: u64, process: ProcessId) {
+        self.cancel_event(seq);
+        if let Some(events) = self.suspended.get_mut(&process) {
+            let before = events.len();
+            events.retain(|(_, e)| e.seq != seq);
+            if events.len() != before {
+                self.batch_timeouts.remove(&seq);
+                return;
+            }
+        }
+        self.batch_timeouts.insert(seq, None);
+        self.drop_disarmed_timeouts();
+        self.track_pending_wakeups();
+    }
+}
+
+#[cfg(test)]
+mod tests;
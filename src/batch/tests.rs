@@ -0,0 +1,160 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, ProcessId, ResourceId, Simulation};
+
+/// Create processes arriving at `arrivals` that use `r` for `service`.
+fn customers(
+    s: &mut Simulation<Effect>,
+    r: ResourceId,
+    service: f64,
+    arrivals: &[f64],
+) -> Vec<ProcessId> {
+    let mut ids = Vec::new();
+    for &arrival in arrivals {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(service)).await;
+            handle.yield_state(Effect::Release(r)).await;
+        });
+        s.schedule_event(arrival, p, Effect::TimeOut(0.));
+        ids.push(p);
+    }
+    ids
+}
+
+#[test]
+fn full_batch() {
+    use crate::EndCondition::NoEvents;
+
+    let mut s = Simulation::new();
+    let oven = s.create_batch_resource(3, 3, 10.0);
+    let p = customers(&mut s, oven, 5.0, &[0.0, 1.0, 2.0, 3.0]);
+    let s = s.run(NoEvents);
+    // the last one waits alone from 3 until its timeout
+    assert_eq!(
+        s.resource_hold_log(oven),
+        &[
+            (2.0, 7.0, p[0]),
+            (2.0, 7.0, p[1]),
+            (2.0, 7.0, p[2]),
+            (13.0, 18.0, p[3])
+        ]
+    );
+    // the timeout is not logged
+    assert_eq!(s.processed_events().len(), 12);
+}
+
+#[test]
+fn partial_batch_on_timeout() {
+    use crate::{EndCondition::NoEvents, WakeReason};
+
+    let mut s = Simulation::new();
+    let oven = s.create_batch_resource(3, 3, 4.0);
+    let p = customers(&mut s, oven, 5.0, &[0.0, 1.0]);
+    let s = s.run(NoEvents);
+    // the timeout counts from the first request
+    assert_eq!(
+        s.resource_hold_log(oven),
+        &[(4.0, 9.0, p[0]), (4.0, 9.0, p[1])]
+    );
+    let waits: Vec<WakeReason> = s
+        .processed_events()
+        .iter()
+        .filter(|(_, state)| matches!(state, Effect::TimeOut(t) if *t == 5.0))
+        .map(|(e, _)| e.wake_reason())
+        .collect();
+    assert_eq!(
+        waits,
+        vec![
+            WakeReason::ResourceAcquired {
+                resource: oven,
+                unit: 0,
+                waited: 4.0
+            },
+            WakeReason::ResourceAcquired {
+                resource: oven,
+                unit: 1,
+                waited: 3.0
+            }
+        ]
+    );
+}
+
+#[test]
+fn timeout_while_busy() {
+    use crate::EndCondition::NoEvents;
+
+    let mut s = Simulation::new();
+    let oven = s.create_batch_resource(2, 2, 1.0);
+    // the third one is left over from the first batch, and its timeout
+    // elapses at 1 while the oven is in use
+    let p = customers(&mut s, oven, 5.0, &[0.0, 0.0, 0.0]);
+    let s = s.run(NoEvents);
+    assert_eq!(
+        s.resource_hold_log(oven),
+        &[(0.0, 5.0, p[0]), (0.0, 5.0, p[1]), (5.0, 10.0, p[2])]
+    );
+}
+
+#[test]
+fn complete_batch_disarms_timeout() {
+    use crate::EndCondition::NoEvents;
+
+    let mut s = Simulation::new();
+    let oven = s.create_batch_resource(2, 2, 100.0);
+    let p = customers(&mut s, oven, 1.0, &[0.0, 1.0]);
+    let s = s.run(NoEvents);
+    assert_eq!(
+        s.resource_hold_log(oven),
+        &[(1.0, 2.0, p[0]), (1.0, 2.0, p[1])]
+    );
+    // no timeout is left to run
+    assert_eq!(s.time(), 2.0);
+}
+
+#[test]
+fn killed_first_waiter() {
+    use crate::{EndCondition::NoEvents, Fault};
+
+    let mut s = Simulation::new();
+    let oven = s.create_batch_resource(3, 3, 4.0);
+    let p = customers(&mut s, oven, 5.0, &[0.0, 1.0]);
+    s.schedule_fault(2.0, Fault::KillProcess(p[0]));
+    let s = s.run(NoEvents);
+    // the timeout is armed again for the second one, when the first is killed
+    assert_eq!(s.resource_hold_log(oven), &[(6.0, 11.0, p[1])]);
+}
+
+#[test]
+fn disarmed_timeout_is_skipped() {
+    use crate::EndCondition::NoEvents;
+
+    let mut s = Simulation::new();
+    let oven = s.create_batch_resource(2, 2, 10.0);
+    let p = customers(&mut s, oven, 5.0, &[0.0, 1.0]);
+    s.step();
+    s.step();
+    // the batch started at 1, before the timeout armed at 0
+    let pending: Vec<_> = s
+        .peek_events(10)
+        .iter()
+        .map(|e| (e.time, e.process))
+        .collect();
+    assert_eq!(pending, vec![(1.0, p[0]), (1.0, p[1])]);
+    let s = s.run(NoEvents);
+    assert_eq!(s.time(), 6.0);
+    s.assert_no_pending();
+}
@@ -0,0 +1,123 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! A compact binary format for the event log.
+//!
+//! This module is only available with the `std` feature.
+
+use crate::{Event, SimState, Simulation, StaticProcess, WakeReason};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"DSIM";
+
+/// A state that can be written in fixed width by `export_binary`.
+pub trait BinarySimState: SimState + Sized {
+    /// The number of bytes of an encoded state.
+    const SIZE: usize;
+
+    /// Encode the state in `buf`, that is `SIZE` bytes long.
+    fn encode(&self, buf: &mut [u8]);
+
+    /// Decode a state encoded by `encode`, or return `None` if the
+    /// bytes are not a valid state.
+    fn decode(buf: &[u8]) -> Option<Self>;
+}
+
+impl<T: BinarySimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Write the log of processed events to `w` in a compact binary
+    /// format, that can be read back with `read_binary`.
+    ///
+    /// After a header, each event is a record of fixed width: the time as
+    /// an `f64`, the process as a `u32`, the `Effect::code()` of the logged
+    /// state as a `u8`, then the state that resumed the process and the
+    /// logged state, encoded by `BinarySimState`. All numbers are little
+    /// endian.
+    pub fn export_binary<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&(T::SIZE as u32).to_le_bytes())?;
+        let mut record = vec![0; 13 + 2 * T::SIZE];
+        for (event, state) in self.processed_events.iter() {
+            let process = u32::try_from(event.process).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Process {} does not fit in a record", event.process),
+                )
+            })?;
+            record[0..8].copy_from_slice(&event.time.to_le_bytes());
+            record[8..12].copy_from_slice(&process.to_le_bytes());
+            record[12] = state.effect().code();
+            event.state.encode(&mut record[13..13 + T::SIZE]);
+            state.encode(&mut record[13 + T::SIZE..]);
+            w.write_all(&record)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read a log of processed events written by `Simulation::export_binary`.
+///
+/// The events are ordered like in the log, that is also the order of
+/// their sequence numbers.
+pub fn read_binary<T: BinarySimState, R: Read>(mut r: R) -> io::Result<Vec<(Event<T>, T)>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut header = [0; 8];
+    r.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC {
+        return Err(invalid("Not a binary event log"));
+    }
+    if u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize != T::SIZE {
+        return Err(invalid("The states in the log have a different size"));
+    }
+    let mut events = Vec::new();
+    let mut record = vec![0; 13 + 2 * T::SIZE];
+    loop {
+        // the log can only end between two records
+        match r.read(&mut record[..1]) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+        r.read_exact(&mut record[1..]).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid("The log ends with a truncated record"),
+            _ => e,
+        })?;
+        let seq = events.len();
+        let mut time = [0; 8];
+        time.copy_from_slice(&record[0..8]);
+        let mut process = [0; 4];
+        process.copy_from_slice(&record[8..12]);
+        let decode = |buf: &[u8]| T::decode(buf).ok_or_else(|| invalid("Invalid state"));
+        let event = Event {
+            time: f64::from_le_bytes(time),
+            process: u32::from_le_bytes(process) as usize,
+            state: decode(&record[13..13 + T::SIZE])?,
+            key: (0, seq as u64),
+            seq: seq as u64,
+            wake: WakeReason::Scheduled,
+            label: None,
+        };
+        let state = decode(&record[13 + T::SIZE..])?;
+        if state.effect().code() != record[12] {
+            return Err(invalid("The effect of a state does not match its code"));
+        }
+        events.push((event, state));
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests;
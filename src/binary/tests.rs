@@ -0,0 +1,81 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{read_binary, BinarySimState, Effect, EndCondition::NoEvents, Simulation};
+
+/// Timeouts and traces, tagged with a number.
+impl BinarySimState for (Effect, u32) {
+    const SIZE: usize = 13;
+
+    fn encode(&self, buf: &mut [u8]) {
+        let (code, time) = match self.0 {
+            Effect::TimeOut(t) => (0, t),
+            Effect::Trace => (5, 0.0),
+            _ => unimplemented!(),
+        };
+        buf[0] = code;
+        buf[1..9].copy_from_slice(&time.to_le_bytes());
+        buf[9..13].copy_from_slice(&self.1.to_le_bytes());
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let mut time = [0; 8];
+        time.copy_from_slice(&buf[1..9]);
+        let mut tag = [0; 4];
+        tag.copy_from_slice(&buf[9..13]);
+        let effect = match buf[0] {
+            0 => Effect::TimeOut(f64::from_le_bytes(time)),
+            5 => Effect::Trace,
+            _ => return None,
+        };
+        Some((effect, u32::from_le_bytes(tag)))
+    }
+}
+
+#[test]
+fn binary_round_trip() {
+    let mut s = Simulation::new();
+    for i in 0..3 {
+        let p = s.create_async_process(move |handle| async move {
+            handle
+                .yield_state((Effect::TimeOut(0.5 + i as f64), i))
+                .await;
+            handle.yield_state((Effect::Trace, 10 + i)).await;
+        });
+        s.schedule_event(i as f64, p, (Effect::TimeOut(0.), 100));
+    }
+    let s = s.run(NoEvents);
+    let mut buf = Vec::new();
+    s.export_binary(&mut buf).unwrap();
+    assert_eq!(buf.len(), 8 + s.processed_events().len() * (13 + 2 * 13));
+
+    let events = read_binary::<(Effect, u32), _>(&buf[..]).unwrap();
+    assert_eq!(events.len(), s.processed_events().len());
+    let encoded = |(e, state): &(crate::Event<(Effect, u32)>, (Effect, u32))| {
+        let mut buf = [0; 26];
+        e.state().encode(&mut buf[..13]);
+        state.encode(&mut buf[13..]);
+        (e.time(), e.process(), buf)
+    };
+    assert!(events
+        .iter()
+        .zip(s.processed_events().iter())
+        .all(|(a, b)| encoded(a) == encoded(b)));
+
+    let truncated = read_binary::<(Effect, u32), _>(&buf[..buf.len() - 1]).unwrap_err();
+    assert_eq!(truncated.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(read_binary::<(Effect, u32), _>(&buf[..8]).unwrap().len(), 0);
+    assert!(read_binary::<(Effect, u32), _>(&b"CSV,time"[..]).is_err());
+}
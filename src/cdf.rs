@@ -0,0 +1,130 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Empirical cumulative distributions of samples.
+
+use crate::{ResourceId, SimState, Simulation, StaticProcess};
+use alloc::vec::Vec;
+
+/// The empirical cumulative distribution function of a set of samples,
+/// e.g. the times waited for a resource returned by
+/// `Simulation::wait_time_cdf`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cdf {
+    points: Vec<(f64, f64)>,
+}
+
+impl Cdf {
+    /// Compute the distribution of `samples`, that must not be NaN.
+    pub fn new(mut samples: Vec<f64>) -> Cdf {
+        assert!(
+            samples.iter().all(|x| !x.is_nan()),
+            "The samples of a distribution cannot be NaN"
+        );
+        samples.sort_by(f64::total_cmp);
+        let n = samples.len() as f64;
+        let mut points: Vec<(f64, f64)> = Vec::new();
+        for (i, &x) in samples.iter().enumerate() {
+            let p = (i + 1) as f64 / n;
+            match points.last_mut() {
+                // equal samples are a single step of the function
+                Some(last) if last.0 == x => last.1 = p,
+                _ => points.push((x, p)),
+            }
+        }
+        Cdf { points }
+    }
+
+    /// Returns the distinct values of the samples in increasing order, each
+    /// with the fraction of the samples that are not greater than it. The
+    /// last fraction is 1, unless there are no samples.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Returns the fraction of the samples that are not greater than `x`.
+    pub fn probability(&self, x: f64) -> f64 {
+        match self.points.iter().rposition(|&(value, _)| value <= x) {
+            Some(i) => self.points[i].1,
+            None => 0.0,
+        }
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Start or stop recording the time waited in queue by each granted
+    /// request of resource `id`, for `wait_time_cdf`.
+    ///
+    /// The samples are not recorded by default, since they grow with the
+    /// number of grants. Stopping the recording drops the samples.
+    pub fn record_wait_samples(&mut self, id: ResourceId, record: bool) {
+        let waits = &mut self.resources[id].waits;
+        match (record, waits.is_some()) {
+            (true, false) => *waits = Some(Vec::new()),
+            (false, true) => *waits = None,
+            _ => {}
+        }
+    }
+
+    /// Returns the distribution of the times waited in queue by the
+    /// requests of resource `id` granted while recording the samples with
+    /// `record_wait_samples`, as the points of its `Cdf`. It has no points
+    /// if the samples are not recorded.
+    pub fn wait_time_cdf(&self, id: ResourceId) -> Vec<(f64, f64)> {
+        match &self.resources[id].waits {
+            Some(waits) => Cdf::new(waits.clone()).points,
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cdf;
+    use crate::{Effect, Simulation, Step};
+
+    #[test]
+    fn cdf_points() {
+        let cdf = Cdf::new(vec![3.0, 1.0, 2.0, 2.0, 0.5]);
+        assert_eq!(
+            cdf.points(),
+            &[(0.5, 0.2), (1.0, 0.4), (2.0, 0.8), (3.0, 1.0)]
+        );
+        assert!(cdf
+            .points()
+            .windows(2)
+            .all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+        assert_eq!(cdf.probability(0.0), 0.0);
+        assert_eq!(cdf.probability(2.5), 0.8);
+        assert_eq!(cdf.probability(10.0), 1.0);
+        assert!(Cdf::new(Vec::new()).points().is_empty());
+    }
+
+    #[test]
+    fn wait_samples_are_opt_in() {
+        let mut s = Simulation::<Effect>::new();
+        let r = s.create_resource(2);
+        let p = s.create_sm_process(Box::new(move |_| Step::Yield(Effect::Request(r))));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+        s.step();
+        assert!(s.wait_time_cdf(r).is_empty());
+        s.record_wait_samples(r, true);
+        s.step();
+        s.step();
+        assert_eq!(s.wait_time_cdf(r), vec![(0.0, 1.0)]);
+        s.record_wait_samples(r, false);
+        assert!(s.wait_time_cdf(r).is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Events scheduled to occur after other events were processed.
+
+use crate::{Event, ProcessId, SimState, Simulation, StaticProcess, WakeReason};
+
+/// Identifies an event scheduled by the owner of a `Simulation`, returned
+/// by `schedule_event`, `schedule_event_at` and `schedule_event_after`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EventId(pub(crate) u64);
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Schedule a process to be executed `delay` time instants after the
+    /// event `after` is processed, rather than at a fixed time.
+    ///
+    /// If `after` was already processed, the event is scheduled `delay`
+    /// time instants from now. If `after` is dropped without being
+    /// processed, e.g. because its process was killed by a `Fault`, the
+    /// event is dropped too, together with the events depending on it, and
+    /// recorded in `dropped_dependents`.
+    ///
+    /// Since an event can only depend on an event scheduled before it,
+    /// the dependencies never form a cycle.
+    pub fn schedule_event_after(
+        &mut self,
+        after: EventId,
+        delay: f64,
+        process: ProcessId,
+        state: T,
+    ) -> EventId {
+        assert!(
+            delay >= 0.0 && delay.is_finite(),
+            "The delay {} after event {} is not finite and non-negative",
+            delay,
+            after.0
+        );
+        assert!(
+            after.0 < self.next_seq,
+            "Event {} was not scheduled by this simulation",
+            after.0
+        );
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        // the time of a dependent event is relative to its predecessor
+        let event = Event {
+            time: delay,
+            process,
+            state,
+            key: (0, seq),
+            seq,
+            wake: WakeReason::Scheduled,
+            label: None,
+        };
+        if self.canceled.contains(&after.0) {
+            self.drop_dependent(event);
+        } else if self.is_pending(after.0) {
+            self.dependents.entry(after.0).or_default().push(event);
+        } else {
+            self.release_dependent(event);
+        }
+        EventId(seq)
+    }
+
+    /// Returns the events scheduled with `schedule_event_after` that were
+    /// dropped because the event they depended on was, each with the time
+    /// when it was dropped.
+    pub fn dropped_dependents(&self) -> &[Event<T>] {
+        &self.dropped_dependents
+    }
+
+    /// Returns the id of the event scheduled by the owner with the sequence
+    /// number `seq`, marking it as dropped if it was not queued.
+    pub(crate) fn event_id(&mut self, seq: u64) -> EventId {
+        if self.next_seq == seq {
+            self.next_seq += 1;
+            self.canceled.insert(seq);
+        }
+        EventId(seq)
+    }
+
+    /// Whether the event with the sequence number `seq` is still to be
+    /// processed.
+    fn is_pending(&self, seq: u64) -> bool {
+        self.pending_events().any(|e| e.seq == seq)
+            || self.dependents.values().flatten().any(|e| e.seq == seq)
+            || self.suspended.values().flatten().any(|(_, e)| e.seq == seq)
+            || self
+                .resources
+                .iter()
+                .any(|res| res.preempted.values().flatten().any(|(_, e)| e.seq == seq))
+    }
+
+    /// Schedule the events depending on the event with the sequence number
+    /// `seq`, that was just processed.
+    pub(crate) fn release_dependents(&mut self, seq: u64) {
+        if let Some(events) = self.dependents.remove(&seq) {
+            for event in events {
+                self.release_dependent(event);
+            }
+        }
+    }
+
+    /// Move the events depending on the event with the sequence number
+    /// `old`, that was queued again with the sequence number `new`, or
+    /// drop them if it was not queued.
+    pub(crate) fn forward_dependents(&mut self, old: u64, new: Option<u64>) {
+        match new {
+            Some(new) => {
+                if let Some(events) = self.dependents.remove(&old) {
+                    self.dependents.entry(new).or_default().extend(events);
+                }
+            }
+            None => self.cancel_event(old),
+        }
+    }
+
+    /// Record that the event with the sequence number `seq` was dropped,
+    /// dropping the events depending on it.
+    pub(crate) fn cancel_event(&mut self, seq: u64) {
+        self.canceled.insert(seq);
+        if let Some(events) = self.dependents.remove(&seq) {
+            for event in events {
+                self.drop_dependent(event);
+            }
+        }
+    }
+
+    fn release_dependent(&mut self, event: Event<T>) {
+        let Event {
+            time: delay,
+            process,
+            state,
+            seq,
+            ..
+        } = event;
+        let time = self.time + delay;
+        if !self.push_event_with_seq(time, process, state, WakeReason::Scheduled, None, seq) {
+            self.cancel_event(seq);
+        }
+    }
+
+    fn drop_dependent(&mut self, event: Event<T>) {
+        let seq = event.seq;
+        self.dropped_dependents.push(Event {
+            time: self.time,
+            ..event
+        });
+        self.cancel_event(seq);
+    }
+}
+
+#[cfg(test)]
+mod tests;
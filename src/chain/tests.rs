@@ -0,0 +1,76 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NSteps, EndCondition::NoEvents, Fault, Simulation};
+
+#[test]
+fn chained_events() {
+    let mut s = Simulation::new();
+    let mut processes = Vec::new();
+    for _ in 0..3 {
+        let p = s.create_async_process(move |handle| async move {
+            loop {
+                handle.yield_state(Effect::Wait).await;
+            }
+        });
+        processes.push(p);
+    }
+    let (a, b, c) = (processes[0], processes[1], processes[2]);
+    let first = s.schedule_event(2.0, a, Effect::TimeOut(0.));
+    // scheduled before the event it depends on, but processed after it
+    let second = s.schedule_event_after(first, 0.0, b, Effect::TimeOut(0.));
+    s.schedule_event(1.0, b, Effect::Trace);
+    s.schedule_event_after(second, 1.5, c, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+    let order: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect();
+    assert_eq!(order, vec![(1.0, b), (2.0, a), (2.0, b), (3.5, c)]);
+}
+
+#[test]
+fn after_processed_and_dropped() {
+    let mut s = Simulation::new();
+    let a = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    let b = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    let early = s.schedule_event(1.0, a, Effect::TimeOut(0.));
+    let late = s.schedule_event(10.0, b, Effect::TimeOut(0.));
+    s.schedule_fault(5.0, Fault::KillProcess(b));
+    s.schedule_event(6.0, a, Effect::Trace);
+    let mut s = s.run(NSteps(1));
+    // the dependency was processed already, the event is scheduled from now
+    s.schedule_event_after(early, 2.0, a, Effect::TimeOut(0.));
+    // dropped when b is killed, with the event depending on it
+    let dropped = s.schedule_event_after(late, 0.0, a, Effect::TimeOut(0.));
+    s.schedule_event_after(dropped, 0.0, a, Effect::TimeOut(0.));
+    let mut s = s.run(NoEvents);
+    // the dependency was dropped already
+    s.schedule_event_after(late, 0.0, a, Effect::TimeOut(0.));
+
+    let times: Vec<_> = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(times, vec![1.0, 3.0, 6.0]);
+    let dropped: Vec<_> = s.dropped_dependents().iter().map(|e| e.time()).collect();
+    assert_eq!(dropped, vec![5.0, 5.0, 6.0]);
+}
@@ -0,0 +1,198 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Typed channels passing messages between processes.
+//!
+//! `Effect` is not generic, so the messages travel next to the effects:
+//! `Channel::send` stages the message before the process yields
+//! `Effect::Send`, and the receiver finds it in the `SimContext` it is
+//! resumed with after yielding `Effect::Recv`.
+
+use crate::{ChannelId, Effect, Event, ProcessId, SimContext, SimState, Simulation, StaticProcess};
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// A message sent through a channel, of a type known only to the
+/// processes using the channel.
+#[derive(Clone)]
+pub(crate) struct Message(Rc<dyn Any>);
+
+impl fmt::Debug for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Message(..)")
+    }
+}
+
+/// The message staged by the running process, until it yields
+/// `Effect::Send`.
+type Outbox = Rc<RefCell<Option<Message>>>;
+
+/// The typed handle of a channel, returned by `Simulation::create_channel`,
+/// used by processes to send messages of type `M`.
+pub struct Channel<M> {
+    id: ChannelId,
+    outbox: Outbox,
+    message: PhantomData<M>,
+}
+
+impl<M> Clone for Channel<M> {
+    fn clone(&self) -> Self {
+        Channel {
+            id: self.id,
+            outbox: self.outbox.clone(),
+            message: PhantomData,
+        }
+    }
+}
+
+impl<M: 'static> Channel<M> {
+    /// Returns the identifier of the channel.
+    pub fn id(&self) -> ChannelId {
+        self.id
+    }
+
+    /// Stage `message` and return the `Effect::Send` that the process must
+    /// yield right away to send it. The process is resumed once the message
+    /// is in the channel or taken by a receiver.
+    pub fn send(&self, message: M) -> Effect {
+        let mut outbox = self.outbox.borrow_mut();
+        assert!(
+            outbox.is_none(),
+            "A message was staged on channel {} without yielding its Effect::Send",
+            self.id
+        );
+        *outbox = Some(Message(Rc::new(message)));
+        Effect::Send(self.id)
+    }
+
+    /// Returns the `Effect::Recv` that a process yields to receive a
+    /// message, resuming it once one is available.
+    pub fn recv(&self) -> Effect {
+        Effect::Recv(self.id)
+    }
+
+    /// Returns the message received by a process resumed with `ctx` after
+    /// yielding `Effect::Recv` on this channel.
+    pub fn received<'a, T>(&self, ctx: &'a SimContext<T>) -> Option<&'a M> {
+        ctx.message.as_ref().and_then(|m| m.0.downcast_ref())
+    }
+}
+
+pub(crate) struct ChannelState<T> {
+    capacity: usize,
+    outbox: Outbox,
+    buffer: VecDeque<Message>,
+    /// Senders waiting for room in the channel, with their messages
+    senders: VecDeque<(Event<T>, Message)>,
+    /// Receivers waiting for a message
+    receivers: VecDeque<Event<T>>,
+}
+
+impl<T> ChannelState<T> {
+    /// Drop the sends and receives of `process` waiting on the channel.
+    pub(crate) fn remove_waiter(&mut self, process: ProcessId) {
+        self.senders.retain(|(e, _)| e.process != process);
+        self.receivers.retain(|e| e.process != process);
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a channel holding up to `capacity` messages of type `M`.
+    /// With a capacity of 0 each sender waits for a receiver to take
+    /// its message.
+    ///
+    /// Returns the typed handle of the channel, to be moved into the
+    /// processes using it.
+    pub fn create_channel<M: 'static>(&mut self, capacity: usize) -> Channel<M> {
+        let outbox = Outbox::default();
+        self.channels.push(ChannelState {
+            capacity,
+            outbox: outbox.clone(),
+            buffer: VecDeque::new(),
+            senders: VecDeque::new(),
+            receivers: VecDeque::new(),
+        });
+        Channel {
+            id: self.channels.len() - 1,
+            outbox,
+            message: PhantomData,
+        }
+    }
+
+    pub(crate) fn send_message(&mut self, id: ChannelId, event: Event<T>) {
+        let chan = &mut self.channels[id];
+        let message = chan.outbox.borrow_mut().take().unwrap_or_else(|| {
+            panic!(
+                "Process {} yielded Effect::Send on channel {} without staging a message with Channel::send",
+                event.process, id
+            )
+        });
+        if let Some(receiver) = chan.receivers.pop_front() {
+            self.deliver_message(receiver, message);
+        } else if chan.buffer.len() < chan.capacity {
+            chan.buffer.push_back(message);
+        } else {
+            chan.senders.push_back((event, message));
+            return;
+        }
+        self.schedule_at(self.time, event.process, event.state);
+    }
+
+    pub(crate) fn recv_message(&mut self, id: ChannelId, event: Event<T>) {
+        let chan = &mut self.channels[id];
+        let message = match chan.buffer.pop_front() {
+            Some(message) => {
+                // make room for the first waiting sender
+                if let Some((sender, pending)) = chan.senders.pop_front() {
+                    chan.buffer.push_back(pending);
+                    self.schedule_at(self.time, sender.process, sender.state);
+                }
+                message
+            }
+            None => match chan.senders.pop_front() {
+                Some((sender, message)) => {
+                    self.schedule_at(self.time, sender.process, sender.state);
+                    message
+                }
+                None => {
+                    chan.receivers.push_back(event);
+                    return;
+                }
+            },
+        };
+        self.deliver_message(event, message);
+    }
+
+    /// Resume a receiver, that finds `message` in its context.
+    fn deliver_message(&mut self, receiver: Event<T>, message: Message) {
+        self.deliveries.insert(receiver.process, message);
+        self.schedule_at(self.time, receiver.process, receiver.state);
+    }
+
+    /// Take the message delivered to `process`, if any, when it is resumed.
+    pub(crate) fn take_delivery(&mut self, process: ProcessId) -> Option<Message> {
+        if self.deliveries.is_empty() {
+            return None;
+        }
+        self.deliveries.remove(&process)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,62 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NoEvents, Simulation};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn producer_consumer() {
+    let mut s = Simulation::new();
+    let chan = s.create_channel::<String>(1);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let tx = chan.clone();
+    let producer = s.create_async_process(move |handle| async move {
+        for i in 0..3 {
+            handle.yield_state(tx.send(format!("message {}", i))).await;
+        }
+    });
+    let rx = chan;
+    let log = received.clone();
+    let consumer = s.create_async_process(move |handle| async move {
+        for _ in 0..3 {
+            let ctx = handle.yield_state(rx.recv()).await;
+            let message = rx.received(&ctx).cloned();
+            log.borrow_mut().push((ctx.time(), message.unwrap()));
+            handle.yield_state(Effect::TimeOut(2.0)).await;
+        }
+    });
+    s.schedule_event(0.0, producer, Effect::TimeOut(0.));
+    s.schedule_event(1.0, consumer, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+
+    // the producer fills the channel and waits for the consumer to make
+    // room for each following message
+    assert_eq!(
+        *received.borrow(),
+        vec![
+            (1.0, "message 0".to_string()),
+            (3.0, "message 1".to_string()),
+            (5.0, "message 2".to_string())
+        ]
+    );
+    let sends: Vec<f64> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, y)| e.process() == producer && matches!(y, Effect::Send(_)))
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(sends, vec![0.0, 0.0, 1.0]);
+}
@@ -0,0 +1,151 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Real-world timestamps for the simulation time, with `chrono`.
+
+use alloc::string::String;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+
+/// Maps the simulation time to UTC timestamps, so that simulations fed
+/// with timestamped data can schedule events and read the log in terms of
+/// dates instead of time units since the start of the scenario.
+///
+/// The simulation time `0` is the `epoch` of the clock, and each time
+/// unit lasts `seconds_per_unit` seconds:
+///
+/// ```
+/// use chrono::{Duration, TimeZone, Utc};
+/// use desim::{DateTimeClock, Effect, EndCondition, Simulation};
+/// let epoch = Utc.with_ymd_and_hms(2024, 1, 1, 8, 0, 0).unwrap();
+/// let clock = DateTimeClock::new(epoch, 60.0);
+///
+/// let mut sim = Simulation::new();
+/// let p = sim.create_async_process(move |handle| async move {
+///     handle.timeout(clock.duration(Duration::hours(1))).await;
+/// });
+/// let arrival = Utc.with_ymd_and_hms(2024, 1, 1, 8, 30, 0).unwrap();
+/// sim.schedule_event_at(clock.to_sim_time(arrival), p, Effect::TimeOut(0.0));
+/// let sim = sim.run(EndCondition::NoEvents);
+/// assert_eq!(clock.to_datetime(sim.time()), arrival + Duration::hours(1));
+/// ```
+///
+/// The log can be printed with the timestamps of the clock setting
+/// `TimeDisplay::DateTime` with `Simulation::set_time_display`.
+///
+/// The timestamps are in UTC, where the arithmetic is not affected by
+/// daylight saving time: local times should be converted to UTC before
+/// being passed to the clock.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DateTimeClock {
+    epoch: DateTime<Utc>,
+    seconds_per_unit: f64,
+}
+
+impl DateTimeClock {
+    /// Create a clock whose simulation time `0` is `epoch`, and whose time
+    /// units last `seconds_per_unit` seconds.
+    pub fn new(epoch: DateTime<Utc>, seconds_per_unit: f64) -> DateTimeClock {
+        assert!(
+            seconds_per_unit > 0.0 && seconds_per_unit.is_finite(),
+            "The seconds in a time unit must be positive and finite, got {}",
+            seconds_per_unit
+        );
+        DateTimeClock {
+            epoch,
+            seconds_per_unit,
+        }
+    }
+
+    /// Returns the timestamp of the simulation time `0`.
+    pub fn epoch(&self) -> DateTime<Utc> {
+        self.epoch
+    }
+
+    /// Returns the simulation time corresponding to the timestamp `time`,
+    /// e.g. to pass it to `Simulation::schedule_event_at` or to
+    /// `EndCondition::Time`.
+    pub fn to_sim_time(&self, time: DateTime<Utc>) -> f64 {
+        self.duration(time.signed_duration_since(self.epoch))
+    }
+
+    /// Returns the timestamp corresponding to the simulation time `time`,
+    /// rounded to the nanosecond.
+    pub fn to_datetime(&self, time: f64) -> DateTime<Utc> {
+        let nanos = time * self.seconds_per_unit * 1e9;
+        // round to the nearest nanosecond, without the float functions of std
+        let nanos = if nanos < 0.0 {
+            nanos - 0.5
+        } else {
+            nanos + 0.5
+        } as i64;
+        self.epoch + Duration::nanoseconds(nanos)
+    }
+
+    /// Returns the number of time units lasting `duration`, e.g. to yield
+    /// it in an `Effect::TimeOut`.
+    pub fn duration(&self, duration: Duration) -> f64 {
+        let nanos = duration
+            .num_nanoseconds()
+            .expect("The duration cannot be represented in nanoseconds");
+        nanos as f64 / 1e9 / self.seconds_per_unit
+    }
+
+    /// Format the simulation time `time` as an RFC 3339 timestamp.
+    pub fn format(&self, time: f64) -> String {
+        self.to_datetime(time)
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DateTimeClock;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn conversions() {
+        let epoch = Utc.with_ymd_and_hms(2024, 3, 31, 0, 0, 0).unwrap();
+        let clock = DateTimeClock::new(epoch, 60.0);
+        // across the night the clocks go forward in most of Europe
+        let later = Utc.with_ymd_and_hms(2024, 3, 31, 6, 30, 0).unwrap();
+        assert_eq!(clock.to_sim_time(later), 390.0);
+        assert_eq!(clock.to_datetime(390.0), later);
+        assert_eq!(clock.duration(Duration::hours(2)), 120.0);
+        assert_eq!(clock.to_datetime(-0.5), epoch + Duration::seconds(-30));
+        assert_eq!(clock.format(390.0), "2024-03-31T06:30:00Z");
+        assert_eq!(clock.format(0.01), "2024-03-31T00:00:00.600Z");
+    }
+
+    #[test]
+    fn log_timestamps() {
+        use crate::{Effect, EndCondition::NoEvents, Simulation, TimeDisplay};
+
+        let epoch = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let clock = DateTimeClock::new(epoch, 1.0);
+        let mut s = Simulation::new();
+        let p = s.create_async_process(move |handle| async move {
+            handle.timeout(clock.duration(Duration::minutes(90))).await;
+            handle.wait().await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        let mut s = s.run(NoEvents);
+        s.set_time_display(TimeDisplay::DateTime(clock));
+        assert_eq!(
+            s.dump_log(),
+            "2024-01-01T00:00:00Z\tprocess 0\tTimeOut(5400.0)\n\
+             2024-01-01T01:30:00Z\tprocess 0\tWait\n"
+        );
+    }
+}
@@ -0,0 +1,62 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Export of the event log in the Apache Arrow columnar format.
+//!
+//! This module is only available with the `arrow` feature.
+
+use crate::{SimState, Simulation, StaticProcess};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use arrow::array::{ArrayRef, Float64Array, UInt32Array, UInt64Array, UInt8Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Convert the log of processed events into an Arrow `RecordBatch`.
+    ///
+    /// The batch has one row per logged event and four columns:
+    /// `time` (`Float64`), `process` (`UInt64`), `effect` (`UInt8`),
+    /// being the `Effect::code()` of the logged state, and `label`
+    /// (`UInt32`), the index of the label of the event, null if it has none.
+    /// The names of the labels are returned by `Simulation::label_name`.
+    pub fn export_arrow(&self) -> RecordBatch {
+        let schema = Schema::new(vec![
+            Field::new("time", DataType::Float64, false),
+            Field::new("process", DataType::UInt64, false),
+            Field::new("effect", DataType::UInt8, false),
+            Field::new("label", DataType::UInt32, true),
+        ]);
+        let events = self.processed_events.iter();
+        let time: Vec<f64> = events.clone().map(|(e, _)| e.time).collect();
+        let process: Vec<u64> = events.clone().map(|(e, _)| e.process as u64).collect();
+        let effect: Vec<u8> = events.clone().map(|(_, s)| s.effect().code()).collect();
+        let label: Vec<Option<u32>> = events.map(|(e, _)| e.label.map(|l| l.index())).collect();
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![
+                Arc::new(Float64Array::from(time)) as ArrayRef,
+                Arc::new(UInt64Array::from(process)) as ArrayRef,
+                Arc::new(UInt8Array::from(effect)) as ArrayRef,
+                Arc::new(UInt32Array::from(label)) as ArrayRef,
+            ],
+        )
+        .expect("columns match the schema")
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,55 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+#[test]
+fn export_arrow() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+    use arrow::datatypes::DataType;
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(3.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(2.0, p2, Effect::TimeOut(2.));
+    let s = s.run(NoEvents);
+
+    let batch = s.export_arrow();
+    assert_eq!(batch.num_rows(), s.processed_events().len());
+    assert_eq!(batch.num_rows(), 6);
+    let schema = batch.schema();
+    let fields: Vec<_> = schema
+        .fields()
+        .iter()
+        .map(|f| (f.name().as_str(), f.data_type().clone()))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("time", DataType::Float64),
+            ("process", DataType::UInt64),
+            ("effect", DataType::UInt8),
+            ("label", DataType::UInt32),
+        ]
+    );
+}
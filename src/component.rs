@@ -0,0 +1,278 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Reusable blocks of a model.
+//!
+//! A `Component` creates its own processes and resources in a simulation
+//! when it is registered, and returns handles to them by name, so that
+//! other parts of the model can be wired to it. By convention, the labels
+//! of the events of a component are interned with
+//! `Simulation::intern_scoped_label` in the scope of its name, so that
+//! they can be told apart in the log when several components are used.
+//!
+//! `ServerStation` and `Breakdowns` are two simple components, that can be
+//! composed and followed as a pattern.
+
+use crate::network::{BatchSize, Delay, NetworkBuilder, Router, Source, Station};
+use crate::{Effect, LabelId, ProcessId, ResourceId, SimContext, SimState, Simulation, Step};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+
+/// A block of a model, that creates its processes and resources when it
+/// is registered in a simulation.
+pub trait Component<T: SimState + Clone> {
+    /// Returns the name of the component, used as the scope of its labels.
+    fn name(&self) -> &'static str;
+
+    /// Create the processes and the resources of the component in `sim`,
+    /// returning the handles to the ones other components can be wired to.
+    fn register(&mut self, sim: &mut Simulation<T>) -> ComponentHandles;
+}
+
+/// A handle returned by a component.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Handle {
+    /// A process of the component
+    Process(ProcessId),
+    /// A resource of the component
+    Resource(ResourceId),
+    /// A label of the events of the component
+    Label(LabelId),
+}
+
+/// The handles returned by `Component::register`, by name.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentHandles {
+    handles: BTreeMap<&'static str, Handle>,
+}
+
+impl ComponentHandles {
+    /// Create an empty set of handles.
+    pub fn new() -> ComponentHandles {
+        ComponentHandles::default()
+    }
+
+    /// Add a handle called `name`, replacing the one with the same name.
+    pub fn insert(&mut self, name: &'static str, handle: Handle) {
+        self.handles.insert(name, handle);
+    }
+
+    /// Returns the handle called `name`, if any.
+    pub fn get(&self, name: &str) -> Option<Handle> {
+        self.handles.get(name).copied()
+    }
+
+    /// Iterate over the handles in the order of their names.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Handle)> + '_ {
+        self.handles.iter().map(|(&name, &handle)| (name, handle))
+    }
+
+    /// Returns the process called `name`.
+    ///
+    /// Panics if there is no process with that name.
+    pub fn process(&self, name: &str) -> ProcessId {
+        match self.get(name) {
+            Some(Handle::Process(id)) => id,
+            _ => panic!("The component has no process called {}", name),
+        }
+    }
+
+    /// Returns the resource called `name`.
+    ///
+    /// Panics if there is no resource with that name.
+    pub fn resource(&self, name: &str) -> ResourceId {
+        match self.get(name) {
+            Some(Handle::Resource(id)) => id,
+            _ => panic!("The component has no resource called {}", name),
+        }
+    }
+
+    /// Returns the label called `name`.
+    ///
+    /// Panics if there is no label with that name.
+    pub fn label(&self, name: &str) -> LabelId {
+        match self.get(name) {
+            Some(Handle::Label(id)) => id,
+            _ => panic!("The component has no label called {}", name),
+        }
+    }
+}
+
+impl<T: SimState + Clone> Simulation<T> {
+    /// Returns the id of the label `scope.name`, interning it if it is new,
+    /// like `intern_label`.
+    ///
+    /// The names of the labels are never freed, but interning the same
+    /// name again does not allocate.
+    pub fn intern_scoped_label(&mut self, scope: &'static str, name: &'static str) -> LabelId {
+        let scoped = format!("{}.{}", scope, name);
+        match self.labels.iter().find(|&&l| l == scoped) {
+            Some(&l) => self.intern_label(l),
+            None => self.intern_label(Box::leak(scoped.into_boxed_str())),
+        }
+    }
+}
+
+/// A station of servers with its own arrivals: each customer requests a
+/// server, holds it for the service time and releases it.
+///
+/// Its handles are the resource `server` and the process `source`.
+pub struct ServerStation {
+    /// Name of the component
+    pub name: &'static str,
+    /// Number of servers
+    pub servers: usize,
+    /// The time it takes to serve a customer
+    pub service: Delay,
+    /// The time between two arrivals
+    pub interarrival: Delay,
+    /// Number of customers arriving
+    pub customers: usize,
+}
+
+impl<T: SimState + Clone + From<Effect> + 'static> Component<T> for ServerStation {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn register(&mut self, sim: &mut Simulation<T>) -> ComponentHandles {
+        let mut net = NetworkBuilder::new();
+        let station = net.station(Station {
+            servers: self.servers,
+            service: self.service,
+        });
+        net.source(Source {
+            interarrival: self.interarrival,
+            batch: BatchSize::Fixed(1),
+            router: Router::to(station),
+            entities: self.customers,
+        });
+        let net = net.build(sim);
+        let mut handles = ComponentHandles::new();
+        handles.insert("server", Handle::Resource(net.resource(station)));
+        handles.insert("source", Handle::Process(net.sources()[0]));
+        handles
+    }
+}
+
+/// Breakdowns of a resource: after each up time a unit of the resource
+/// is requested, and held until it is repaired, as soon as it is not
+/// in use anymore.
+///
+/// The failures and the repairs are marked in the log by events with the
+/// labels `failed` and `repaired`, in the scope of the name of the
+/// component. Its handles are the process `breakdowns` and the two labels.
+pub struct Breakdowns {
+    /// Name of the component
+    pub name: &'static str,
+    /// The resource that breaks down
+    pub resource: ResourceId,
+    /// The time between a repair and the next breakdown
+    pub up: Delay,
+    /// The time it takes to repair the resource
+    pub down: Delay,
+    /// Number of breakdowns
+    pub cycles: usize,
+}
+
+impl<T: SimState + Clone + From<Effect> + 'static> Component<T> for Breakdowns {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn register(&mut self, sim: &mut Simulation<T>) -> ComponentHandles {
+        let failed = sim.intern_scoped_label(self.name, "failed");
+        let repaired = sim.intern_scoped_label(self.name, "repaired");
+        let cycle = [
+            self.up.effect(),
+            Effect::Request(self.resource),
+            Effect::LabeledTimeOut {
+                time: 0.0,
+                label: failed,
+            },
+            self.down.effect(),
+            Effect::LabeledTimeOut {
+                time: 0.0,
+                label: repaired,
+            },
+            Effect::Release(self.resource),
+        ];
+        let steps = self.cycles * cycle.len();
+        let mut step = 0;
+        let p = sim.create_sm_process(Box::new(move |_: SimContext<T>| {
+            if step == steps {
+                return Step::Done;
+            }
+            step += 1;
+            Step::Yield(T::from(cycle[(step - 1) % cycle.len()]))
+        }));
+        sim.schedule_event(0.0, p, T::from(Effect::TimeOut(0.0)));
+        let mut handles = ComponentHandles::new();
+        handles.insert("breakdowns", Handle::Process(p));
+        handles.insert("failed", Handle::Label(failed));
+        handles.insert("repaired", Handle::Label(repaired));
+        handles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Breakdowns, Component, Handle, ServerStation};
+    use crate::network::Delay;
+    use crate::{Effect, EndCondition, Simulation};
+
+    #[test]
+    fn compose_components() {
+        let mut sim = Simulation::<Effect>::new();
+        let station = ServerStation {
+            name: "station",
+            servers: 1,
+            service: Delay::Fixed(1.0),
+            interarrival: Delay::Fixed(1.0),
+            customers: 10,
+        }
+        .register(&mut sim);
+        let server = station.resource("server");
+        let crew = Breakdowns {
+            name: "crew",
+            resource: server,
+            up: Delay::Fixed(2.5),
+            down: Delay::Fixed(2.0),
+            cycles: 1,
+        }
+        .register(&mut sim);
+        assert_eq!(
+            crew.get("failed"),
+            Some(Handle::Label(crew.label("failed")))
+        );
+        assert_eq!(sim.label_name(crew.label("repaired")), "crew.repaired");
+        assert_eq!(
+            sim.intern_scoped_label("crew", "failed"),
+            crew.label("failed")
+        );
+
+        let sim = sim.run(EndCondition::NoEvents);
+        // the server breaks down when the third customer leaves, delaying
+        // the other ones by the 2 time units of the repair
+        let p = crew.process("breakdowns");
+        assert!(sim.resource_hold_log(server).contains(&(3.0, 5.0, p)));
+        let times =
+            |label| -> Vec<f64> { sim.log().with_label(label).map(|(e, _)| e.time).collect() };
+        assert_eq!(times(crew.label("failed")), vec![3.0]);
+        assert_eq!(times(crew.label("repaired")), vec![5.0]);
+        assert_eq!(sim.time(), 12.0);
+    }
+}
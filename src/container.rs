@@ -0,0 +1,203 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Containers of a continuous quantity, with exact arithmetic.
+
+use crate::{ContainerId, Event, ProcessId, SimState, Simulation, StaticProcess};
+use alloc::collections::VecDeque;
+use core::fmt;
+use core::ops::{Add, Sub};
+
+/// A non-negative quantity in fixed point, with a resolution of `1e-9`.
+///
+/// Sums and differences of quantities are exact, so that e.g. a thousand
+/// puts of `0.001` fill a container of capacity `1` exactly, which is not
+/// the case adding `f64`s.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fixed(i64);
+
+impl Fixed {
+    /// The number of units of the resolution in a unit of quantity
+    pub const SCALE: i64 = 1_000_000_000;
+    /// The zero quantity
+    pub const ZERO: Fixed = Fixed(0);
+
+    /// Create a quantity of `units` times the resolution.
+    pub const fn from_units(units: i64) -> Fixed {
+        Fixed(units)
+    }
+
+    /// Create the quantity nearest to `x`.
+    pub fn from_f64(x: f64) -> Fixed {
+        let units = x * Fixed::SCALE as f64;
+        // round half away from zero, without the float functions of std
+        Fixed(if units < 0.0 {
+            units - 0.5
+        } else {
+            units + 0.5
+        } as i64)
+    }
+
+    /// Returns the quantity as a number of units of the resolution.
+    pub const fn units(self) -> i64 {
+        self.0
+    }
+
+    /// Returns the nearest `f64` to the quantity.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Fixed::SCALE as f64
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0 + other.0)
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0 - other.0)
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let units = self.0.unsigned_abs();
+        let scale = Fixed::SCALE as u64;
+        write!(f, "{}{}.{:09}", sign, units / scale, units % scale)
+    }
+}
+
+pub(crate) struct Container<T> {
+    capacity: Fixed,
+    level: Fixed,
+    /// Processes waiting to put an amount, in FIFO order
+    puts: VecDeque<(Fixed, Event<T>)>,
+    /// Processes waiting to get an amount, in FIFO order
+    gets: VecDeque<(Fixed, Event<T>)>,
+}
+
+impl<T> Container<T> {
+    /// Drop the puts and gets of `process` waiting on the container.
+    pub(crate) fn remove_waiter(&mut self, process: ProcessId) {
+        self.puts.retain(|(_, e)| e.process != process);
+        self.gets.retain(|(_, e)| e.process != process);
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a container holding up to `capacity` of a continuous
+    /// quantity, initially `level`. Processes add to it yielding
+    /// `Effect::Put`, waiting while it would overflow, and take from it
+    /// yielding `Effect::Get`, waiting while it does not hold enough.
+    ///
+    /// The level is kept in fixed point, so that it is exact.
+    ///
+    /// Returns the identifier of the container.
+    pub fn create_exact_container(&mut self, capacity: Fixed, level: Fixed) -> ContainerId {
+        assert!(
+            Fixed::ZERO <= level && level <= capacity,
+            "The initial level {} is not between 0 and the capacity {}",
+            level,
+            capacity
+        );
+        let id = self.containers.len();
+        self.containers.push(Container {
+            capacity,
+            level,
+            puts: VecDeque::new(),
+            gets: VecDeque::new(),
+        });
+        id
+    }
+
+    /// Returns the quantity held by container `id`.
+    pub fn container_level(&self, id: ContainerId) -> Fixed {
+        self.containers[id].level
+    }
+
+    pub(crate) fn put(&mut self, id: ContainerId, amount: Fixed, event: Event<T>) {
+        let container = &mut self.containers[id];
+        assert!(
+            Fixed::ZERO <= amount && amount <= container.capacity,
+            "Process {} put {} in container {} of capacity {}",
+            event.process,
+            amount,
+            id,
+            container.capacity
+        );
+        container.puts.push_back((amount, event));
+        self.grant_container(id);
+    }
+
+    /// Set the level of container `id`, resuming the processes that can
+    /// then complete their put or get.
+    pub(crate) fn set_container_level(&mut self, id: ContainerId, level: Fixed) {
+        let container = &mut self.containers[id];
+        assert!(
+            Fixed::ZERO <= level && level <= container.capacity,
+            "The level {} of container {} is not between 0 and the capacity {}",
+            level,
+            id,
+            container.capacity
+        );
+        container.level = level;
+        self.grant_container(id);
+    }
+
+    pub(crate) fn get(&mut self, id: ContainerId, amount: Fixed, event: Event<T>) {
+        let container = &mut self.containers[id];
+        assert!(
+            Fixed::ZERO <= amount && amount <= container.capacity,
+            "Process {} got {} from container {} of capacity {}",
+            event.process,
+            amount,
+            id,
+            container.capacity
+        );
+        container.gets.push_back((amount, event));
+        self.grant_container(id);
+    }
+
+    /// Resume the queued processes that can complete their put or get,
+    /// in FIFO order, until none of them can.
+    fn grant_container(&mut self, id: ContainerId) {
+        loop {
+            let container = &mut self.containers[id];
+            let (_, event) = match (container.puts.front(), container.gets.front()) {
+                (Some(&(amount, _)), _) if container.level + amount <= container.capacity => {
+                    container.level = container.level + amount;
+                    container.puts.pop_front().unwrap()
+                }
+                (_, Some(&(amount, _))) if amount <= container.level => {
+                    container.level = container.level - amount;
+                    container.gets.pop_front().unwrap()
+                }
+                _ => return,
+            };
+            self.schedule_at(self.time, event.process, event.state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
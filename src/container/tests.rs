@@ -0,0 +1,73 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NoEvents, Fixed, Simulation};
+
+#[test]
+fn exact_level() {
+    let mut s = Simulation::new();
+    let c = s.create_exact_container(Fixed::from_f64(1.0), Fixed::ZERO);
+    let amount = Fixed::from_f64(0.001);
+    let producer = s.create_async_process(move |handle| async move {
+        for _ in 0..1000 {
+            handle
+                .yield_state(Effect::Put {
+                    container: c,
+                    amount,
+                })
+                .await;
+        }
+    });
+    s.schedule_event(0.0, producer, Effect::TimeOut(0.));
+    let mut s = s.run(NoEvents);
+    assert_eq!(s.container_level(c), Fixed::from_f64(1.0));
+    // adding the amounts as floats leaves a residual error
+    assert_ne!((0..1000).map(|_| 0.001).sum::<f64>(), 1.0);
+
+    // the container is full, so this put waits for the consumer
+    let producer = s.create_async_process(move |handle| async move {
+        handle
+            .yield_state(Effect::Put {
+                container: c,
+                amount,
+            })
+            .await;
+    });
+    let consumer = s.create_async_process(move |handle| async move {
+        for _ in 0..1001 {
+            handle
+                .yield_state(Effect::Get {
+                    container: c,
+                    amount,
+                })
+                .await;
+        }
+    });
+    s.schedule_event(1.0, producer, Effect::TimeOut(0.));
+    s.schedule_event(2.0, consumer, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+    assert_eq!(s.container_level(c), Fixed::ZERO);
+    s.assert_no_pending();
+}
+
+#[test]
+fn fixed_display() {
+    assert_eq!(Fixed::from_f64(0.001).to_string(), "0.001000000");
+    assert_eq!(
+        Fixed::from_units(-1_500_000_000).to_string(),
+        "-1.500000000"
+    );
+    assert_eq!(Fixed::from_f64(2.5).to_f64(), 2.5);
+}
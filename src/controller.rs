@@ -0,0 +1,113 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Control of a simulation through a trait object, whatever its state type.
+
+use crate::{SimState, Simulation, StaticProcess, StopReason};
+
+/// The operations of a `Simulation` that do not depend on its state type,
+/// so that tools like debuggers and visualizers can drive any simulation
+/// through a `&mut dyn SimController`.
+pub trait SimController {
+    /// Returns the current simulation time.
+    fn time(&self) -> f64;
+
+    /// Process the next event, like `Simulation::step`.
+    fn step(&mut self);
+
+    /// Returns the number of steps processed so far.
+    fn steps(&self) -> usize;
+
+    /// Returns the number of events scheduled in the future.
+    fn events_pending(&self) -> usize;
+
+    /// Returns the number of processes created, including the completed
+    /// ones.
+    fn process_count(&self) -> usize;
+
+    /// Returns the number of processes that did not complete yet.
+    fn live_process_count(&self) -> usize;
+
+    /// Returns the reason the last call to `Simulation::run_until`
+    /// stopped with, if it was called.
+    fn stop_reason(&self) -> Option<&StopReason>;
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> SimController for Simulation<T, G> {
+    fn time(&self) -> f64 {
+        Simulation::time(self)
+    }
+
+    fn step(&mut self) {
+        Simulation::step(self)
+    }
+
+    fn steps(&self) -> usize {
+        self.steps
+    }
+
+    fn events_pending(&self) -> usize {
+        self.pending_event_count()
+    }
+
+    fn process_count(&self) -> usize {
+        self.processes.len()
+    }
+
+    fn live_process_count(&self) -> usize {
+        Simulation::live_process_count(self)
+    }
+
+    fn stop_reason(&self) -> Option<&StopReason> {
+        self.stop_reason.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SimController;
+    use crate::{Effect, SimContext, Simulation, Step, StopReason};
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn controller() {
+        let mut s = Simulation::new();
+        let mut ticks = 0;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            ticks += 1;
+            if ticks > 2 {
+                Step::Done
+            } else {
+                Step::Yield(Effect::TimeOut(1.5))
+            }
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+        let reason = StopReason("first timeout".to_string());
+        s.run_until(vec![(reason.clone(), Box::new(|s| s.time() >= 1.5))]);
+
+        let mut controller: Box<dyn SimController> = Box::new(s);
+        assert_eq!(controller.stop_reason(), Some(&reason));
+        assert_eq!((controller.steps(), controller.events_pending()), (2, 1));
+        while controller.events_pending() > 0 {
+            controller.step();
+        }
+        assert_eq!(controller.time(), 3.0);
+        assert_eq!(controller.steps(), 3);
+        assert_eq!(controller.process_count(), 1);
+        assert_eq!(controller.live_process_count(), 0);
+    }
+}
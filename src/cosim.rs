@@ -0,0 +1,110 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Co-simulation with an external simulator advancing in lockstep.
+//!
+//! The external side grants the simulation an advance up to a time bound,
+//! the simulation processes all its events up to the bound and reports
+//! the outputs produced meanwhile, then the external side injects its
+//! inputs and grants the next advance.
+
+use crate::{Event, ProcessId, Queued, SimState, Simulation, StaticProcess};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// What happened during a call to `Simulation::advance_bounded`.
+#[derive(Debug, Clone)]
+pub struct AdvanceReport<T> {
+    /// The simulation time reached, that is the bound of the advance
+    pub time: f64,
+    /// Number of events processed during the advance
+    pub steps: usize,
+    /// Events selected by the output filter that were processed during
+    /// the advance, with the state yielded by their process
+    pub outputs: Vec<(Event<T>, T)>,
+    /// Time of the next scheduled event, if any, which is after the bound
+    pub next_event: Option<f64>,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Process all the events scheduled up to `t_max` included, never one
+    /// after it, then move the simulation time to `t_max`.
+    ///
+    /// The inputs registered with `inject_input` since the previous advance
+    /// are scheduled first, in the order they were registered. This makes
+    /// the run deterministic: splitting it into a different sequence of
+    /// advances processes the same events in the same order, as long as the
+    /// same inputs are injected at the same times.
+    pub fn advance_bounded(&mut self, t_max: f64) -> AdvanceReport<T> {
+        assert!(
+            t_max >= self.time,
+            "Cannot advance to time {} before the current time {}",
+            self.format_time(t_max),
+            self.format_time(self.time)
+        );
+        for (time, process, state) in core::mem::take(&mut self.inputs) {
+            self.schedule_at(time, process, state);
+        }
+        let start = self.steps;
+        while let Some(Queued(next)) = self.future_events.peek() {
+            if next.time > t_max {
+                break;
+            }
+            self.step();
+        }
+        self.time = t_max;
+        AdvanceReport {
+            time: self.time,
+            steps: self.steps - start,
+            outputs: core::mem::take(&mut self.outputs),
+            next_event: self.future_events.peek().map(|Queued(e)| e.time),
+        }
+    }
+
+    /// Register an input of the external simulator: an event resuming
+    /// `process` with `state` at the absolute time `time`, that is scheduled
+    /// at the beginning of the next call to `advance_bounded`.
+    pub fn inject_input(&mut self, time: f64, process: ProcessId, state: T) {
+        assert!(
+            time >= self.time,
+            "Cannot inject an input at time {} before the current time {}",
+            self.format_time(time),
+            self.format_time(self.time)
+        );
+        self.inputs.push((time, process, state));
+    }
+
+    /// Set the filter selecting the processed events that are outputs for
+    /// the external simulator, collected in the next `AdvanceReport`.
+    ///
+    /// Outputs are selected regardless of the log settings.
+    pub fn set_output_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&Event<T>, &T) -> bool + 'static,
+    {
+        self.output_filter = Some(Box::new(filter));
+    }
+
+    pub(crate) fn collect_output(&mut self, event: &Event<T>, sim_state: &T) {
+        if let Some(filter) = &self.output_filter {
+            if filter(event, sim_state) {
+                self.outputs.push((event.clone(), sim_state.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,83 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, Simulation};
+
+/// A producer timing out every 1.5 time units, whose events are outputs,
+/// and a consumer resumed by the inputs.
+fn model() -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    let producer = s.create_async_process(move |handle| async move {
+        for _ in 0..6 {
+            handle.yield_state(Effect::TimeOut(1.5)).await;
+        }
+    });
+    s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    s.schedule_event(0.0, producer, Effect::TimeOut(0.));
+    s.set_output_filter(move |e, _| e.process() == producer);
+    s
+}
+
+fn log(s: &Simulation<Effect>) -> Vec<(f64, usize)> {
+    s.processed_events()
+        .iter()
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect()
+}
+
+#[test]
+fn advance_bounded() {
+    let mut s = model();
+    let report = s.advance_bounded(4.5);
+    assert_eq!(report.time, 4.5);
+    assert_eq!(s.time(), 4.5);
+    // the event at the bound is processed, the next one is not
+    assert_eq!(report.steps, 4);
+    assert_eq!(report.next_event, Some(6.0));
+    let outputs: Vec<_> = report.outputs.iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(outputs, vec![0.0, 1.5, 3.0, 4.5]);
+
+    // an advance without events only moves the time
+    s.inject_input(5.5, 1, Effect::TimeOut(0.));
+    let report = s.advance_bounded(5.0);
+    assert_eq!((report.time, report.steps), (5.0, 0));
+    assert_eq!(report.next_event, Some(5.5));
+    assert!(report.outputs.is_empty());
+}
+
+#[test]
+fn advance_bounded_deterministic() {
+    let run = |bounds: &[f64]| {
+        let mut s = model();
+        s.inject_input(3.0, 1, Effect::TimeOut(0.));
+        s.inject_input(3.0, 1, Effect::TimeOut(0.));
+        let mut outputs = Vec::new();
+        for &bound in bounds {
+            let report = s.advance_bounded(bound);
+            outputs.extend(report.outputs.into_iter().map(|(e, _)| e.time()));
+            if bound == 4.0 {
+                s.inject_input(6.0, 1, Effect::TimeOut(0.));
+            }
+        }
+        (log(&s), outputs)
+    };
+    let split = run(&[1.0, 3.0, 4.0, 7.0, 10.0]);
+    assert_eq!(split, run(&[2.5, 4.0, 10.0]));
+    assert_eq!(split.1, vec![0.0, 1.5, 3.0, 4.5, 6.0, 7.5]);
+}
@@ -0,0 +1,133 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Cost accounting of the usage of the resources.
+
+use crate::{ResourceId, SimState, Simulation, StaticProcess};
+
+/// The cost of the usage of a resource, accrued at the current rate from
+/// the time it was set.
+#[derive(Debug, Copy, Clone, Default)]
+pub(crate) struct Cost {
+    rate: f64,
+    /// Cost accrued before the current rate was set
+    accrued: f64,
+    /// Time the units were in use when the current rate was set
+    busy: f64,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Set the cost of using a unit of resource `id` for a time unit, from
+    /// now on. The default rate is 0.
+    ///
+    /// The cost accrues for the units granted by `Request`, like the time
+    /// counted by `resource_utilization`, and is not affected by the
+    /// capacity of the resource.
+    pub fn set_resource_cost_rate(&mut self, id: ResourceId, rate: f64) {
+        assert!(
+            rate.is_finite() && rate >= 0.0,
+            "The cost rate of resource {} must be finite and not negative, got {}",
+            id,
+            rate
+        );
+        let accrued = self.total_resource_cost(id);
+        self.resources[id].cost = Cost {
+            rate,
+            accrued,
+            busy: self.busy_time(id),
+        };
+    }
+
+    /// Returns the cost of the usage of resource `id` from the start time
+    /// to the current time, i.e. the integral over time of the cost rate
+    /// times the units in use.
+    pub fn total_resource_cost(&self, id: ResourceId) -> f64 {
+        let cost = &self.resources[id].cost;
+        cost.accrued + cost.rate * (self.busy_time(id) - cost.busy)
+    }
+
+    /// Returns the sum of `total_resource_cost` over all the resources.
+    pub fn total_cost(&self) -> f64 {
+        (0..self.resources.len())
+            .map(|id| self.total_resource_cost(id))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, EndCondition, SimContext, Simulation, Step};
+
+    /// Schedule an event at `time`, where `EndCondition::Time(time)` stops.
+    fn stop_at(s: &mut Simulation<Effect>, time: f64) {
+        let p = s.create_sm_process(Box::new(|_: SimContext<Effect>| Step::Done));
+        s.schedule_event(time, p, Effect::TimeOut(0.0));
+    }
+
+    #[test]
+    fn resource_cost() {
+        let mut s = Simulation::new();
+        let r = s.create_resource(2);
+        let free = s.create_resource(1);
+        s.set_resource_cost_rate(r, 2.0);
+        let cycle = [
+            Effect::Request(r),
+            Effect::Request(free),
+            Effect::TimeOut(10.0),
+            Effect::Release(free),
+            Effect::Release(r),
+        ];
+        let mut step = 0;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            step += 1;
+            cycle.get(step - 1).map_or(Step::Done, |&e| Step::Yield(e))
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+        stop_at(&mut s, 5.0);
+        let mut s = s.run(EndCondition::Time(5.0));
+        // still held, accrued until now
+        assert_eq!(s.total_resource_cost(r), 10.0);
+        // setting the rate again keeps the cost accrued so far
+        s.set_resource_cost_rate(r, 2.0);
+        let s = s.run(EndCondition::NoEvents);
+        assert_eq!(s.time(), 10.0);
+        assert_eq!(s.total_resource_cost(r), 20.0);
+        assert_eq!(s.total_resource_cost(free), 0.0);
+        assert_eq!(s.total_cost(), 20.0);
+    }
+
+    #[test]
+    fn rate_change() {
+        let mut s = Simulation::new();
+        let r = s.create_resource(1);
+        let cycle = [
+            Effect::Request(r),
+            Effect::TimeOut(10.0),
+            Effect::Release(r),
+        ];
+        let mut step = 0;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            step += 1;
+            cycle.get(step - 1).map_or(Step::Done, |&e| Step::Yield(e))
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+        s.set_resource_cost_rate(r, 1.0);
+        stop_at(&mut s, 4.0);
+        let mut s = s.run(EndCondition::Time(4.0));
+        s.set_resource_cost_rate(r, 3.0);
+        let s = s.run(EndCondition::NoEvents);
+        assert_eq!(s.total_cost(), 4.0 + 3.0 * 6.0);
+    }
+}
@@ -0,0 +1,137 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Time-shared resources, preempting their holders after a quantum.
+
+use crate::{
+    Event, Internal, ProcessId, Queued, ResourceId, SimState, Simulation, StaticProcess, WakeReason,
+};
+use alloc::vec::Vec;
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a resource with one unit, like a CPU shared in round robin:
+    /// a process holding it for more than `quantum` time units without
+    /// releasing it is preempted and queued again, and the unit is granted
+    /// to the next waiting process.
+    ///
+    /// Only a process waiting for one of its events, e.g. yielding a long
+    /// `Effect::TimeOut` while holding the CPU, is preempted. Its events are
+    /// postponed by the time it spends in the queue, so the remaining work
+    /// is resumed when it is granted the CPU again. A process is not
+    /// preempted while nobody is waiting for the CPU.
+    ///
+    /// Returns the identifier of the resource.
+    pub fn create_cpu(&mut self, quantum: f64) -> ResourceId {
+        assert!(
+            quantum > 0.0,
+            "The quantum of a CPU must be positive, not {}",
+            quantum
+        );
+        let id = self.create_resource(1);
+        self.resources[id].quantum = Some(quantum);
+        id
+    }
+
+    /// Start the time slice of `process`, granted resource `r` now.
+    pub(crate) fn start_slice(&mut self, r: ResourceId, process: ProcessId) {
+        if let Some(quantum) = self.resources[r].quantum {
+            let since = self.time;
+            self.schedule_internal(
+                since + quantum,
+                Internal::Preempt {
+                    resource: r,
+                    process,
+                    since,
+                },
+            );
+        }
+    }
+
+    /// End the time slice of `process`, that has held resource `r` since
+    /// time `since`.
+    pub(crate) fn preempt(&mut self, r: ResourceId, process: ProcessId, since: f64) {
+        let now = self.time;
+        let res = &self.resources[r];
+        if !res.holders.iter().any(|&(p, t)| p == process && t == since) {
+            // the process released the resource in the meanwhile
+            return;
+        }
+        let pending = self
+            .pending_events()
+            .filter(|e| e.process == process)
+            .map(|e| e.time)
+            .fold(f64::INFINITY, f64::min);
+        if pending <= now || pending == f64::INFINITY {
+            // the process is about to continue, or is not running
+            return;
+        }
+        if res.queue.is_empty() {
+            // nobody is waiting, the process keeps the resource for
+            // another quantum
+            let quantum = res.quantum.unwrap();
+            self.schedule_internal(
+                now + quantum,
+                Internal::Preempt {
+                    resource: r,
+                    process,
+                    since,
+                },
+            );
+            return;
+        }
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .take_future_events()
+            .into_iter()
+            .partition(|Queued(e)| e.process == process);
+        self.future_events = kept.into();
+        let mut removed: Vec<Event<T>> = removed.into_iter().map(|Queued(e)| e).collect();
+        removed.sort_by(Event::queue_order);
+        self.track_pending_wakeups();
+        let request = Event {
+            time: now,
+            process,
+            state: removed[0].state.clone(),
+            key: (0, 0),
+            seq: 0,
+            wake: WakeReason::Scheduled,
+            label: None,
+        };
+        let res = &mut self.resources[r];
+        res.preempted.insert(
+            process,
+            removed.into_iter().map(|e| (e.time - now, e)).collect(),
+        );
+        res.queue.push_back(request);
+        res.record_release(process, now);
+        self.free_unit(r);
+    }
+
+    /// Resume `process`, granted resource `r` again after being preempted,
+    /// scheduling its postponed events. Returns false if it was not
+    /// preempted.
+    pub(crate) fn resume_preempted(&mut self, r: ResourceId, process: ProcessId) -> bool {
+        match self.resources[r].preempted.remove(&process) {
+            Some(events) => {
+                for (left, event) in events {
+                    let seq = self.next_seq;
+                    self.schedule_at(self.time + left, event.process, event.state);
+                    self.forward_dependents(event.seq, (self.next_seq > seq).then_some(seq));
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
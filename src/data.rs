@@ -0,0 +1,90 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Data attached to the processes, that can be read and written from
+//! outside of them.
+
+use crate::{ProcessId, SimState, Simulation, StaticProcess};
+use alloc::boxed::Box;
+use core::any::Any;
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Attach `data` to `process`, replacing the data attached before.
+    ///
+    /// Each process has a single slot, shared by all the types of data:
+    /// reading it back with a different type returns `None`. The data is
+    /// kept after the process completes, until the simulation is dropped.
+    pub fn set_process_data<D: Any>(&mut self, process: ProcessId, data: D) {
+        assert!(
+            process < self.processes.len(),
+            "Process {} does not exist",
+            process
+        );
+        self.process_data.insert(process, Box::new(data));
+    }
+
+    /// Returns the data attached to `process`, if it has any of type `D`.
+    pub fn process_data<D: Any>(&self, process: ProcessId) -> Option<&D> {
+        self.process_data.get(&process)?.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the data attached to `process`, if
+    /// it has any of type `D`.
+    pub fn process_data_mut<D: Any>(&mut self, process: ProcessId) -> Option<&mut D> {
+        self.process_data.get_mut(&process)?.downcast_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, SimContext, Simulation, Step};
+
+    #[test]
+    fn process_data() {
+        let mut s = Simulation::new();
+        let owner = s.create_sm_process(Box::new(|_: SimContext<Effect>| Step::Done));
+        let mut ticks = 0;
+        let writer = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            ticks += 1;
+            if ticks > 3 {
+                Step::Done
+            } else {
+                Step::Yield(Effect::TimeOut(1.0))
+            }
+        }));
+        s.schedule_event(0.0, owner, Effect::TimeOut(0.0));
+        s.schedule_event(0.0, writer, Effect::TimeOut(0.0));
+        s.set_process_data(owner, Vec::<f64>::new());
+        // the code driving the simulation writes for the writer each time
+        // it yields, i.e. the final resume is not counted
+        while !s.peek_events(1).is_empty() {
+            let logged = s.processed_events().len();
+            s.step();
+            match s.processed_events()[logged..].first() {
+                Some((e, _)) if e.process() == writer => {
+                    let time = s.time();
+                    s.process_data_mut::<Vec<f64>>(owner).unwrap().push(time);
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(
+            s.process_data::<Vec<f64>>(owner),
+            Some(&vec![0.0, 1.0, 2.0])
+        );
+        assert_eq!(s.process_data::<u32>(owner), None);
+        assert_eq!(s.process_data::<Vec<f64>>(writer), None);
+    }
+}
@@ -0,0 +1,138 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Faults injected at scheduled times, to test the resilience of models.
+
+use crate::{ContainerId, Fixed, Internal, ProcessId, ProcessStatus, ResourceId, StaticProcess};
+use crate::{Queued, SimState, Simulation};
+use alloc::vec::Vec;
+
+/// A fault scheduled with `Simulation::schedule_fault`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Fault {
+    /// Kill a process: it is never resumed again, and is counted as
+    /// completed. Its pending events and its requests waiting for
+    /// resources, locks, channels and containers are dropped, and the
+    /// units it holds of resources granted by a `Request` are released.
+    /// Events scheduled for it later are ignored.
+    KillProcess(ProcessId),
+    /// Fail a resource, like `Effect::FailResource`
+    FailResource(ResourceId),
+    /// Repair a resource, like `Effect::RepairResource`
+    RepairResource(ResourceId),
+    /// Set the level of a container, resuming the processes that can then
+    /// complete their put or get
+    SetContainerLevel {
+        /// The container to corrupt
+        container: ContainerId,
+        /// The new level, that must not exceed the capacity
+        level: Fixed,
+    },
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Inject `fault` at the absolute time `at`, that must not be before
+    /// the current simulation time.
+    ///
+    /// Like the other actions of the simulation itself, the fault is
+    /// applied before the events of the processes scheduled at the same
+    /// time, and only if any event is processed at or after `at`.
+    pub fn schedule_fault(&mut self, at: f64, fault: Fault) {
+        assert!(
+            at >= self.time,
+            "Cannot schedule a fault at time {} before the current time {}",
+            self.format_time(at),
+            self.format_time(self.time)
+        );
+        self.schedule_internal(at, Internal::Fault(fault));
+    }
+
+    pub(crate) fn apply_fault(&mut self, fault: Fault) {
+        match fault {
+            Fault::KillProcess(id) => self.kill_process(id),
+            Fault::FailResource(r) => self.resources[r].failed = true,
+            Fault::RepairResource(r) => {
+                self.resources[r].failed = false;
+                self.grant_available(r);
+            }
+            Fault::SetContainerLevel { container, level } => {
+                self.set_container_level(container, level)
+            }
+        }
+    }
+
+    fn kill_process(&mut self, id: ProcessId) {
+        if self.processes[id].take().is_none() {
+            // the process already completed
+            return;
+        }
+        self.killed.insert(id);
+        let now = self.time;
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .take_future_events()
+            .into_iter()
+            .partition(|Queued(e)| e.process == id);
+        self.future_events = kept.into();
+        self.track_pending_wakeups();
+        let mut dropped: Vec<u64> = removed.iter().map(|Queued(e)| e.seq).collect();
+        if dropped.iter().any(|&seq| self.source_pending == Some(seq)) {
+            self.pull_event_source();
+        }
+        if let Some(events) = self.suspended.remove(&id) {
+            dropped.extend(events.iter().map(|(_, e)| e.seq));
+        }
+        self.backoffs.remove(&id);
+        self.rejected.remove(&id);
+        self.deliveries.remove(&id);
+        for lock in self.rwlocks.iter_mut() {
+            lock.remove_waiter(id);
+        }
+        for channel in self.channels.iter_mut() {
+            channel.remove_waiter(id);
+        }
+        for container in self.containers.iter_mut() {
+            container.remove_waiter(id);
+        }
+        for r in 0..self.resources.len() {
+            let res = &mut self.resources[r];
+            res.queue.retain(|e| e.process != id);
+            res.lease_queue.retain(|e| e.process != id);
+            if let Some(providers) = &mut res.providers {
+                providers.retain(|e| e.process != id);
+            }
+            res.preferred.remove(&id);
+            if let Some(events) = res.preempted.remove(&id) {
+                dropped.extend(events.iter().map(|(_, e)| e.seq));
+            }
+            let held = res.holders.iter().filter(|&&(p, _)| p == id).count();
+            self.forget_batch_waiter(r, id);
+            for _ in 0..held {
+                self.resources[r].record_release(id, now);
+                self.free_unit(r);
+            }
+        }
+        for seq in dropped {
+            self.cancel_event(seq);
+        }
+        if self.scheduled.get(id) == Some(&true) {
+            self.completed += 1;
+        }
+        self.record_status(id, ProcessStatus::Completed);
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,111 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NoEvents, Fault, Fixed, Simulation};
+
+#[test]
+fn kill_process() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let victim = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        loop {
+            handle.yield_state(Effect::TimeOut(2.0)).await;
+        }
+    });
+    let waiter = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let pinger = s.create_async_process(move |handle| async move {
+        for _ in 0..5 {
+            handle
+                .yield_state(Effect::Event {
+                    time: 0.0,
+                    process: victim,
+                })
+                .await;
+            handle.yield_state(Effect::TimeOut(2.0)).await;
+        }
+    });
+    s.schedule_event(0.0, victim, Effect::TimeOut(0.));
+    s.schedule_event(1.0, waiter, Effect::TimeOut(0.));
+    s.schedule_event(1.0, pinger, Effect::TimeOut(0.));
+    s.schedule_fault(5.0, Fault::KillProcess(victim));
+
+    let s = s.run(NoEvents);
+    s.assert_no_pending();
+    let last = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| e.process() == victim)
+        .map(|(e, _)| e.time())
+        .fold(0.0, f64::max);
+    assert!(last < 5.0, "{}", last);
+    // the unit held by the killed process is released to the waiter
+    let granted = s
+        .processed_events()
+        .iter()
+        .find(|(e, state)| e.process() == waiter && matches!(state, Effect::Release(_)))
+        .map(|(e, _)| e.time());
+    assert_eq!(granted, Some(5.0));
+}
+
+#[test]
+fn fail_resource_and_set_level() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let c = s.create_exact_container(Fixed::from_units(10), Fixed::ZERO);
+    let user = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(2.0)).await;
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let consumer = s.create_async_process(move |handle| async move {
+        handle
+            .yield_state(Effect::Get {
+                container: c,
+                amount: Fixed::from_units(3),
+            })
+            .await;
+        handle.yield_state(Effect::Trace).await;
+    });
+    s.schedule_event(0.0, user, Effect::TimeOut(0.));
+    s.schedule_event(0.0, consumer, Effect::TimeOut(0.));
+    s.schedule_fault(1.0, Fault::FailResource(r));
+    s.schedule_fault(
+        3.0,
+        Fault::SetContainerLevel {
+            container: c,
+            level: Fixed::from_units(4),
+        },
+    );
+    s.schedule_fault(4.0, Fault::RepairResource(r));
+    // keep the simulation running until the faults are applied
+    let idle = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(5.0)).await;
+    });
+    s.schedule_event(0.0, idle, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    let resumed: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| e.time() > 0.0 && e.process() != idle)
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect();
+    assert_eq!(resumed, vec![(2.0, user), (3.0, consumer), (4.0, user)]);
+    assert_eq!(s.container_level(c), Fixed::from_units(1));
+}
@@ -0,0 +1,213 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Federations of simulations exchanging timestamped messages.
+//!
+//! A large model can be split into sub-models, each one a `Simulation`,
+//! connected by links that turn the outputs of a member into events of
+//! another one after a minimum latency, the lookahead of the link.
+//! The federation advances its members conservatively, so that no member
+//! ever receives a message in its past.
+
+use crate::{Event, ProcessId, Queued, SimState, Simulation};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Identifies a member of a federation.
+pub type MemberId = usize;
+
+/// Maps an output of the sending member to the process of the receiving
+/// member to resume and its state, or drops it returning `None`.
+pub type Route<T> = dyn Fn(&Event<T>, &T) -> Option<(ProcessId, T)>;
+
+struct Link<T> {
+    from: MemberId,
+    to: MemberId,
+    lookahead: f64,
+    route: Box<Route<T>>,
+}
+
+/// A single-threaded coordinator running simulations that exchange
+/// timestamped messages.
+///
+/// The outputs of a member, selected by its `Simulation::set_output_filter`,
+/// are offered to the links leaving it. A message sent through a link is
+/// delivered to the receiving member as an event scheduled `lookahead` time
+/// units after the output.
+///
+/// Members are advanced in round robin. In each round every member processes
+/// only the events that come before the earliest time a message could still
+/// reach it, so the events of each member are processed in the same order
+/// as if the whole model were a single simulation.
+pub struct Federation<T: SimState + Clone> {
+    members: Vec<Simulation<T>>,
+    links: Vec<Link<T>>,
+}
+
+impl<T: SimState + Clone> Default for Federation<T> {
+    fn default() -> Self {
+        Federation::new()
+    }
+}
+
+impl<T: SimState + Clone> Federation<T> {
+    /// Create a federation without members.
+    pub fn new() -> Federation<T> {
+        Federation {
+            members: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /// Add a simulation to the federation.
+    ///
+    /// Returns the identifier of the member.
+    pub fn add_member(&mut self, sim: Simulation<T>) -> MemberId {
+        self.members.push(sim);
+        self.members.len() - 1
+    }
+
+    /// Connect member `from` to member `to`: each output of `from` that
+    /// `route` maps to a process of `to` is delivered to it `lookahead` time
+    /// units later.
+    ///
+    /// The lookahead must be positive, otherwise the members could not
+    /// advance without risking to receive a message in their past.
+    pub fn connect<F>(&mut self, from: MemberId, to: MemberId, lookahead: f64, route: F)
+    where
+        F: Fn(&Event<T>, &T) -> Option<(ProcessId, T)> + 'static,
+    {
+        assert!(
+            lookahead > 0.0,
+            "The lookahead of a link must be positive, not {}",
+            lookahead
+        );
+        assert!(
+            from < self.members.len() && to < self.members.len(),
+            "Cannot connect member {} to member {} of a federation of {}",
+            from,
+            to,
+            self.members.len()
+        );
+        self.links.push(Link {
+            from,
+            to,
+            lookahead,
+            route: Box::new(route),
+        });
+    }
+
+    /// Returns a reference to a member.
+    pub fn member(&self, id: MemberId) -> &Simulation<T> {
+        &self.members[id]
+    }
+
+    /// Returns a mutable reference to a member, for example to schedule
+    /// its initial events.
+    pub fn member_mut(&mut self, id: MemberId) -> &mut Simulation<T> {
+        &mut self.members[id]
+    }
+
+    /// Consume the federation, returning its members.
+    pub fn into_members(self) -> Vec<Simulation<T>> {
+        self.members
+    }
+
+    /// Returns, for each member, the earliest time of an event it could
+    /// still process: its next scheduled event, or a message caused by an
+    /// event of another member.
+    fn lower_bounds(&self) -> Vec<f64> {
+        let mut bounds: Vec<f64> = self
+            .members
+            .iter()
+            .map(|m| {
+                m.future_events
+                    .peek()
+                    .map_or(f64::INFINITY, |Queued(e)| e.time)
+            })
+            .collect();
+        // the lookaheads are positive, so this converges
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for link in self.links.iter() {
+                let bound = bounds[link.from] + link.lookahead;
+                if bound < bounds[link.to] {
+                    bounds[link.to] = bound;
+                    changed = true;
+                }
+            }
+        }
+        bounds
+    }
+
+    /// Run the members until all their events up to time `until` included
+    /// are processed.
+    ///
+    /// Returns the total number of events processed.
+    pub fn run_until(&mut self, until: f64) -> usize {
+        let mut steps = 0;
+        loop {
+            let bounds = self.lower_bounds();
+            if !bounds.iter().any(|&b| b.is_finite() && b <= until) {
+                return steps;
+            }
+            for id in 0..self.members.len() {
+                // a message reaches this member no earlier than `safe`
+                let safe = self
+                    .links
+                    .iter()
+                    .filter(|l| l.to == id)
+                    .map(|l| bounds[l.from] + l.lookahead)
+                    .fold(f64::INFINITY, f64::min);
+                let member = &mut self.members[id];
+                while let Some(Queued(next)) = member.future_events.peek() {
+                    if next.time >= safe || next.time > until {
+                        break;
+                    }
+                    member.step();
+                    steps += 1;
+                }
+                self.deliver(id);
+            }
+        }
+    }
+
+    /// Run the members until none of them has events left.
+    ///
+    /// Returns the total number of events processed.
+    pub fn run(&mut self) -> usize {
+        self.run_until(f64::INFINITY)
+    }
+
+    /// Send the outputs collected by member `id` through its links.
+    fn deliver(&mut self, id: MemberId) {
+        let outputs = core::mem::take(&mut self.members[id].outputs);
+        for (event, state) in outputs.iter() {
+            for link in self.links.iter().filter(|l| l.from == id) {
+                if let Some((process, message)) = (link.route)(event, state) {
+                    self.members[link.to].schedule_event_at(
+                        event.time + link.lookahead,
+                        process,
+                        message,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,85 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, Federation, Simulation};
+
+/// Returns the times of the events processed by a member.
+fn times(s: &Simulation<Effect>) -> Vec<f64> {
+    s.processed_events().iter().map(|(e, _)| e.time).collect()
+}
+
+#[test]
+fn ping_pong() {
+    let mut fed = Federation::new();
+    let mut ping = Simulation::new();
+    let pinger = ping.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    ping.set_output_filter(|_, _| true);
+    ping.schedule_event(0.0, pinger, Effect::TimeOut(0.));
+    let mut pong = Simulation::new();
+    let ponger = pong.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    pong.set_output_filter(|_, _| true);
+    let a = fed.add_member(ping);
+    let b = fed.add_member(pong);
+    fed.connect(a, b, 1.0, move |_, _| Some((ponger, Effect::Wait)));
+    fed.connect(b, a, 1.0, move |_, _| Some((pinger, Effect::Wait)));
+
+    assert_eq!(fed.run_until(5.0), 6);
+    assert_eq!(times(fed.member(a)), vec![0.0, 2.0, 4.0]);
+    assert_eq!(times(fed.member(b)), vec![1.0, 3.0, 5.0]);
+}
+
+#[test]
+fn messages_do_not_overtake_local_events() {
+    let mut fed = Federation::new();
+    let mut sender = Simulation::new();
+    let source = sender.create_async_process(move |handle| async move {
+        for _ in 0..3 {
+            handle.yield_state(Effect::TimeOut(2.0)).await;
+        }
+    });
+    sender.set_output_filter(|_, _| true);
+    sender.schedule_event(0.0, source, Effect::TimeOut(0.));
+    let mut receiver = Simulation::new();
+    let sink = receiver.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    let local = receiver.create_async_process(move |handle| async move {
+        for _ in 0..6 {
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    receiver.schedule_event(0.5, local, Effect::TimeOut(0.));
+    let a = fed.add_member(sender);
+    let b = fed.add_member(receiver);
+    fed.connect(a, b, 0.25, move |_, _| Some((sink, Effect::Wait)));
+
+    fed.run();
+    // messages sent at 0, 2 and 4 are received in order with the local
+    // events of the receiver
+    assert_eq!(
+        times(fed.member(b)),
+        vec![0.25, 0.5, 1.5, 2.25, 2.5, 3.5, 4.25, 4.5, 5.5]
+    );
+}
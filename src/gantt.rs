@@ -0,0 +1,78 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Busy intervals of the resources, in the form used by Gantt charts.
+
+use crate::{ProcessId, ResourceId, SimState, Simulation, StaticProcess};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// The busy spans of a resource, returned by `Simulation::gantt_data`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GanttRow {
+    /// The resource
+    pub resource: ResourceId,
+    /// The start and end times of the spans during which a unit of the
+    /// resource was held, with the process holding it, in order of start
+    pub spans: Vec<(f64, f64, ProcessId)>,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Returns a row for each resource with its busy spans, derived from
+    /// the hold log of the resource.
+    ///
+    /// Only the spans completed so far are included, and like in the hold
+    /// log the units granted as leases are not.
+    pub fn gantt_data(&self) -> Vec<GanttRow> {
+        (0..self.resources.len())
+            .map(|resource| {
+                let mut spans = self.resource_hold_log(resource).to_vec();
+                spans.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                GanttRow { resource, spans }
+            })
+            .collect()
+    }
+
+    /// Returns the rows of `gantt_data` as JSON, e.g.
+    /// `[{"resource":0,"spans":[{"start":0,"end":7,"process":0}]}]`.
+    pub fn gantt_json(&self) -> String {
+        let mut out = String::from("[");
+        for (i, row) in self.gantt_data().iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(out, "{{\"resource\":{},\"spans\":[", row.resource).unwrap();
+            for (j, (start, end, process)) in row.spans.iter().enumerate() {
+                if j > 0 {
+                    out.push(',');
+                }
+                write!(
+                    out,
+                    "{{\"start\":{},\"end\":{},\"process\":{}}}",
+                    start, end, process
+                )
+                .unwrap();
+            }
+            out.push_str("]}");
+        }
+        out.push(']');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,54 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NoEvents, GanttRow, Simulation};
+
+#[test]
+fn gantt_data() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let idle = s.create_resource(1);
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(3.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(2.0, p2, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+
+    assert_eq!(
+        s.gantt_data(),
+        vec![
+            GanttRow {
+                resource: r,
+                spans: vec![(0.0, 7.0, p1), (7.0, 10.0, p2)]
+            },
+            GanttRow {
+                resource: idle,
+                spans: vec![]
+            }
+        ]
+    );
+    assert_eq!(
+        s.gantt_json(),
+        r#"[{"resource":0,"spans":[{"start":0,"end":7,"process":0},{"start":7,"end":10,"process":1}]},{"resource":1,"spans":[]}]"#
+    );
+}
@@ -0,0 +1,81 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Random delays around a base duration.
+
+#[cfg(feature = "std")]
+use core::f64::consts::PI;
+
+/// The distribution of the noise added to the base delay of an
+/// `Effect::TimeOutJitter`.
+///
+/// The realized delay is never negative: how a negative sample is handled
+/// is described by each variant.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum JitterSpec {
+    /// Noise drawn uniformly in `[-w, w]`. A negative delay is clamped
+    /// to zero.
+    Uniform(f64),
+    /// Noise drawn from an exponential distribution with the given mean,
+    /// that is never negative, so the base delay is a minimum. A negative
+    /// base delay is clamped to zero.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    Exponential(f64),
+    /// Noise drawn from a normal distribution centered on zero with the
+    /// given standard deviation, truncated at zero: delays that would be
+    /// negative are resampled, so the realized delays follow the normal
+    /// distribution conditioned on being non-negative.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    Normal(f64),
+}
+
+impl JitterSpec {
+    /// Draw a delay around `base`, from the numbers uniformly distributed
+    /// in `[0, 1)` returned by `draw`.
+    pub(crate) fn sample(&self, base: f64, mut draw: impl FnMut() -> f64) -> f64 {
+        match *self {
+            JitterSpec::Uniform(w) => (base + w * (2.0 * draw() - 1.0)).max(0.0),
+            #[cfg(feature = "std")]
+            JitterSpec::Exponential(mean) => {
+                // in (0, 1], so that the logarithm is finite
+                let u = 1.0 - draw();
+                (base - mean * u.ln()).max(0.0)
+            }
+            #[cfg(feature = "std")]
+            JitterSpec::Normal(sigma) => {
+                assert!(
+                    base >= 0.0,
+                    "The base delay of a normal jitter must not be negative, not {}",
+                    base
+                );
+                loop {
+                    // Box-Muller transform
+                    let u = 1.0 - draw();
+                    let v = draw();
+                    let delay = base + sigma * (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos();
+                    if delay >= 0.0 {
+                        return delay;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Labels telling apart the kinds of scheduled events in the log.
+
+use crate::{Event, LogView, ProcessId, SimState, Simulation, StaticProcess, WakeReason};
+use alloc::collections::BTreeMap;
+
+/// Identifies a label interned with `Simulation::intern_label`.
+///
+/// Labels are attached to events scheduled with `Simulation::schedule_labeled`,
+/// `Effect::LabeledTimeOut` or `Effect::LabeledEvent`, and are carried to the
+/// log, where they can be read with `Event::label`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LabelId(u32);
+
+impl LabelId {
+    /// Returns the index of the label, in the order labels were interned.
+    pub fn index(self) -> u32 {
+        self.0
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Returns the id of `name`, interning it if it is new. Interning the
+    /// same name again returns the same id.
+    pub fn intern_label(&mut self, name: &'static str) -> LabelId {
+        if let Some(i) = self.labels.iter().position(|&l| l == name) {
+            return LabelId(i as u32);
+        }
+        self.labels.push(name);
+        LabelId(self.labels.len() as u32 - 1)
+    }
+
+    /// Returns the name of a label interned by this simulation.
+    pub fn label_name(&self, label: LabelId) -> &'static str {
+        self.labels[label.0 as usize]
+    }
+
+    /// Schedule a process to be executed after `time` time instants, like
+    /// `schedule_event`, with the label `label`.
+    pub fn schedule_labeled(&mut self, time: f64, process: ProcessId, state: T, label: LabelId) {
+        self.push_event(
+            self.time + time,
+            process,
+            state,
+            WakeReason::Scheduled,
+            Some(label),
+        );
+    }
+}
+
+impl<'a, T> LogView<'a, T> {
+    /// Iterate over the logged events with the label `label`, in the
+    /// order they were processed.
+    pub fn with_label(&self, label: LabelId) -> impl Iterator<Item = &'a (Event<T>, T)> + 'a {
+        self.iter().filter(move |(e, _)| e.label == Some(label))
+    }
+
+    /// Returns the number of logged events with each label. Events without
+    /// a label are not counted.
+    pub fn count_by_label(&self) -> BTreeMap<LabelId, usize> {
+        let mut counts = BTreeMap::new();
+        for (event, _) in self.iter() {
+            if let Some(label) = event.label {
+                *counts.entry(label).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+}
@@ -14,10 +14,11 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 
 //! This crate implements a discrete time event simulation framework
-//! inspired by the SimPy library for Python. It uses the generator
+//! inspired by the SimPy library for Python. By default it uses the generator
 //! feature that is nightly. Once the feature is stabilized, also this
 //! crate will use stable. Generators will be the only nightly feature
-//! used in this crate.
+//! used in this crate. Without the `nightly` feature, the crate builds on
+//! stable and processes are written as async blocks.
 //!
 //! # Simulation
 //! A simulation is performed scheduling one or more processes that
@@ -51,6 +52,11 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //! For example, a generator can set a timeout after which it is executed again.
 //! The process may also return. In that case it can not be resumed anymore.
 //!
+//! Processes can also be written as async blocks, created with
+//! `create_async_process`, awaiting `ProcessHandle::yield_state` where a
+//! generator would yield. They are resumed exactly like generators, and are
+//! the only kind of process available without the `nightly` feature.
+//!
 //!
 //! # Resource
 //! A resource is a finite amount of entities that can be used by one process
@@ -68,12 +74,155 @@ along with this program.  If not, see <http://www.gnu.org/licenses/>. */
 //! yielding `Release` was holding a resource with that ID, but if a resource
 //! gets more release then requests, the simulation will panic.
 //!
+//! # Features
+//! - `nightly` (default): write processes as generators, with
+//!   `create_process`. Requires a nightly compiler.
+//! - `std` (default): use the standard library. Without it the crate only
+//!   needs `alloc`, and the functionality relying on threads or on the
+//!   system clock or on I/O, i.e. `subscribe`, `subscribe_bounded`,
+//!   `set_resume_profiling` and `export_binary`, or on the floating point functions, i.e. the
+//!   exponential and normal `JitterSpec`, is not available.
+//! - `async`: stream the processed events to asynchronous code with
+//!   `run_streaming`.
+//! - `arrow`: export the event log in the Apache Arrow format with
+//!   `Simulation::export_arrow`.
+//! - `chrono`: use UTC timestamps as the simulation time through a
+//!   `DateTimeClock`.
+//! - `rayon`: run a simulation for each of many seeds in parallel with
+//!   `run_ensemble`, and resume independent processes at the same time in
+//!   parallel with `Simulation::step_parallel`.
+//! - `serde`: implement `Serialize` and `Deserialize` for effects, events,
+//!   ending conditions and the other plain data types. With `std`, also
+//!   load the initial schedule from a trace with `Simulation::load_schedule`.
+//!
+
+#![cfg_attr(feature = "nightly", feature(generators, generator_trait))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cmp::{Ordering, Reverse};
+use core::convert::TryFrom;
+use core::fmt::{self, Write};
+use core::mem;
+#[cfg(feature = "nightly")]
+use core::ops::Generator;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+#[cfg(feature = "std")]
+use std::time::Instant;
 
-#![feature(generators, generator_trait)]
-use std::cmp::{Ordering, Reverse};
-use std::collections::{BinaryHeap, VecDeque};
-use std::ops::{Generator, GeneratorState};
-use std::pin::Pin;
+use batch::Batch;
+use channel::{ChannelState, Message};
+use container::Container;
+use cost::Cost;
+use process::{Process, ProcessState};
+use quantile::P2Quantile;
+use rewind::Rewind;
+use rng::Rng;
+use rwlock::RwLock;
+use shift::Schedule;
+use status::StatusTrack;
+use timer::{Timer, TimerReply};
+use watchdog::Watchdog;
+
+mod allocator;
+mod backoff;
+mod batch;
+#[cfg(feature = "std")]
+mod binary;
+mod cdf;
+mod chain;
+mod channel;
+#[cfg(feature = "chrono")]
+mod clock;
+#[cfg(feature = "arrow")]
+mod columnar;
+pub mod component;
+mod container;
+mod controller;
+mod cosim;
+mod cost;
+mod cpu;
+mod data;
+mod fault;
+mod federation;
+mod gantt;
+mod jitter;
+mod label;
+mod marker;
+mod merge;
+mod metrics;
+mod nested;
+pub mod network;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod perf;
+mod process;
+mod quantile;
+mod query;
+mod rendezvous;
+mod results;
+mod rewind;
+mod rng;
+mod rwlock;
+#[cfg(all(feature = "serde", feature = "std"))]
+mod scenario;
+mod shift;
+mod skew;
+mod status;
+#[cfg(feature = "async")]
+mod stream;
+pub mod testing;
+mod timer;
+mod trace;
+pub mod typed;
+mod watchdog;
+pub use allocator::{AllocDecision, Allocator, FifoAllocator, ResourceState};
+#[cfg(feature = "std")]
+pub use binary::{read_binary, BinarySimState};
+pub use cdf::Cdf;
+pub use chain::EventId;
+pub use channel::Channel;
+#[cfg(feature = "chrono")]
+pub use clock::DateTimeClock;
+pub use container::Fixed;
+pub use controller::SimController;
+pub use cosim::AdvanceReport;
+pub use fault::Fault;
+pub use federation::{Federation, MemberId, Route};
+pub use gantt::GanttRow;
+pub use jitter::JitterSpec;
+pub use label::LabelId;
+pub use marker::Marker;
+pub use merge::{merge_logs, OffsetLog};
+#[cfg(all(feature = "rayon", feature = "nightly"))]
+pub use parallel::SendSimGen;
+pub use perf::RunCounters;
+pub use process::{
+    Indexed, NoStaticProcess, ProcessHandle, StateMachineProcess, StaticProcess, Step, YieldState,
+};
+pub use query::{Buckets, LogView};
+#[cfg(feature = "rayon")]
+pub use results::run_ensemble;
+pub use results::SimResults;
+pub use rewind::Rebuild;
+#[cfg(all(feature = "serde", feature = "std"))]
+pub use scenario::{LoadError, RecordError, ScheduleFormat};
+pub use status::ProcessStatus;
+#[cfg(feature = "async")]
+pub use stream::{run_streaming, EventStream};
+pub use timer::TimerId;
+pub use trace::{ResourceChange, ResourceSnapshot, StepOutcome, StepTrace, TracedEvent};
+pub use watchdog::{WatchdogHook, WatchdogReport};
 
 /// Data structures implementing this trait can be yielded from the generator
 /// associated with a `Process`. This allows attaching application-specific data
@@ -85,9 +234,11 @@ use std::pin::Pin;
 ///
 /// A process can then yield `ItemState` instead of `Effect` types:
 ///
-/// ```
+#[cfg_attr(feature = "nightly", doc = "```")]
+#[cfg_attr(not(feature = "nightly"), doc = "```ignore")]
 /// #![feature(generators, generator_trait)]
 /// use desim::{Effect, SimState, Simulation};
+/// use std::borrow::Cow;
 ///
 /// // enum used as part of state logged during simulation
 /// #[derive(Clone)]
@@ -105,7 +256,7 @@ use std::pin::Pin;
 /// }
 ///
 /// impl SimState for ItemState {
-///     fn get_effect(&self) -> Effect { self.effect }
+///     fn effect(&self) -> Cow<'_, Effect> { Cow::Borrowed(&self.effect) }
 ///     fn set_effect(&mut self, e: Effect) { self.effect = e; }
 ///     fn should_log(&self) -> bool { self.log }
 /// }
@@ -124,8 +275,20 @@ use std::pin::Pin;
 ///
 /// For a full example, see examples/monitoring-state.rs
 ///
+/// # Migrating from `get_effect`
+///
+/// The effect used to be returned by value by `get_effect`, which is now
+/// deprecated and will be removed in the next release. Implement `effect`
+/// instead, borrowing the effect when the state stores it, as above, or
+/// returning `Cow::Owned` when it is computed from the state. Until then
+/// the callers of `get_effect` keep working, as it calls `effect`.
 pub trait SimState {
-    fn get_effect(&self) -> Effect;
+    /// Returns the effect of the state, borrowed when possible.
+    fn effect(&self) -> Cow<'_, Effect>;
+    #[deprecated(note = "call `effect` instead")]
+    fn get_effect(&self) -> Effect {
+        self.effect().into_owned()
+    }
     fn set_effect(&mut self, effect: Effect);
     fn should_log(&self) -> bool;
 }
@@ -133,11 +296,28 @@ pub trait SimState {
 /// The effect is yelded by a process generator to
 /// interact with the simulation environment.
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 #[non_exhaustive]
 pub enum Effect {
     /// The process that yields this effect will be resumed
-    /// after the speified time
+    /// after the speified time, without any other process having to
+    /// wake it up, unlike `Wait`.
     TimeOut(f64),
+    /// The same as `TimeOut`, with a clearer name. `Sleep(0.0)` is a
+    /// cooperative yield: the process is resumed at the current time,
+    /// after the other events already scheduled at this time.
+    Sleep(f64),
+    /// Like `TimeOut`, with a delay drawn around `base` from the random
+    /// number generator of the simulation when the effect is yielded.
+    /// The logged state, and the one resuming the process, have their
+    /// effect replaced by the `TimeOut` of the realized delay.
+    TimeOutJitter {
+        /// The delay around which the realized one is drawn
+        base: f64,
+        /// The distribution of the noise added to `base`
+        dist: JitterSpec,
+    },
     /// Yielding this effect it is possible to schedule the specified event
     Event {
         /// Time interval between the current simulation time and the event schedule
@@ -145,20 +325,222 @@ pub enum Effect {
         /// Process to execute when the event occur
         process: ProcessId,
     },
-    /// This effect is yielded to request a resource
+    /// Like `TimeOut`, labeling the event resuming the process with a label
+    /// interned with `Simulation::intern_label`.
+    LabeledTimeOut {
+        /// Duration of the timeout
+        time: f64,
+        /// Label of the event
+        label: LabelId,
+    },
+    /// Like `Event`, labeling the scheduled event with a label interned
+    /// with `Simulation::intern_label`.
+    LabeledEvent {
+        /// Time interval between the current simulation time and the event schedule
+        time: f64,
+        /// Process to execute when the event occur
+        process: ProcessId,
+        /// Label of the event
+        label: LabelId,
+    },
+    /// Like `Trace`, also recording a marker with the label, that is kept
+    /// even if the event is not logged. See `Simulation::record_marker`.
+    LabeledTrace(LabelId),
+    /// Set a timer expiring after the given duration, resuming the process
+    /// when it expires with `WakeReason::TimerExpired`. The process is
+    /// resumed immediately, and the id of the timer is returned by
+    /// `SimContext::timer_set`. A process can set many timers.
+    SetTimer(f64),
+    /// Cancel a timer set by the process, if it is still to expire. The
+    /// process is resumed immediately, and `SimContext::timer_was_armed`
+    /// returns whether the timer was canceled.
+    CancelTimer(TimerId),
+    /// Restart a timer set by the process, to expire after `duration` from
+    /// now, whether it expired already or not. The process is resumed
+    /// immediately, and `SimContext::timer_was_armed` returns whether the
+    /// timer was still to expire.
+    RestartTimer {
+        /// The timer to restart
+        timer: TimerId,
+        /// Duration after which the timer expires
+        duration: f64,
+    },
+    /// This effect is yielded to request a resource. If the request is
+    /// rejected, because the queue of the resource is full or by its
+    /// allocator, the process is resumed right away and
+    /// `SimContext::rejected` returns `true`.
     Request(ResourceId),
     /// This effect is yielded to release a resource that is not needed anymore.
     Release(ResourceId),
-    /// Keep the process' state until it is resumed by another event.
+    /// Request a resource like `Request`, retrying while the request is
+    /// rejected by the allocator of the resource. The `n`-th retry happens
+    /// `base * 2^(n-1)` time units after the previous attempt; after
+    /// `max_retries` retries the process is resumed, and
+    /// `SimContext::rejected` returns `true`.
+    RequestWithBackoff {
+        /// The resource to request
+        resource: ResourceId,
+        /// Delay before the first retry
+        base: f64,
+        /// Maximum number of retries
+        max_retries: usize,
+    },
+    /// Request a resource like `Request`, preferring one of its units.
+    /// The preferred unit is granted if it is free, otherwise any other;
+    /// the unit granted is returned by `SimContext::granted_unit`.
+    RequestAffinity {
+        /// The resource to request
+        resource: ResourceId,
+        /// The unit to grant if it is free, e.g. the one granted before
+        prefer_unit: Option<usize>,
+    },
+    /// Meet a process requesting a rendezvous created with
+    /// `Simulation::create_rendezvous`, waiting until one yields
+    /// `Request` on it. Both processes are resumed at the same time.
+    Provide(ResourceId),
+    /// Acquire a shared lease on a unit of a resource. The first process
+    /// acquiring the lease takes a unit of the resource, waiting like a
+    /// `Request` if none is available; the following ones share it and are
+    /// resumed immediately, until the lease is released by all of them.
+    AcquireLease(ResourceId),
+    /// Release a lease acquired with `AcquireLease`. The unit of the
+    /// resource is returned only when the last holder releases the lease.
+    ReleaseLease(ResourceId),
+    /// Make all the units of a resource unavailable. Processes already
+    /// holding the resource keep it, but no new request is granted until
+    /// the resource is repaired. The process is resumed immediately.
+    FailResource(ResourceId),
+    /// Repair a failed resource, granting it to the queued requests.
+    /// The process is resumed immediately.
+    RepairResource(ResourceId),
+    /// Acquire a reader-writer lock for reading, sharing it with the other
+    /// readers. The process waits while a writer holds the lock, or while
+    /// other processes are waiting for it.
+    AcquireRead(RwLockId),
+    /// Acquire a reader-writer lock for writing, waiting until no other
+    /// process holds it.
+    AcquireWrite(RwLockId),
+    /// Upgrade the access of a process holding a reader-writer lock for
+    /// reading to writing, waiting until all the other readers release it.
+    /// Only one process at a time can be waiting for an upgrade.
+    UpgradeToWrite(RwLockId),
+    /// Release a reader-writer lock held for reading or writing.
+    ReleaseLock(RwLockId),
+    /// Send the message staged with `Channel::send`, waiting while the
+    /// channel is full.
+    Send(ChannelId),
+    /// Receive a message from a channel, waiting while it is empty. The
+    /// message is returned by `Channel::received` when the process resumes.
+    Recv(ChannelId),
+    /// Add `amount` to a container, waiting while it would exceed its
+    /// capacity.
+    Put {
+        /// The container to add to
+        container: ContainerId,
+        /// The quantity to add
+        amount: Fixed,
+    },
+    /// Take `amount` from a container, waiting while it holds less.
+    Get {
+        /// The container to take from
+        container: ContainerId,
+        /// The quantity to take
+        amount: Fixed,
+    },
+    /// Keep the process' state until it is resumed by another event,
+    /// e.g. an `Effect::Event` yielded by another process. Unlike
+    /// `TimeOut`, the process is never resumed by itself.
     Wait,
     Trace,
 }
 
+impl Effect {
+    /// Returns a numeric code identifying the variant of the effect,
+    /// used by the columnar and binary exports.
+    ///
+    /// Codes are never reused: new variants get new codes, so that exported
+    /// data remains readable. `u8::MAX` is reserved for unknown variants.
+    ///
+    /// | Variant              | Code |
+    /// |----------------------|------|
+    /// | `TimeOut`            | 0    |
+    /// | `Event`              | 1    |
+    /// | `Request`            | 2    |
+    /// | `Release`            | 3    |
+    /// | `Wait`               | 4    |
+    /// | `Trace`              | 5    |
+    /// | `FailResource`       | 6    |
+    /// | `RepairResource`     | 7    |
+    /// | `AcquireLease`       | 8    |
+    /// | `ReleaseLease`       | 9    |
+    /// | `AcquireRead`        | 10   |
+    /// | `AcquireWrite`       | 11   |
+    /// | `UpgradeToWrite`     | 12   |
+    /// | `ReleaseLock`        | 13   |
+    /// | `Send`               | 14   |
+    /// | `Recv`               | 15   |
+    /// | `RequestWithBackoff` | 16   |
+    /// | `TimeOutJitter`      | 17   |
+    /// | `Put`                | 18   |
+    /// | `Get`                | 19   |
+    /// | `RequestAffinity`    | 20   |
+    /// | `LabeledTimeOut`     | 21   |
+    /// | `LabeledEvent`       | 22   |
+    /// | `SetTimer`           | 23   |
+    /// | `CancelTimer`        | 24   |
+    /// | `RestartTimer`       | 25   |
+    /// | `Sleep`              | 26   |
+    /// | `Provide`            | 27   |
+    /// | `LabeledTrace`       | 28   |
+    pub fn code(&self) -> u8 {
+        match self {
+            Effect::TimeOut(_) => 0,
+            Effect::Event { .. } => 1,
+            Effect::Request(_) => 2,
+            Effect::Release(_) => 3,
+            Effect::Wait => 4,
+            Effect::Trace => 5,
+            Effect::FailResource(_) => 6,
+            Effect::RepairResource(_) => 7,
+            Effect::AcquireLease(_) => 8,
+            Effect::ReleaseLease(_) => 9,
+            Effect::AcquireRead(_) => 10,
+            Effect::AcquireWrite(_) => 11,
+            Effect::UpgradeToWrite(_) => 12,
+            Effect::ReleaseLock(_) => 13,
+            Effect::Send(_) => 14,
+            Effect::Recv(_) => 15,
+            Effect::RequestWithBackoff { .. } => 16,
+            Effect::TimeOutJitter { .. } => 17,
+            Effect::Put { .. } => 18,
+            Effect::Get { .. } => 19,
+            Effect::RequestAffinity { .. } => 20,
+            Effect::LabeledTimeOut { .. } => 21,
+            Effect::LabeledEvent { .. } => 22,
+            Effect::SetTimer(_) => 23,
+            Effect::CancelTimer(_) => 24,
+            Effect::RestartTimer { .. } => 25,
+            Effect::Sleep(_) => 26,
+            Effect::Provide(_) => 27,
+            Effect::LabeledTrace(_) => 28,
+        }
+    }
+}
+
 /// Identifies a process. Can be used to resume it from another one and to schedule it.
 pub type ProcessId = usize;
 /// Identifies a resource. Can be used to request and release it.
 pub type ResourceId = usize;
+/// Identifies a reader-writer lock. Can be used to acquire and release it.
+pub type RwLockId = usize;
+/// Identifies a channel between processes.
+pub type ChannelId = usize;
+/// Identifies a container. Can be used to put and get quantities.
+pub type ContainerId = usize;
+/// Identifies a probe. Can be used to retrieve the series of values it sampled.
+pub type ProbeId = usize;
 /// The type of each `Process` generator
+#[cfg(feature = "nightly")]
 pub type SimGen<T> = dyn Generator<SimContext<T>, Yield = T, Return = ()> + Unpin;
 
 #[derive(Debug)]
@@ -166,6 +548,101 @@ struct Resource<T> {
     allocated: usize,
     available: usize,
     queue: VecDeque<Event<T>>,
+    /// Number of requests that can wait in the queue, beyond which the
+    /// requests are rejected
+    queue_cap: Option<usize>,
+    /// How the requests are grouped, for a batch resource
+    batch: Option<Batch>,
+    /// Processes waiting to meet a requester, for a rendezvous
+    providers: Option<VecDeque<Event<T>>>,
+    /// Number of requests granted so far
+    grants: usize,
+    /// Sum of the time waited in queue by the granted requests
+    total_wait: f64,
+    /// Time waited in queue by each granted request, if recorded
+    waits: Option<Vec<f64>>,
+    /// Streaming estimates of quantiles of the time waited in queue
+    wait_quantiles: Vec<P2Quantile>,
+    /// A failed resource does not grant requests until it is repaired
+    failed: bool,
+    /// Number of processes sharing the lease on a unit of the resource
+    lease_holders: usize,
+    /// Processes waiting for a unit to start a lease
+    lease_queue: VecDeque<Event<T>>,
+    /// Processes holding a unit granted by a `Request`, and since when
+    holders: Vec<(ProcessId, f64)>,
+    /// Process holding each unit granted by a `Request`, if any
+    units: Vec<Option<ProcessId>>,
+    /// Units preferred by the processes waiting with `RequestAffinity`
+    preferred: BTreeMap<ProcessId, usize>,
+    /// Completed holds, as acquire time, release time and holder
+    hold_log: Vec<(f64, f64, ProcessId)>,
+    /// Units in use to remove when they are released, after the capacity
+    /// of the resource shrank
+    retiring: usize,
+    /// The capacity from each time it changed, starting from the start
+    /// time of the simulation, if it ever changed
+    capacity_log: Vec<(f64, usize)>,
+    /// The schedule changing the capacity, if any
+    schedule: Option<Schedule>,
+    /// Time slice after which a holder is preempted, for a CPU
+    quantum: Option<f64>,
+    /// Events of the preempted processes waiting for the resource again,
+    /// with the time that was left until each of them
+    preempted: BTreeMap<ProcessId, Vec<(f64, Event<T>)>>,
+    /// The cost of the usage of the resource
+    cost: Cost,
+}
+
+/// An action performed by the simulation itself at a scheduled time,
+/// rather than by a process.
+#[derive(Debug, Copy, Clone)]
+enum Internal {
+    /// Sample a probe
+    Probe(ProbeId),
+    /// Preempt a process holding a CPU since the given time
+    Preempt {
+        resource: ResourceId,
+        process: ProcessId,
+        since: f64,
+    },
+    /// Inject a fault
+    Fault(Fault),
+    /// Change the capacity of a resource, as set by its schedule
+    Shift {
+        resource: ResourceId,
+        index: usize,
+        cycle: u64,
+        generation: u32,
+    },
+}
+
+/// An event scheduled by the simulation itself. Internal events run
+/// before the process events scheduled at the same time.
+#[derive(Debug, Copy, Clone)]
+struct InternalEvent {
+    time: f64,
+    seq: u64,
+    action: Internal,
+}
+
+/// A probe registered with `Simulation::add_probe`.
+struct Probe<T: SimState + Clone, G> {
+    interval: f64,
+    /// Taken out while it is called, since it borrows the simulation
+    sample: Option<Box<ProbeFn<T, G>>>,
+    series: Vec<(f64, f64)>,
+}
+
+/// The type of the closures sampled by a probe
+pub type ProbeFn<T, G = NoStaticProcess> = dyn FnMut(&Simulation<T, G>) -> f64;
+
+/// The sending half of a subscription created with `subscribe`
+/// or `subscribe_bounded`.
+#[cfg(feature = "std")]
+enum Subscriber<T> {
+    Unbounded(Sender<(Event<T>, T)>),
+    Bounded(SyncSender<(Event<T>, T)>),
 }
 
 /// This struct provides the methods to create and run the simulation
@@ -176,13 +653,167 @@ struct Resource<T> {
 ///
 /// See the crate-level documentation for more information about how the
 /// simulation framework works
-pub struct Simulation<T: SimState + Clone> {
+///
+/// The processes of type `G`, created with `create_static_process`, are
+/// stored inline and resumed with static dispatch. By default there are
+/// none, and all the processes are boxed.
+pub struct Simulation<T: SimState + Clone, G = NoStaticProcess> {
     time: f64,
+    /// The time the simulation starts from, set with `with_start_time`
+    start_time: f64,
     steps: usize,
-    processes: Vec<Option<Box<SimGen<T>>>>,
-    future_events: BinaryHeap<Reverse<Event<T>>>,
+    processes: Vec<Option<Process<T, G>>>,
+    future_events: BinaryHeap<Queued<T>>,
     processed_events: Vec<(Event<T>, T)>,
+    /// Offsets in the log of the events of each process
+    log_index: Vec<Vec<u32>>,
+    statuses: Vec<Option<StatusTrack>>,
+    /// Effects yielded by each process, when recorded
+    effect_history: Option<Vec<Vec<(f64, Effect)>>>,
+    force_log_all: bool,
+    /// Number of pending events of each process at each time, tracked
+    /// while the wake-ups are de-duplicated
+    pending_wakeups: Option<BTreeMap<(ProcessId, u64), usize>>,
+    /// Pending events of the suspended processes, with the time that was
+    /// left until each of them
+    suspended: BTreeMap<ProcessId, Vec<(f64, Event<T>)>>,
+    /// Processes killed by a `Fault`, whose new events are ignored
+    killed: BTreeSet<ProcessId>,
+    /// Events scheduled with `schedule_event_after`, by the sequence
+    /// number of the event they wait for, with the delay as their time
+    dependents: BTreeMap<u64, Vec<Event<T>>>,
+    /// Sequence numbers of the events dropped without being processed
+    canceled: BTreeSet<u64>,
+    dropped_dependents: Vec<Event<T>>,
+    /// Whether each process was ever scheduled
+    scheduled: Vec<bool>,
+    /// Number of processes that were ever scheduled
+    started: usize,
+    /// Number of completed processes
+    completed: usize,
+    /// Number of events accepted in the log
+    logged_events: usize,
+    debug_events: Vec<(Event<T>, T)>,
     resources: Vec<Resource<T>>,
+    allocators: Vec<Box<dyn Allocator<T>>>,
+    rwlocks: Vec<RwLock<T>>,
+    channels: Vec<ChannelState<T>>,
+    containers: Vec<Container<T>>,
+    /// Messages received from channels, waiting for their receivers to
+    /// be resumed
+    deliveries: BTreeMap<ProcessId, Message>,
+    /// Processes whose last request was rejected, waiting to be resumed
+    rejected: BTreeSet<ProcessId>,
+    /// Timers set by the processes, indexed by `TimerId`
+    timers: Vec<Timer>,
+    /// Timer set, restarted or canceled by each process waiting to be
+    /// resumed
+    timer_replies: BTreeMap<ProcessId, TimerReply>,
+    /// Number of retries done so far by the processes requesting a
+    /// resource with a backoff, and the sequence number of the event
+    /// of their next retry
+    backoffs: BTreeMap<ProcessId, (usize, u64)>,
+    /// Resource of each scheduled timeout of a batch, by sequence number,
+    /// or `None` if it was disarmed but is still queued, to be skipped
+    batch_timeouts: BTreeMap<u64, Option<ResourceId>>,
+    #[cfg(feature = "std")]
+    subscribers: Vec<Subscriber<T>>,
+    #[cfg(feature = "std")]
+    dropped_events: usize,
+    resume_profile: Option<Vec<ResumeStats>>,
+    tiebreak: TieBreak,
+    infinite_time: InfiniteTimePolicy,
+    release_order: ReleaseOrder,
+    livelock_limit: Option<usize>,
+    invariant_checks: bool,
+    /// Number of effects yielded by each process at `livelock_time`
+    livelock_counts: BTreeMap<ProcessId, usize>,
+    livelock_time: f64,
+    watchdog: Watchdog,
+    /// Largest number of events in `future_events` at the start of a step
+    max_queue_depth: usize,
+    /// Counters of each run, when they can be measured
+    perf_counters: Vec<RunCounters>,
+    /// Data attached to the processes with `set_process_data`
+    process_data: BTreeMap<ProcessId, Box<dyn Any>>,
+    /// Names of the processes created with `create_processes` and its
+    /// variants
+    process_names: BTreeMap<ProcessId, String>,
+    /// Offset and rate of the clocks of the processes, set with
+    /// `set_clock_skew`
+    clock_skews: BTreeMap<ProcessId, (f64, f64)>,
+    /// Sum of the number of events in `future_events` at the start of
+    /// each step
+    queue_depth_sum: u64,
+    rewind: Rewind<T, G>,
+    /// The reason the last `run_until` stopped with
+    stop_reason: Option<StopReason>,
+    /// Time simulated by the runs before the last `reset`
+    simulated_before_reset: f64,
+    next_seq: u64,
+    priorities: Vec<i32>,
+    rng: Rng,
+    /// The seed set with `set_seed`
+    seed: u64,
+    /// Numbers drawn from the random number generator, when recorded
+    rng_log: Option<Vec<f64>>,
+    /// Recorded numbers returned instead of drawing new ones
+    rng_replay: VecDeque<f64>,
+    log_filter: Option<Box<LogFilter<T>>>,
+    effect_middleware: Vec<Box<EffectMiddleware>>,
+    event_selector: Option<Box<EventSelector>>,
+    /// Number of upcoming events given to the processes when resumed
+    context_lookahead: usize,
+    /// Available units and queue length of each resource, as given to
+    /// the last process resumed, shared with its context
+    resources_snapshot: Arc<[(usize, usize)]>,
+    /// Names of the interned labels, indexed by `LabelId`
+    labels: Vec<&'static str>,
+    markers: Vec<Marker>,
+    log_levels: Vec<LogLevel>,
+    time_display: TimeDisplay,
+    internal_events: BinaryHeap<Reverse<InternalEvent>>,
+    probes: Vec<Probe<T, G>>,
+    /// The event processed by the last step and what its process did
+    last_step: Option<(TracedEvent, StepOutcome)>,
+    /// The event processed by the last step and the state yielded by its
+    /// process, kept only while `EndCondition::LastEvent` is checked
+    last_event: Option<(Event<T>, T)>,
+    track_last_event: bool,
+    event_source: Option<Box<EventSource<T>>>,
+    /// Sequence number of the event pulled from the source that is
+    /// still scheduled, if any
+    source_pending: Option<u64>,
+    /// Factor applied to the durations of `TimeOut` effects
+    time_scale: f64,
+    /// Inputs to schedule at the next `advance_bounded`
+    inputs: Vec<(f64, ProcessId, T)>,
+    output_filter: Option<Box<LogFilter<T>>>,
+    /// Outputs to report at the next `advance_bounded`
+    outputs: Vec<(Event<T>, T)>,
+}
+
+/// The type of the filter set with `Simulation::set_log_filter`
+pub type LogFilter<T> = dyn Fn(&Event<T>, &T) -> bool;
+
+/// The type of the middleware added with `Simulation::add_effect_middleware`
+pub type EffectMiddleware = dyn FnMut(ProcessId, Effect) -> Effect;
+
+/// The type of the iterator set with `Simulation::set_event_source`,
+/// producing the time, the process and the state of each event.
+pub type EventSource<T> = dyn Iterator<Item = (f64, ProcessId, T)>;
+
+/// Wall-clock time spent resuming the generator of a process,
+/// collected when profiling is enabled with `set_resume_profiling`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResumeStats {
+    /// Total time spent in the generator
+    pub total: Duration,
+    /// Number of times the generator was resumed
+    pub count: usize,
+    /// Longest single resume
+    pub max: Duration,
 }
 
 /// The Simulation Context is the argument used to resume the generator.
@@ -190,7 +821,17 @@ pub struct Simulation<T: SimState + Clone> {
 #[derive(Debug, Clone)]
 pub struct SimContext<T> {
     time: f64,
+    /// The simulation time, that differs from `time` if the clock of the
+    /// process is skewed
+    global_time: f64,
     state: T,
+    message: Option<Message>,
+    rejected: bool,
+    timer: Option<TimerReply>,
+    wake: WakeReason,
+    /// Available units and queue length of each resource
+    resources: Arc<[(usize, usize)]>,
+    upcoming: Vec<PendingEvent>,
 }
 
 /*
@@ -202,6 +843,7 @@ pub struct ParallelSimulation {
 /// An event that can be scheduled by a process, yelding the `Event` `Effect`
 /// or by the owner of a `Simulation` through the `schedule` method
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Event<T> {
     /// Time interval between the current simulation time and the event schedule
     time: f64,
@@ -209,43 +851,360 @@ pub struct Event<T> {
     process: ProcessId,
     /// Effect that generated the event
     state: T,
+    /// Order among the events scheduled at the same time, the ties
+    /// are broken by `seq`
+    #[cfg_attr(feature = "serde", serde(skip))]
+    key: (i64, u64),
+    /// Order in which the event was scheduled
+    #[cfg_attr(feature = "serde", serde(skip))]
+    seq: u64,
+    /// Why the process is resumed by the event
+    #[cfg_attr(feature = "serde", serde(default))]
+    wake: WakeReason,
+    /// Label given when the event was scheduled, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    label: Option<LabelId>,
+}
+
+/// Why a process was resumed, returned by `SimContext::wake_reason` and
+/// `Event::wake_reason`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[non_exhaustive]
+pub enum WakeReason {
+    /// An event scheduled for the process occurred, e.g. at the end of an
+    /// `Effect::TimeOut` or by an `Effect::Event`.
+    #[default]
+    Scheduled,
+    /// The process was granted a unit of the resource it requested with
+    /// `Effect::Request`.
+    ResourceAcquired {
+        /// The resource granted
+        resource: ResourceId,
+        /// The unit of the resource granted
+        unit: usize,
+        /// Time the process waited in the queue of the resource
+        waited: f64,
+    },
+    /// A timer set by the process with `Effect::SetTimer` expired.
+    TimerExpired {
+        /// The timer that expired
+        timer: TimerId,
+    },
+}
+
+/// A scheduled event, offered to the selector set with
+/// `Simulation::set_event_selector` or returned by `Simulation::peek_events`.
+#[derive(Debug, Copy, Clone)]
+pub struct PendingEvent {
+    /// Time at which the event occurs
+    pub time: f64,
+    /// Process resumed by the event
+    pub process: ProcessId,
+    /// Sequence number assigned to the event when it was scheduled
+    pub seq: u64,
+    /// Effect of the state resuming the process
+    pub effect: Effect,
+    /// Label of the event, if any
+    pub label: Option<LabelId>,
 }
 
+/// The type of the selector set with `Simulation::set_event_selector`
+pub type EventSelector = dyn FnMut(&mut [PendingEvent]) -> usize;
+
+/// The predicate of `EndCondition::LastEvent`, called with the event
+/// processed by the last step and the state yielded by its process.
+pub type EventPredicate<T> = Arc<dyn Fn(&Event<T>, &T) -> bool + Send + Sync>;
+
 /// Specify which condition must be met for the simulation to stop.
-pub enum EndCondition {
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case", bound = ""))]
+pub enum EndCondition<T = Effect> {
     /// Run the simulation until a certain point in time is reached.
     Time(f64),
+    /// Run the simulation until the next event is after a certain point in
+    /// time, without processing it. When the run stops, the simulation time
+    /// is the one of the last event processed, at or before that point, or
+    /// the point itself if the run processed no event. The internal events
+    /// of the simulation due by that point, like the capacity changes of
+    /// the resources, are run too.
+    TimeExact(f64),
     /// Run the simulation until there are no more events scheduled.
     NoEvents,
     /// Execute exactly N steps of the simulation.
     NSteps(usize),
+    /// Run the simulation until all the processes that were ever scheduled
+    /// have completed. Processes that never had an event, e.g. passive
+    /// helpers, are not waited for.
+    AllProcessesCompleted,
+    /// Run the simulation until N events were accepted in the log, after
+    /// the log levels and filters.
+    LoggedEvents(usize),
+    /// Run the simulation until the predicate holds for the event just
+    /// processed, so that it stops right after that event. Steps in which
+    /// the resumed process completes, or no process is resumed, never
+    /// match. It cannot be serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    LastEvent(EventPredicate<T>),
+    /// Run the simulation until any of the conditions is met.
+    Any(Vec<EndCondition<T>>),
+    /// Run the simulation until all the conditions are met.
+    All(Vec<EndCondition<T>>),
+}
+
+impl<T> fmt::Debug for EndCondition<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EndCondition::Time(t) => f.debug_tuple("Time").field(t).finish(),
+            EndCondition::TimeExact(t) => f.debug_tuple("TimeExact").field(t).finish(),
+            EndCondition::NoEvents => f.write_str("NoEvents"),
+            EndCondition::NSteps(n) => f.debug_tuple("NSteps").field(n).finish(),
+            EndCondition::AllProcessesCompleted => f.write_str("AllProcessesCompleted"),
+            EndCondition::LoggedEvents(n) => f.debug_tuple("LoggedEvents").field(n).finish(),
+            EndCondition::LastEvent(_) => f.write_str("LastEvent(..)"),
+            EndCondition::Any(conditions) => f.debug_tuple("Any").field(conditions).finish(),
+            EndCondition::All(conditions) => f.debug_tuple("All").field(conditions).finish(),
+        }
+    }
+}
+
+impl<T> EndCondition<T> {
+    fn watches_last_event(&self) -> bool {
+        match self {
+            EndCondition::LastEvent(_) => true,
+            EndCondition::Any(conditions) | EndCondition::All(conditions) => {
+                conditions.iter().any(EndCondition::watches_last_event)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the time the run stops at for the `TimeExact` conditions,
+    /// up to which the internal events must be run even without process
+    /// events.
+    fn exact_time(&self) -> Option<f64> {
+        match self {
+            EndCondition::TimeExact(t) => Some(*t),
+            EndCondition::Any(conditions) => conditions
+                .iter()
+                .filter_map(EndCondition::exact_time)
+                .reduce(f64::min),
+            EndCondition::All(conditions) => conditions
+                .iter()
+                .filter_map(EndCondition::exact_time)
+                .reduce(f64::max),
+            _ => None,
+        }
+    }
+}
+
+/// Specify how events scheduled at the same time are ordered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TieBreak {
+    /// In the order they were scheduled.
+    Fifo,
+    /// In a random order, drawn from the seeded random number generator
+    /// of the simulation.
+    Random,
+    /// By decreasing priority of the process they resume, then in the
+    /// order they were scheduled.
+    Priority,
+    /// By increasing id of the process they resume, then in the order
+    /// they were scheduled.
+    ProcessId,
+}
+
+/// Specify what happens when a process yields an effect that would
+/// resume it, or another process, at an infinite time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum InfiniteTimePolicy {
+    /// Panic, reporting the process that yielded the effect.
+    Reject,
+    /// Drop the event, so that the process it would resume is not resumed
+    /// by it, like after `Effect::Wait`.
+    Skip,
+}
+
+/// Specify whether a process releasing a resource, a lease or a lock is
+/// resumed before or after the processes it was granted to.
+///
+/// Both are resumed at the current time, so the order is the one in which
+/// their events are scheduled: it holds with `TieBreak::Fifo`, and among
+/// processes of the same priority with `TieBreak::Priority`, while the
+/// other policies order the events by themselves.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ReleaseOrder {
+    /// The processes waiting for what was released are resumed first,
+    /// seeing the state of the simulation right after the release.
+    WaiterFirst,
+    /// The releasing process is resumed first.
+    ReleaserFirst,
+}
+
+/// Specify which events of a process are added to the log.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum LogLevel {
+    /// No event of the process is logged.
+    Off,
+    /// Only the events whose effect interacts with the simulation are
+    /// logged, while those yielding `Effect::Trace` or
+    /// `Effect::LabeledTrace`, which only carry the state of the process,
+    /// are not.
+    Effects,
+    /// All the events of the process are logged. This is the default.
+    Full,
 }
 
+/// Specify how times are formatted in the textual outputs of the
+/// simulation, such as `dump_log`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TimeDisplay {
+    /// The time as a plain number, e.g. `5400`. This is the default.
+    Raw,
+    /// The time followed by the name of its unit, e.g. `5400 s`.
+    Unit(&'static str),
+    /// The time as hours, minutes and seconds, e.g. `01:30:00.000`,
+    /// given the number of seconds in a time unit of the simulation.
+    HoursMinutesSeconds {
+        /// Number of seconds in a time unit of the simulation
+        seconds_per_unit: f64,
+    },
+    /// The time as an RFC 3339 timestamp of the clock, e.g.
+    /// `2024-01-01T01:30:00Z`.
+    #[cfg(feature = "chrono")]
+    DateTime(DateTimeClock),
+}
+
+/// Names the condition that caused `run_until` to stop the simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StopReason(pub String);
+
+impl StopReason {
+    /// The reason `run_until` stops with when no condition was met and
+    /// there are no more events to process.
+    pub fn no_events() -> StopReason {
+        StopReason(String::from("no events"))
+    }
+}
+
+/// A labeled stopping condition for `run_until`.
+pub type StopCondition<T, G = NoStaticProcess> =
+    (StopReason, Box<dyn Fn(&Simulation<T, G>) -> bool>);
+
 impl<T: SimState + Clone> Simulation<T> {
     /// Create a new `Simulation` environment.
     pub fn new() -> Simulation<T> {
         Simulation::<T>::default()
     }
 
+    /// Create a new `Simulation` environment ordering the events scheduled
+    /// at the same time with `tiebreak`.
+    pub fn with_tiebreak(tiebreak: TieBreak) -> Simulation<T> {
+        Simulation {
+            tiebreak,
+            ..Simulation::default()
+        }
+    }
+
+    /// Create a new `Simulation` environment whose time starts from `time`
+    /// instead of 0.
+    ///
+    /// Events scheduled with a delay, e.g. with `schedule_event` or
+    /// `Effect::TimeOut`, are relative to the start time, so a model run
+    /// from `time` processes the same events as from 0, shifted by `time`.
+    /// The times of `EndCondition::Time` and `schedule_event_at` remain
+    /// absolute, and the statistics over time, like
+    /// `resource_utilization`, start from `time`.
+    pub fn with_start_time(time: f64) -> Simulation<T> {
+        assert!(
+            time.is_finite(),
+            "The start time must be finite, got {}",
+            time
+        );
+        Simulation {
+            time,
+            start_time: time,
+            ..Simulation::default()
+        }
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a new `Simulation` environment that can store the processes
+    /// of type `G` inline, see `create_static_process`.
+    pub fn with_static_processes() -> Simulation<T, G> {
+        Simulation::empty()
+    }
+
     /// Returns the current simulation time
     pub fn time(&self) -> f64 {
         self.time
     }
 
+    /// Returns the time the simulation started from, 0 unless it was
+    /// created with `with_start_time`.
+    pub fn start_time(&self) -> f64 {
+        self.start_time
+    }
+
     /// Returns the log of processed events
     pub fn processed_events(&self) -> &[(Event<T>, T)] {
         self.processed_events.as_slice()
     }
 
+    /// Iterate over the logged events of process `id`, in the order they
+    /// were processed.
+    ///
+    /// The events are looked up in an index of the log by process, so
+    /// that this takes a time proportional to their number.
+    pub fn events_for_process(&self, id: ProcessId) -> impl Iterator<Item = &(Event<T>, T)> + '_ {
+        let offsets = self.log_index.get(id).map_or(&[][..], Vec::as_slice);
+        offsets
+            .iter()
+            .map(move |&i| &self.processed_events[i as usize])
+    }
+
+    /// Returns the number of bytes allocated by the index of the log by
+    /// process, used by `events_for_process`.
+    pub fn log_index_memory(&self) -> usize {
+        self.log_index.capacity() * mem::size_of::<Vec<u32>>()
+            + self
+                .log_index
+                .iter()
+                .map(|offsets| offsets.capacity() * mem::size_of::<u32>())
+                .sum::<usize>()
+    }
+
+    /// Returns the number of processes that did not complete yet.
+    pub fn live_process_count(&self) -> usize {
+        self.processes.iter().filter(|p| p.is_some()).count()
+    }
+
     /// Create a process.
     ///
     /// For more information about a process, see the crate level documentation
     ///
     /// Returns the identifier of the process.
+    #[cfg(feature = "nightly")]
     pub fn create_process(
         &mut self,
         process: Box<dyn Generator<SimContext<T>, Yield = T, Return = ()> + Unpin>,
     ) -> ProcessId {
+        self.add_process(Process::Generator(process))
+    }
+
+    fn add_process(&mut self, process: Process<T, G>) -> ProcessId {
         let id = self.processes.len();
         self.processes.push(Some(process));
         id
@@ -258,310 +1217,2216 @@ impl<T: SimState + Clone> Simulation<T> {
     /// Returns the identifier of the resource
     pub fn create_resource(&mut self, n: usize) -> ResourceId {
         let id = self.resources.len();
-        self.resources.push(Resource {
-            allocated: n,
-            available: n,
-            queue: VecDeque::new(),
-        });
+        self.resources.push(Resource::new(n));
+        self.allocators.push(Box::new(FifoAllocator));
+        id
+    }
+
+    /// Create a new finite resource like `create_resource`, whose
+    /// queue holds at most `queue_cap` waiting requests.
+    ///
+    /// A request that would have to wait while the queue is full is
+    /// rejected: the process is resumed right away, and
+    /// `SimContext::rejected` returns `true`. With a zero capacity the
+    /// requests are either granted immediately or rejected, like the
+    /// blocked customers of a loss system.
+    pub fn create_resource_with_queue_cap(&mut self, n: usize, queue_cap: usize) -> ResourceId {
+        let id = self.create_resource(n);
+        self.resources[id].queue_cap = Some(queue_cap);
         id
     }
 
+    /// Start estimating the quantile `q` of the time waited in queue by
+    /// the requests of resource `id`, from the next granted request on.
+    ///
+    /// No quantile is estimated by default, since each one is updated on
+    /// every grant of the resource.
+    pub fn track_wait_time_quantile(&mut self, id: ResourceId, q: f64) {
+        let res = &mut self.resources[id];
+        if res.wait_quantiles.iter().all(|e| e.quantile() != q) {
+            res.wait_quantiles.push(P2Quantile::new(q));
+        }
+    }
+
+    /// Returns an estimate of the quantile `q` of the time waited in queue
+    /// by the granted requests of resource `id`, or NaN if no request was
+    /// granted since it is tracked.
+    ///
+    /// The estimate is updated on each grant using constant memory, with
+    /// the P² algorithm. Returns `None` if the quantile is not tracked, see
+    /// `track_wait_time_quantile`.
+    pub fn wait_time_quantile(&self, id: ResourceId, q: f64) -> Option<f64> {
+        self.resources[id]
+            .wait_quantiles
+            .iter()
+            .find(|e| e.quantile() == q)
+            .map(P2Quantile::estimate)
+    }
+
+    /// Returns the hold spans of resource `id` completed so far, as
+    /// tuples of the time a `Request` was granted, the time of the matching
+    /// `Release` and the process holding the unit, in order of release.
+    ///
+    /// Units granted as leases are not included.
+    pub fn resource_hold_log(&self, id: ResourceId) -> &[(f64, f64, ProcessId)] {
+        &self.resources[id].hold_log
+    }
+
+    /// Returns the fraction of the units of resource `id` in use by the
+    /// processes that requested it, from the start time to the current time.
+    ///
+    /// The time the units were held is divided by the integral of the
+    /// number of units over time, accounting for the changes of the
+    /// capacity of the resource. Units granted as leases are not included.
+    pub fn resource_utilization(&self, id: ResourceId) -> f64 {
+        self.busy_time(id) / self.resources[id].capacity_time(self.start_time, self.time)
+    }
+
+    /// Returns the time the units of resource `id` were held by the
+    /// processes that requested them, summed over the units.
+    pub(crate) fn busy_time(&self, id: ResourceId) -> f64 {
+        let res = &self.resources[id];
+        let held: f64 = res
+            .hold_log
+            .iter()
+            .map(|&(start, end, _)| end - start)
+            .sum();
+        let holding: f64 = res
+            .holders
+            .iter()
+            .map(|&(_, since)| self.time - since)
+            .sum();
+        held + holding
+    }
+
+    /// Returns the processes waiting for a unit of resource `id`, in the
+    /// order they are queued.
+    ///
+    /// Processes waiting for a lease are not included.
+    pub fn processes_waiting_on(&self, id: ResourceId) -> Vec<ProcessId> {
+        self.resources[id].queue.iter().map(|e| e.process).collect()
+    }
+
+    /// Returns the resources of which process `id` holds at least one unit,
+    /// in increasing order.
+    ///
+    /// Units granted as leases are not included.
+    pub fn resources_held_by(&self, id: ProcessId) -> Vec<ResourceId> {
+        self.resources
+            .iter()
+            .enumerate()
+            .filter(|(_, res)| res.holders.iter().any(|&(p, _)| p == id))
+            .map(|(r, _)| r)
+            .collect()
+    }
+
     /// Schedule a process to be executed after `time` time instants.
     /// Another way to schedule events is
     /// yielding `Effect::Event` from a process during the simulation.
+    ///
+    /// The time is relative to the current simulation time: the event
+    /// occurs at the absolute time `self.time() + time`.
+    /// See `schedule_event_at` to use an absolute time instead.
+    ///
+    /// Returns the id of the event, that `schedule_event_after` can use.
     // TODO: Review this API
-    pub fn schedule_event(&mut self, time: f64, process: ProcessId, state: T) {
-        self.future_events.push(Reverse(Event { time, process, state }));
+    pub fn schedule_event(&mut self, time: f64, process: ProcessId, state: T) -> EventId {
+        let seq = self.next_seq;
+        self.schedule_at(self.time + time, process, state);
+        self.event_id(seq)
     }
 
-    fn log_processed_event(&mut self, event: &Event<T>, sim_state: T) {
-        if sim_state.should_log() {
-            self.processed_events.push((event.clone(), sim_state));
-        }
+    /// Schedule a process to be executed at the absolute time `time`,
+    /// that must not be before the current simulation time.
+    ///
+    /// Returns the id of the event, that `schedule_event_after` can use.
+    pub fn schedule_event_at(&mut self, time: f64, process: ProcessId, state: T) -> EventId {
+        assert!(
+            time >= self.time,
+            "Cannot schedule an event at time {} before the current time {}",
+            self.format_time(time),
+            self.format_time(self.time)
+        );
+        let seq = self.next_seq;
+        self.schedule_at(time, process, state);
+        self.event_id(seq)
     }
 
-    /// Proceed in the simulation by 1 step
-    pub fn step(&mut self) {
-        self.steps += 1;
-        match self.future_events.pop() {
-            Some(Reverse(event)) => {
-                self.time = event.time;
-                let gstatepin = Pin::new(
-                    self.processes[event.process]
-                        .as_mut()
-                        .expect("ERROR. Tried to resume a completed process."),
-                )
-                .resume(SimContext {
-                    time: self.time,
-                    state: event.state.clone(),
-                });
-                // log event
-                // logging needs to happen before the processing because processing
-                // can add further events (such as resource acquired/released) and
-                // it becomes confusing if you first get a resource acquired event
-                // and only log the request for it afterwards.
-                match gstatepin.clone() {
-                    GeneratorState::Yielded(y) => {
-                        self.log_processed_event(&event, y);
-                    }
-                    GeneratorState::Complete(_) => {}
-                }
-                // process event
-                match gstatepin {
-                    GeneratorState::Yielded(y) => {
-                        let effect = y.get_effect();
-                        match effect {
-                            Effect::TimeOut(t) => self.future_events.push(Reverse(Event {
-                                time: self.time + t,
-                                process: event.process,
-                                state: y,
-                            })),
-                            Effect::Event { time, process } => {
-                                let e = Event {
-                                    time: time + self.time,
-                                    process,
-                                    state: y,
-                                };
-                                self.future_events.push(Reverse(e))
-                            }
-                            Effect::Request(r) => {
-                                let mut res = &mut self.resources[r];
-                                if res.available == 0 {
-                                    // enqueue the process
-                                    res.queue.push_back(event);
-                                } else {
-                                    // the process can use the resource immediately
-                                    self.future_events.push(Reverse(Event {
-                                        time: self.time,
-                                        process: event.process,
-                                        state: y,
-                                    }));
-                                    res.available -= 1;
-                                }
-                            }
-                            Effect::Release(r) => {
-                                let res = &mut self.resources[r];
-                                match res.queue.pop_front() {
-                                    // some processes in queue: schedule the next.
-                                    Some(mut request_event) => {
-                                        request_event.time = self.time;
-                                        self.future_events.push(Reverse(request_event))
-                                    }
-                                    None => {
-                                        assert!(res.available < res.allocated);
-                                        res.available += 1;
-                                    }
-                                }
-                                // after releasing the resource the process
-                                // can be resumed
-                                self.future_events.push(Reverse(Event {
-                                    time: self.time,
-                                    process: event.process,
-                                    state: y,
-                                }))
-                            }
-                            Effect::Wait => {}
-                            Effect::Trace => {
-                                // this event is only for tracing, reschedule
-                                // immediately
-                                self.future_events.push(Reverse(Event {
-                                    time: self.time,
-                                    process: event.process,
-                                    state: y,
-                                }))
-                            }
-                        }
-                    }
-                    GeneratorState::Complete(_) => {
-                        // FIXME: removing the process from the vector would invalidate
-                        // all existing `ProcessId`s, but keeping it would be a
-                        // waste of space since it is completed.
-                        // May be worth to use another data structure.
-                        // At least let's remove the generator itself.
-                        self.processes[event.process].take();
-                    }
+    /// Set an iterator producing events to schedule, as tuples of the
+    /// absolute time of the event, the process to resume and its state.
+    ///
+    /// The iterator is consumed lazily: only one of its events is scheduled
+    /// at a time, and the next one is pulled when it is processed, so that
+    /// long traces of arrivals do not have to be loaded in advance.
+    /// The events must be produced in non-decreasing order of time, and
+    /// never before the current simulation time.
+    /// Setting a new source replaces the previous one, but an event already
+    /// pulled from it stays scheduled.
+    pub fn set_event_source(&mut self, src: Box<EventSource<T>>) {
+        self.event_source = Some(src);
+        self.source_pending = None;
+        self.pull_event_source();
+    }
+
+    /// Schedule the next event of the event source, if any.
+    fn pull_event_source(&mut self) {
+        let next = self.event_source.as_mut().and_then(|src| src.next());
+        match next {
+            Some((time, process, state)) => {
+                assert!(
+                    time >= self.time,
+                    "The event source produced an event at time {} before the current time {}",
+                    self.format_time(time),
+                    self.format_time(self.time)
+                );
+                let seq = self.next_seq;
+                self.schedule_at(time, process, state);
+                if self.next_seq > seq {
+                    self.source_pending = Some(seq);
+                } else {
+                    // coalesced with a pending wake-up, pull the next one
+                    self.pull_event_source();
                 }
             }
-            None => {}
+            None => {
+                self.event_source = None;
+                self.source_pending = None;
+            }
         }
     }
 
-    /// Run the simulation until and ending condition is met.
-    pub fn run(mut self, until: EndCondition) -> Simulation<T> {
-        while !self.check_ending_condition(&until) {
-            self.step();
-        }
-        self
+    /// Enable or disable the de-duplication of wake-ups.
+    ///
+    /// When enabled, an event is not scheduled if the process it resumes
+    /// already has a pending event at the same time, so that a process
+    /// woken up by several sources at once is activated only once, with the
+    /// state of the first event.
+    pub fn set_dedup_wakeups(&mut self, enabled: bool) {
+        self.pending_wakeups = if enabled { Some(BTreeMap::new()) } else { None };
+        self.track_pending_wakeups();
     }
-    /*
-        pub fn nonblocking_run(mut self, until: EndCondition) -> thread::JoinHandle<Simulation> {
-            thread::spawn(move || {
-                self.run(until)
-            })
-        }
-    */
 
-    /// Return `true` if the ending condition was met, `false` otherwise.
-    fn check_ending_condition(&self, ending_condition: &EndCondition) -> bool {
-        match &ending_condition {
-            EndCondition::Time(t) => self.time >= *t,
-            EndCondition::NoEvents => self.future_events.len() == 0,
-            EndCondition::NSteps(n) => self.steps == *n,
+    /// Count again the pending events of each process after the events
+    /// were moved, if the wake-ups are de-duplicated.
+    fn track_pending_wakeups(&mut self) {
+        if let Some(pending) = &mut self.pending_wakeups {
+            pending.clear();
+            let timeouts = &self.batch_timeouts;
+            for Queued(event) in self.future_events.iter() {
+                if let Some(None) = timeouts.get(&event.seq) {
+                    continue;
+                }
+                *pending
+                    .entry((event.process, event.time.to_bits()))
+                    .or_insert(0) += 1;
+            }
         }
     }
-}
 
-impl<T> SimContext<T> {
-    /// Returns current simulation time.
-    pub fn time(&self) -> f64 {
-        self.time
+    /// Set the seed of the random number generator of the simulation.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+        self.seed = seed;
     }
 
-    /// Returns the `State` that caused the process to wake up
-    pub fn state(&self) -> &T {
-        &self.state
+    /// Record the numbers drawn from the random number generator of the
+    /// simulation, e.g. by `TieBreak::Random` and `Effect::TimeOutJitter`,
+    /// so that they can be replayed in another run with `replay_rng`.
+    ///
+    /// Stopping the recording discards the numbers recorded so far.
+    pub fn record_rng_draws(&mut self, record: bool) {
+        self.rng_log = if record { Some(Vec::new()) } else { None };
     }
 
-    /// Returns the `State` that caused the process to wake up
-    pub fn into_state(self) -> T {
-        self.state
+    /// Returns the numbers drawn from the random number generator since
+    /// they started being recorded, in `[0, 1)`.
+    pub fn rng_log(&self) -> &[f64] {
+        self.rng_log.as_deref().unwrap_or(&[])
     }
-}
 
-impl<T> Event<T> {
-    pub fn time(&self) -> f64 {
-        self.time
+    /// Use `draws`, e.g. recorded by a previous run, in order as the next
+    /// numbers of the random number generator of the simulation.
+    ///
+    /// New numbers are drawn from the generator once they are exhausted.
+    pub fn replay_rng(&mut self, draws: Vec<f64>) {
+        self.rng_replay = draws.into();
     }
 
-    pub fn process(&self) -> ProcessId {
-        self.process
+    /// Returns the next number of the random number generator, uniformly
+    /// distributed in `[0, 1)`.
+    fn draw(&mut self) -> f64 {
+        let x = match self.rng_replay.pop_front() {
+            Some(x) => x,
+            None => self.rng.next_f64(),
+        };
+        if let Some(log) = &mut self.rng_log {
+            log.push(x);
+        }
+        x
     }
 
-    pub fn state(&self) -> &T {
-        &self.state
+    /// Set how the events scheduled at the same time are ordered.
+    ///
+    /// The default is `TieBreak::Fifo`. The policy applies to the events
+    /// scheduled after this call.
+    pub fn set_same_time_tiebreak(&mut self, tiebreak: TieBreak) {
+        self.tiebreak = tiebreak;
     }
-}
 
-impl<T: SimState + Clone> Default for Simulation<T> {
-    fn default() -> Self {
-        Simulation::<T> {
-            time: 0.0,
-            steps: 0,
-            processes: Vec::default(),
-            future_events: BinaryHeap::default(),
-            processed_events: Vec::default(),
-            resources: Vec::default(),
-        }
+    /// Set what happens when an `Effect::TimeOut` or an `Effect::Event`
+    /// would schedule an event at an infinite time, which would corrupt the
+    /// simulation clock.
+    ///
+    /// The default is `InfiniteTimePolicy::Reject`.
+    pub fn set_infinite_time_policy(&mut self, policy: InfiniteTimePolicy) {
+        self.infinite_time = policy;
     }
-}
 
-impl<T> PartialEq for Event<T> {
-    fn eq(&self, other: &Event<T>) -> bool {
-        self.time == other.time
+    /// Set whether a process releasing a resource, a lease or a lock is
+    /// resumed before or after the processes it was granted to.
+    ///
+    /// The default is `ReleaseOrder::WaiterFirst`.
+    pub fn set_release_order(&mut self, order: ReleaseOrder) {
+        self.release_order = order;
     }
-}
-
-impl<T> Eq for Event<T> {}
 
-impl<T> PartialOrd for Event<T> {
-    fn partial_cmp(&self, other: &Event<T>) -> Option<Ordering> {
-        self.time.partial_cmp(&other.time)
+    /// Set the maximum number of effects a process can yield at the same
+    /// time, after which the simulation panics reporting a livelock, or
+    /// `None` to disable the check.
+    ///
+    /// A process yielding e.g. `Effect::Trace` in a loop keeps the clock
+    /// from advancing, so that a run until some time never returns. The
+    /// counts are reset whenever the clock advances. The default is
+    /// `Some(10_000)`.
+    pub fn set_livelock_limit(&mut self, limit: Option<usize>) {
+        self.livelock_limit = limit;
     }
-}
 
-impl<T> Ord for Event<T> {
-    fn cmp(&self, other: &Event<T>) -> Ordering {
-        match self.time.partial_cmp(&other.time) {
-            Some(o) => o,
-            None => panic!("Event time was uncomparable. Maybe a NaN"),
+    /// Set the priority of a process, used by `TieBreak::Priority`.
+    /// All processes have priority 0 by default.
+    pub fn set_process_priority(&mut self, id: ProcessId, priority: i32) {
+        if self.priorities.len() <= id {
+            self.priorities.resize(id + 1, 0);
         }
+        self.priorities[id] = priority;
     }
-}
 
-impl SimState for Effect {
-    fn get_effect(&self) -> Effect {
-        *self
-    }
-    fn set_effect(&mut self, e: Effect) {
-        *self = e;
-    }
-    fn should_log(&self) -> bool {
-        true
+    /// Shift all the pending events of process `id` by `delta` time units.
+    ///
+    /// Panics if an event would be moved before the current simulation time.
+    pub fn shift_process_events(&mut self, id: ProcessId, delta: f64) {
+        let now = self.time;
+        assert!(
+            self.pending_events()
+                .all(|e| e.process != id || e.time + delta >= now),
+            "Shifting the events of process {} by {} would move an event in the past",
+            id,
+            delta
+        );
+        let mut events = self.take_future_events();
+        for Queued(event) in events.iter_mut().filter(|Queued(e)| e.process == id) {
+            event.time += delta;
+        }
+        self.future_events = events.into();
+        self.track_pending_wakeups();
     }
-}
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        use crate::{Effect, Simulation};
 
-        let mut s = Simulation::new();
-        let p = s.create_process(Box::new(|_| {
-            let mut a = 0.0;
-            loop {
-                a += 1.0;
+    /// Suspend process `id`, removing its pending events and recording the
+    /// time left until each of them, e.g. to model a machine powered off.
+    ///
+    /// Requests waiting in the queue of a resource are not affected.
+    pub fn suspend_process(&mut self, id: ProcessId) {
+        assert!(
+            !self.suspended.contains_key(&id),
+            "Process {} is already suspended",
+            id
+        );
+        let now = self.time;
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .take_future_events()
+            .into_iter()
+            .partition(|Queued(e)| e.process == id);
+        self.future_events = kept.into();
+        let mut removed: Vec<_> = removed.into_iter().map(|Queued(e)| e).collect();
+        removed.sort_by(Event::queue_order);
+        self.suspended
+            .insert(id, removed.into_iter().map(|e| (e.time - now, e)).collect());
+        self.track_pending_wakeups();
+    }
 
-                yield Effect::TimeOut(a);
-            }
-        }));
-        s.schedule_event(0.0, p, Effect::TimeOut(0.));
-        s.step();
-        s.step();
-        assert_eq!(s.time(), 1.0);
-        s.step();
-        assert_eq!(s.time(), 3.0);
-        s.step();
-        assert_eq!(s.time(), 6.0);
-    }
-
-    #[test]
-    fn run() {
-        use crate::{Effect, EndCondition, Simulation};
-
-        let mut s = Simulation::new();
-        let p = s.create_process(Box::new(|_| {
-            let tik = 0.7;
-            loop {
-                println!("tik");
-                yield Effect::TimeOut(tik);
+    /// Resume process `id`, suspended with `suspend_process`, scheduling
+    /// its events again after the time that was left until each of them,
+    /// counted from the current time.
+    pub fn resume_process(&mut self, id: ProcessId) {
+        let events = self
+            .suspended
+            .remove(&id)
+            .unwrap_or_else(|| panic!("Process {} is not suspended", id));
+        for (left, event) in events {
+            let seq = self.next_seq;
+            let from_source = self.source_pending == Some(event.seq);
+            let old = event.seq;
+            self.schedule_at(self.time + left, event.process, event.state);
+            self.forward_dependents(old, (self.next_seq > seq).then_some(seq));
+            if from_source {
+                // keep pulling the event source after the moved event
+                if self.next_seq > seq {
+                    self.source_pending = Some(seq);
+                } else {
+                    self.pull_event_source();
+                }
             }
-        }));
-        s.schedule_event(0.0, p, Effect::TimeOut(0.));
-        let s = s.run(EndCondition::Time(10.0));
-        println!("{}", s.time());
-        assert!(s.time() >= 10.0);
+        }
+    }
+
+    /// Scale the durations of the simulation by `factor`, e.g. to rerun
+    /// a model with all its durations doubled.
+    ///
+    /// The time left until each pending event is multiplied by `factor`,
+    /// and so are the durations of the `TimeOut` effects yielded from now
+    /// on. Calling this method again composes the factors.
+    pub fn scale_future_times(&mut self, factor: f64) {
+        assert!(
+            factor > 0.0 && factor.is_finite(),
+            "The time scaling factor must be positive and finite, got {}",
+            factor
+        );
+        let now = self.time;
+        let mut events = self.take_future_events();
+        for Queued(event) in events.iter_mut() {
+            event.time = now + (event.time - now) * factor;
+        }
+        self.future_events = events.into();
+        self.track_pending_wakeups();
+        self.time_scale *= factor;
     }
 
-    #[test]
-    fn resource() {
-        use crate::{Effect, EndCondition::NoEvents, Simulation};
+    /// Enable or disable the profiling of process resumes.
+    ///
+    /// When enabled, each step measures the wall-clock time spent in the
+    /// generator of the resumed process, excluding the time spent by the
+    /// simulation itself. Disabling it discards the collected data.
+    #[cfg(feature = "std")]
+    pub fn set_resume_profiling(&mut self, enabled: bool) {
+        if enabled {
+            self.resume_profile.get_or_insert_with(Vec::new);
+        } else {
+            self.resume_profile = None;
+        }
+    }
 
-        let mut s = Simulation::new();
-        let r = s.create_resource(1);
+    /// Returns the profiling data of the processes that were resumed while
+    /// profiling was enabled, sorted by decreasing total time.
+    pub fn resume_profile(&self) -> Vec<(ProcessId, ResumeStats)> {
+        let mut profile: Vec<_> = self
+            .resume_profile
+            .iter()
+            .flatten()
+            .copied()
+            .enumerate()
+            .filter(|(_, stats)| stats.count > 0)
+            .collect();
+        profile.sort_by_key(|(_, stats)| Reverse(stats.total));
+        profile
+    }
 
-        // simple process that lock the resource for 7 time units
-        let p1 = s.create_process(Box::new(move |_| {
-            yield Effect::Request(r);
-            yield Effect::TimeOut(7.0);
-            yield Effect::Release(r);
-        }));
-        // simple process that holds the resource for 3 time units
-        let p2 = s.create_process(Box::new(move |_| {
-            yield Effect::Request(r);
-            yield Effect::TimeOut(3.0);
-            yield Effect::Release(r);
-        }));
+    /// Set how times are formatted in the textual outputs.
+    pub fn set_time_display(&mut self, time_display: TimeDisplay) {
+        self.time_display = time_display;
+    }
 
-        // let p1 start immediately...
-        s.schedule_event(0.0, p1, Effect::TimeOut(0.));
-        // let p2 start after 2 t.u., when r is not available
-        s.schedule_event(2.0, p2, Effect::TimeOut(2.));
-        // p2 will wait r to be free (time 7.0) and its timeout
-        // of 3.0 t.u. The simulation will end at time 10.0
+    /// Format a time as configured with `set_time_display`.
+    pub fn format_time(&self, time: f64) -> String {
+        self.time_display.format(time)
+    }
 
-        let s = s.run(NoEvents);
-        println!("{:?}", s.processed_events());
-        assert_eq!(s.time(), 10.0);
+    /// Returns the log of processed events as text, one line per event
+    /// with its time, process and state.
+    pub fn dump_log(&self) -> String
+    where
+        T: fmt::Debug,
+    {
+        let mut out = String::new();
+        for (event, state) in self.processed_events.iter() {
+            writeln!(
+                out,
+                "{}\tprocess {}\t{:?}",
+                self.format_time(event.time),
+                event.process,
+                state
+            )
+            .unwrap();
+        }
+        out
     }
-}
+
+    /// Register a probe that samples a value from the simulation every
+    /// `interval` time units, starting from the current time.
+    ///
+    /// Probes are sampled before the events scheduled at the same time,
+    /// and only while there are events to process: they do not keep the
+    /// simulation running by themselves.
+    ///
+    /// Returns the identifier of the probe.
+    pub fn add_probe(&mut self, interval: f64, sample: Box<ProbeFn<T, G>>) -> ProbeId {
+        assert!(interval > 0.0, "The interval of a probe must be positive");
+        let id = self.probes.len();
+        self.probes.push(Probe {
+            interval,
+            sample: Some(sample),
+            series: Vec::new(),
+        });
+        self.schedule_internal(self.time, Internal::Probe(id));
+        id
+    }
+
+    /// Returns the `(time, value)` pairs sampled so far by a probe.
+    pub fn probe_series(&self, id: ProbeId) -> &[(f64, f64)] {
+        &self.probes[id].series
+    }
+
+    fn schedule_internal(&mut self, time: f64, action: Internal) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.internal_events
+            .push(Reverse(InternalEvent { time, seq, action }));
+    }
+
+    /// Run the internal events scheduled up to the time of the next
+    /// process event.
+    fn run_internal_events(&mut self) {
+        loop {
+            let next_time = match self.future_events.peek() {
+                Some(Queued(event)) => event.time,
+                // without events, only the processes waiting for a
+                // capacity change can be resumed
+                None => match self.next_awaited_shift() {
+                    Some(time) => time,
+                    None => return,
+                },
+            };
+            match self.internal_events.peek() {
+                Some(Reverse(internal)) if internal.time <= next_time => {}
+                _ => return,
+            }
+            let Reverse(internal) = self.internal_events.pop().unwrap();
+            self.run_internal(internal);
+        }
+    }
+
+    /// Run the next internal event if it is due by `t` and no process event
+    /// is, since the steps run the internal events only before a process
+    /// event. Returns whether it ran one.
+    fn run_internal_event_until(&mut self, t: f64) -> bool {
+        match self.internal_events.peek() {
+            Some(Reverse(internal)) if internal.time <= t => {}
+            _ => return false,
+        }
+        if let Some(Queued(next)) = self.future_events.peek() {
+            if next.time <= t {
+                return false;
+            }
+        }
+        let Reverse(internal) = self.internal_events.pop().unwrap();
+        self.run_internal(internal);
+        true
+    }
+
+    /// Advance the clock to `internal` and run it.
+    fn run_internal(&mut self, internal: InternalEvent) {
+        self.time = internal.time;
+        match internal.action {
+            Internal::Probe(id) => {
+                let mut sample = self.probes[id].sample.take().unwrap();
+                let value = sample(self);
+                let probe = &mut self.probes[id];
+                probe.sample = Some(sample);
+                probe.series.push((self.time, value));
+                let next = self.time + probe.interval;
+                self.schedule_internal(next, Internal::Probe(id));
+            }
+            Internal::Preempt {
+                resource,
+                process,
+                since,
+            } => self.preempt(resource, process, since),
+            Internal::Fault(fault) => self.apply_fault(fault),
+            Internal::Shift {
+                resource,
+                index,
+                cycle,
+                generation,
+            } => self.apply_shift(resource, index, cycle, generation),
+        }
+    }
+
+    /// Subscribe to the processed events.
+    ///
+    /// Every event added to the log is also sent through the returned
+    /// channel, so that it can be consumed from another thread while
+    /// the simulation is running.
+    /// If the receiver is dropped, the subscription is removed.
+    #[cfg(feature = "std")]
+    pub fn subscribe(&mut self) -> Receiver<(Event<T>, T)>
+    where
+        T: Send,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(Subscriber::Unbounded(tx));
+        rx
+    }
+
+    /// Subscribe to the processed events through a channel that buffers
+    /// at most `bound` events.
+    ///
+    /// The simulation never blocks on a slow consumer: when the channel
+    /// is full, the event is dropped for that subscriber and counted in
+    /// `dropped_events()`. Panics if `bound` is 0, since such a channel
+    /// could never hold an event.
+    #[cfg(feature = "std")]
+    pub fn subscribe_bounded(&mut self, bound: usize) -> Receiver<(Event<T>, T)>
+    where
+        T: Send,
+    {
+        assert!(bound > 0, "The bound of a subscription must be positive");
+        let (tx, rx) = mpsc::sync_channel(bound);
+        self.subscribers.push(Subscriber::Bounded(tx));
+        rx
+    }
+
+    /// Returns the number of events that were not delivered to bounded
+    /// subscribers because their channel was full.
+    #[cfg(feature = "std")]
+    pub fn dropped_events(&self) -> usize {
+        self.dropped_events
+    }
+
+    /// Add a middleware rewriting the effects yielded by the processes
+    /// before they are processed, e.g. to inject faults or delays.
+    ///
+    /// The middleware is called with the process and the effect it yielded,
+    /// or the one returned by the middleware added before it, and returns
+    /// the effect to process instead. The log keeps the yielded effects.
+    pub fn add_effect_middleware(&mut self, f: Box<EffectMiddleware>) {
+        self.effect_middleware.push(f);
+    }
+
+    /// Set a selector choosing which event is processed next, among the
+    /// events scheduled at the earliest time, when there are many.
+    ///
+    /// The selector is called with the events in the order given by the
+    /// tie-break policy, and returns the position of the event to process;
+    /// the others are queued again, and offered to the selector at the next
+    /// step together with the events the chosen one scheduled at the same
+    /// time, if any. Without a selector, the events are not collected.
+    pub fn set_event_selector<F>(&mut self, selector: F)
+    where
+        F: FnMut(&mut [PendingEvent]) -> usize + 'static,
+    {
+        self.event_selector = Some(Box::new(selector));
+    }
+
+    /// Returns the next `k` scheduled events, or all of them if there are
+    /// fewer, in the order they will be processed, without processing them.
+    ///
+    /// The order is the one given by the time and the tie-break policy;
+    /// an event selector, if set, may still pick another event among those
+    /// at the same time.
+    pub fn peek_events(&self, k: usize) -> Vec<PendingEvent> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut events: Vec<&Event<T>> = self.pending_events().collect();
+        if events.len() > k {
+            events.select_nth_unstable_by(k, |a, b| a.queue_order(b));
+            events.truncate(k);
+        }
+        events.sort_by(|a, b| a.queue_order(b));
+        events
+            .into_iter()
+            .map(|e| PendingEvent {
+                time: e.time,
+                process: e.process,
+                seq: e.seq,
+                effect: e.state.effect().into_owned(),
+                label: e.label,
+            })
+            .collect()
+    }
+
+    /// Set the number of upcoming events, as returned by `peek_events`,
+    /// that are given to the processes when they are resumed, and can be
+    /// read with `SimContext::upcoming_events`. It is 0 by default.
+    pub fn set_context_lookahead(&mut self, k: usize) {
+        self.context_lookahead = k;
+    }
+
+    /// Set a filter on the events added to the log.
+    ///
+    /// An event is logged only if both the `should_log()` method of its state
+    /// and the filter return `true`. The filter is called before the event
+    /// and the state are copied into the log.
+    pub fn set_log_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(&Event<T>, &T) -> bool + 'static,
+    {
+        self.log_filter = Some(Box::new(filter));
+    }
+
+    /// Set which events of process `id` are added to the log.
+    ///
+    /// The level is combined with `should_log()` and with the filter set
+    /// with `set_log_filter`: an event is logged only if all of them accept it.
+    pub fn set_process_log_level(&mut self, id: ProcessId, level: LogLevel) {
+        if self.log_levels.len() <= id {
+            self.log_levels.resize(id + 1, LogLevel::Full);
+        }
+        self.log_levels[id] = level;
+    }
+
+    /// Remove the filter set with `set_log_filter`.
+    pub fn clear_log_filter(&mut self) {
+        self.log_filter = None;
+    }
+
+    /// When enabled, every processed event is also recorded in a separate
+    /// debug log, returned by `debug_events()`, regardless of `should_log()`,
+    /// of the log filter and of the log levels of the processes.
+    ///
+    /// The normal log is not affected. Disabling it stops the recording but
+    /// keeps the events already recorded.
+    pub fn set_force_log_all(&mut self, enabled: bool) {
+        self.force_log_all = enabled;
+    }
+
+    /// Returns the events recorded while `set_force_log_all` was enabled.
+    pub fn debug_events(&self) -> &[(Event<T>, T)] {
+        self.debug_events.as_slice()
+    }
+
+    fn log_processed_event(&mut self, event: &Event<T>, sim_state: &T) {
+        if self.force_log_all {
+            self.debug_events.push((event.clone(), sim_state.clone()));
+        }
+        self.collect_output(event, sim_state);
+        let level = self
+            .log_levels
+            .get(event.process)
+            .copied()
+            .unwrap_or(LogLevel::Full);
+        let level_accepts = match level {
+            LogLevel::Off => false,
+            LogLevel::Effects => {
+                !matches!(*sim_state.effect(), Effect::Trace | Effect::LabeledTrace(_))
+            }
+            LogLevel::Full => true,
+        };
+        if !level_accepts {
+            return;
+        }
+        let accepted = match &self.log_filter {
+            Some(filter) => sim_state.should_log() && filter(event, sim_state),
+            None => sim_state.should_log(),
+        };
+        if accepted {
+            #[cfg(feature = "std")]
+            self.notify_subscribers(event, sim_state);
+            // the log is only appended to, so the index stays valid
+            let offset = u32::try_from(self.processed_events.len())
+                .expect("The log has more events than its index can address");
+            if self.log_index.len() <= event.process {
+                self.log_index.resize(event.process + 1, Vec::new());
+            }
+            self.log_index[event.process].push(offset);
+            self.processed_events
+                .push((event.clone(), sim_state.clone()));
+            self.logged_events += 1;
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn notify_subscribers(&mut self, event: &Event<T>, sim_state: &T) {
+        let dropped = &mut self.dropped_events;
+        self.subscribers.retain(|subscriber| match subscriber {
+            Subscriber::Unbounded(tx) => tx.send((event.clone(), sim_state.clone())).is_ok(),
+            Subscriber::Bounded(tx) => match tx.try_send((event.clone(), sim_state.clone())) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    *dropped += 1;
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            },
+        });
+    }
+
+    /// Proceed in the simulation by 1 step
+    pub fn step(&mut self) {
+        let time_before = self.time;
+        self.begin_step();
+        let event = self.pop_next_event();
+        self.finish_step(event, time_before);
+    }
+
+    /// Process `event`, popped by the step begun at `time_before`.
+    fn finish_step(&mut self, event: Option<Event<T>>, time_before: f64) {
+        if let Some(event) = event {
+            let (event, context) = match self.prepare_resume(event) {
+                Some(prepared) => prepared,
+                None => {
+                    self.check_step(time_before);
+                    return;
+                }
+            };
+            let state = self.resume_event(event.process, context);
+            self.apply_resumed(event, state);
+        }
+        self.check_step(time_before);
+        self.take_snapshot();
+    }
+
+    /// Count a new step and run the internal events due before it.
+    fn begin_step(&mut self) {
+        self.steps += 1;
+        self.record_queue_depth();
+        self.last_step = None;
+        self.last_event = None;
+        self.run_internal_events();
+    }
+
+    /// Advance the clock to `event` and build the context its process is
+    /// resumed with. Returns `None` if the event does not resume the
+    /// process, like the retry of a request.
+    fn prepare_resume(&mut self, event: Event<T>) -> Option<(Event<T>, SimContext<T>)> {
+        self.time = event.time;
+        if let Some(pending) = &mut self.pending_wakeups {
+            let key = (event.process, event.time.to_bits());
+            if let Some(count) = pending.get_mut(&key) {
+                *count -= 1;
+                if *count == 0 {
+                    pending.remove(&key);
+                }
+            }
+        }
+        if self.source_pending == Some(event.seq) {
+            self.pull_event_source();
+        }
+        if !self.dependents.is_empty() {
+            self.release_dependents(event.seq);
+        }
+        if let WakeReason::TimerExpired { timer } = event.wake {
+            self.expire_timer(timer);
+        }
+        if self.is_retry(&event) {
+            // the process is not resumed until the request is
+            // granted or it gives up
+            self.retry_request(event);
+            return None;
+        }
+        if self.is_batch_timeout(&event) {
+            self.expire_batch(event);
+            return None;
+        }
+        let message = self.take_delivery(event.process);
+        let rejected = self.rejected.remove(&event.process);
+        let timer = self.timer_replies.remove(&event.process);
+        let upcoming = self.peek_events(self.context_lookahead);
+        let local_time = self.local_time(event.process, self.time);
+        let context = SimContext {
+            time: local_time,
+            global_time: self.time,
+            state: event.state.clone(),
+            message,
+            rejected,
+            timer,
+            wake: event.wake,
+            resources: self.capture_resources(),
+            upcoming,
+        };
+        Some((event, context))
+    }
+
+    /// Returns the number of available units and the queue length of each
+    /// resource, for the context of a process. The snapshot is shared with
+    /// the contexts given before, and only allocated again once a resource
+    /// changed.
+    fn capture_resources(&mut self) -> Arc<[(usize, usize)]> {
+        let current = self
+            .resources
+            .iter()
+            .map(|res| (res.available, res.queue.len()));
+        if !current.clone().eq(self.resources_snapshot.iter().copied()) {
+            self.resources_snapshot = current.collect();
+        }
+        self.resources_snapshot.clone()
+    }
+
+    /// Resume the process `id` with `context`, profiling the resume if
+    /// enabled.
+    fn resume_event(&mut self, id: ProcessId, context: SimContext<T>) -> ProcessState<T> {
+        let process = self.processes[id]
+            .as_mut()
+            .expect("ERROR. Tried to resume a completed process.");
+        #[cfg(feature = "std")]
+        if let Some(profile) = &mut self.resume_profile {
+            let start = Instant::now();
+            let state = process.resume(context);
+            let elapsed = start.elapsed();
+            if profile.len() <= id {
+                profile.resize(id + 1, ResumeStats::default());
+            }
+            profile[id].record(elapsed);
+            return state;
+        }
+        // without a clock the resumes cannot be profiled
+        process.resume(context)
+    }
+
+    /// Log the event that resumed a process and apply what the process
+    /// did with it.
+    fn apply_resumed(&mut self, event: Event<T>, mut gstatepin: ProcessState<T>) {
+        // draw the jitter first, so that the realized delay is logged
+        if let ProcessState::Yielded(y) = &mut gstatepin {
+            self.realize_jitter(y);
+        }
+        // log event
+        // logging needs to happen before the processing because processing
+        // can add further events (such as resource acquired/released) and
+        // it becomes confusing if you first get a resource acquired event
+        // and only log the request for it afterwards.
+        let traced = TracedEvent {
+            time: event.time,
+            process: event.process,
+            seq: event.seq,
+        };
+        match &gstatepin {
+            ProcessState::Yielded(y) => {
+                self.last_step = Some((traced, StepOutcome::Yielded(y.effect().into_owned())));
+                if self.track_last_event {
+                    self.last_event = Some((event.clone(), y.clone()));
+                }
+                self.log_processed_event(&event, y);
+                self.check_livelock(event.process, &y.effect());
+                self.record_yield_status(event.process, &y.effect());
+                self.watch_progress(event.process, &y.effect());
+            }
+            ProcessState::Complete => {
+                self.last_step = Some((traced, StepOutcome::Completed));
+            }
+        }
+        // process event
+        match gstatepin {
+            ProcessState::Yielded(y) => {
+                // borrow the effect, only copying the fields the
+                // arms need
+                let mut effect = y.effect();
+                if !self.effect_middleware.is_empty() {
+                    let mut rewritten = effect.into_owned();
+                    for middleware in self.effect_middleware.iter_mut() {
+                        rewritten = middleware(event.process, rewritten);
+                    }
+                    effect = Cow::Owned(rewritten);
+                }
+                match *effect {
+                    Effect::TimeOut(t) | Effect::Sleep(t) => {
+                        self.schedule_finite(self.time + t * self.time_scale, event.process, y)
+                    }
+                    Effect::TimeOutJitter { base, dist } => {
+                        // the effect of the state was not replaced
+                        let delay = dist.sample(base, || self.draw());
+                        self.schedule_finite(self.time + delay * self.time_scale, event.process, y)
+                    }
+                    Effect::Event { time, process } => {
+                        self.schedule_finite(time + self.time, process, y)
+                    }
+                    Effect::SetTimer(duration) => self.set_timer(event.process, duration, y),
+                    Effect::CancelTimer(timer) => self.reset_timer(event.process, timer, None, y),
+                    Effect::RestartTimer { timer, duration } => {
+                        self.reset_timer(event.process, timer, Some(duration), y)
+                    }
+                    Effect::LabeledTimeOut { time, label } => self.schedule_finite_labeled(
+                        self.time + time * self.time_scale,
+                        event.process,
+                        y,
+                        Some(label),
+                    ),
+                    Effect::LabeledEvent {
+                        time,
+                        process,
+                        label,
+                    } => self.schedule_finite_labeled(time + self.time, process, y, Some(label)),
+                    Effect::Request(r) => {
+                        let process = event.process;
+                        if let Some(y) = self.request_unit(r, event, y) {
+                            // resume the process, that can find out
+                            // that it was rejected from its context
+                            self.rejected.insert(process);
+                            self.schedule_at(self.time, process, y);
+                        }
+                    }
+                    Effect::RequestWithBackoff {
+                        resource,
+                        base,
+                        max_retries,
+                    } => self.request_with_backoff(resource, base, max_retries, 0, event, y),
+                    Effect::RequestAffinity {
+                        resource,
+                        prefer_unit,
+                    } => {
+                        let process = event.process;
+                        if let Some(unit) = prefer_unit {
+                            let res = &mut self.resources[resource];
+                            assert!(
+                                unit < res.allocated,
+                                "Process {} preferred unit {} of resource {}, that has {} units",
+                                process,
+                                unit,
+                                resource,
+                                res.allocated
+                            );
+                            res.preferred.insert(process, unit);
+                        }
+                        if let Some(y) = self.request_unit(resource, event, y) {
+                            self.resources[resource].preferred.remove(&process);
+                            self.rejected.insert(process);
+                            self.schedule_at(self.time, process, y);
+                        }
+                    }
+                    Effect::Provide(r) => self.meet_requester(r, Event { state: y, ..event }),
+                    Effect::Release(r) => {
+                        self.resources[r].record_release(event.process, self.time);
+                        // after releasing the resource the process
+                        // can be resumed
+                        self.resume_releaser(event.process, y, |s| s.free_unit(r))
+                    }
+                    Effect::AcquireLease(r) => {
+                        let res = &mut self.resources[r];
+                        if res.lease_holders > 0 {
+                            // share the unit already held by the lease
+                            res.lease_holders += 1;
+                            res.record_grant(0.0);
+                            self.schedule_at(self.time, event.process, y);
+                        } else if res.available == 0 || res.failed {
+                            res.lease_queue.push_back(event);
+                        } else {
+                            res.available -= 1;
+                            res.lease_holders = 1;
+                            res.record_grant(0.0);
+                            self.schedule_at(self.time, event.process, y);
+                        }
+                    }
+                    Effect::ReleaseLease(r) => {
+                        let res = &mut self.resources[r];
+                        assert!(
+                            res.lease_holders > 0,
+                            "The lease on resource {} was released more times than it was acquired",
+                            r
+                        );
+                        res.lease_holders -= 1;
+                        let last = res.lease_holders == 0;
+                        self.resume_releaser(event.process, y, |s| {
+                            if last {
+                                s.free_unit(r);
+                            }
+                        })
+                    }
+                    Effect::FailResource(r) => {
+                        self.resources[r].failed = true;
+                        self.schedule_at(self.time, event.process, y)
+                    }
+                    Effect::RepairResource(r) => {
+                        self.resources[r].failed = false;
+                        self.grant_available(r);
+                        self.schedule_at(self.time, event.process, y)
+                    }
+                    Effect::AcquireRead(l) => self.acquire_read(l, Event { state: y, ..event }),
+                    Effect::AcquireWrite(l) => self.acquire_write(l, Event { state: y, ..event }),
+                    Effect::UpgradeToWrite(l) => {
+                        self.upgrade_to_write(l, Event { state: y, ..event })
+                    }
+                    Effect::ReleaseLock(l) => {
+                        self.resume_releaser(event.process, y, |s| s.release_lock(l))
+                    }
+                    Effect::Send(c) => self.send_message(c, Event { state: y, ..event }),
+                    Effect::Recv(c) => self.recv_message(c, Event { state: y, ..event }),
+                    Effect::Put { container, amount } => {
+                        self.put(container, amount, Event { state: y, ..event })
+                    }
+                    Effect::Get { container, amount } => {
+                        self.get(container, amount, Event { state: y, ..event })
+                    }
+                    Effect::Wait => {}
+                    Effect::Trace => {
+                        // this event is only for tracing, reschedule
+                        // immediately
+                        self.schedule_at(self.time, event.process, y)
+                    }
+                    Effect::LabeledTrace(label) => {
+                        self.push_marker(label, Some(event.process));
+                        self.schedule_at(self.time, event.process, y)
+                    }
+                }
+            }
+            ProcessState::Complete => {
+                // FIXME: removing the process from the vector would invalidate
+                // all existing `ProcessId`s, but keeping it would be a
+                // waste of space since it is completed.
+                // May be worth to use another data structure.
+                // At least let's remove the generator itself.
+                self.processes[event.process].take();
+                self.completed += 1;
+                self.record_status(event.process, ProcessStatus::Completed);
+                self.unwatch(event.process);
+            }
+        }
+    }
+
+    /// Pop the next event to process. If an event selector is set, it
+    /// chooses among the events at the earliest time, and the others are
+    /// queued again.
+    fn pop_next_event(&mut self) -> Option<Event<T>> {
+        let first = self.pop_event()?;
+        if self.event_selector.is_none() {
+            return Some(first);
+        }
+        let mut ready = alloc::vec![first];
+        while let Some(Queued(event)) = self.future_events.peek() {
+            if event.time != ready[0].time {
+                break;
+            }
+            let event = self.pop_event().unwrap();
+            ready.push(event);
+        }
+        if ready.len() == 1 {
+            return ready.pop();
+        }
+        let mut pending: Vec<PendingEvent> = ready
+            .iter()
+            .map(|e| PendingEvent {
+                time: e.time,
+                process: e.process,
+                seq: e.seq,
+                effect: e.state.effect().into_owned(),
+                label: e.label,
+            })
+            .collect();
+        let chosen = (self.event_selector.as_mut().unwrap())(&mut pending);
+        assert!(
+            chosen < ready.len(),
+            "The event selector chose event {} among {}",
+            chosen,
+            ready.len()
+        );
+        let event = ready.swap_remove(chosen);
+        for other in ready {
+            self.future_events.push(Queued(other));
+        }
+        Some(event)
+    }
+
+    /// Pop the earliest scheduled event, then drop the disarmed timeouts
+    /// that became the earliest, so that the queue always starts with an
+    /// event to process.
+    pub(crate) fn pop_event(&mut self) -> Option<Event<T>> {
+        let Queued(event) = self.future_events.pop()?;
+        self.drop_disarmed_timeouts();
+        Some(event)
+    }
+
+    /// Pop the disarmed timeouts at the start of the queue.
+    pub(crate) fn drop_disarmed_timeouts(&mut self) {
+        if self.batch_timeouts.is_empty() {
+            return;
+        }
+        while let Some(Queued(event)) = self.future_events.peek() {
+            if self.batch_timeouts.get(&event.seq) != Some(&None) {
+                break;
+            }
+            self.batch_timeouts.remove(&event.seq);
+            self.future_events.pop();
+        }
+    }
+
+    /// Iterate over the scheduled events, skipping the disarmed timeouts.
+    pub(crate) fn pending_events(&self) -> impl Iterator<Item = &Event<T>> + '_ {
+        self.future_events
+            .iter()
+            .map(|Queued(e)| e)
+            .filter(move |e| self.batch_timeouts.get(&e.seq) != Some(&None))
+    }
+
+    /// Returns the number of scheduled events, without the disarmed
+    /// timeouts.
+    pub(crate) fn pending_event_count(&self) -> usize {
+        let disarmed = self.batch_timeouts.values().filter(|r| r.is_none()).count();
+        self.future_events.len() - disarmed
+    }
+
+    /// Take all the scheduled events out of the queue, dropping the
+    /// disarmed timeouts, to change or remove some of them.
+    pub(crate) fn take_future_events(&mut self) -> Vec<Queued<T>> {
+        let mut events = mem::take(&mut self.future_events).into_vec();
+        if self.batch_timeouts.values().any(Option::is_none) {
+            let timeouts = &mut self.batch_timeouts;
+            events.retain(|Queued(e)| timeouts.get(&e.seq) != Some(&None));
+            timeouts.retain(|_, r| r.is_some());
+        }
+        events
+    }
+
+    /// Replace the `Effect::TimeOutJitter` of `state`, if any, with the
+    /// `Effect::TimeOut` of a delay drawn from its distribution.
+    fn realize_jitter(&mut self, state: &mut T) {
+        let jitter = match *state.effect() {
+            Effect::TimeOutJitter { base, dist } => Some((base, dist)),
+            _ => None,
+        };
+        if let Some((base, dist)) = jitter {
+            let delay = dist.sample(base, || self.draw());
+            state.set_effect(Effect::TimeOut(delay));
+        }
+    }
+
+    /// Count the effect yielded by `process` at the current time, panicking
+    /// if it exceeds the livelock limit.
+    fn check_livelock(&mut self, process: ProcessId, effect: &Effect) {
+        let limit = match self.livelock_limit {
+            Some(limit) => limit,
+            None => return,
+        };
+        if self.livelock_time != self.time {
+            self.livelock_time = self.time;
+            self.livelock_counts.clear();
+        }
+        let count = self.livelock_counts.entry(process).or_insert(0);
+        *count += 1;
+        let count = *count;
+        if count > limit {
+            panic!(
+                "Process {} yielded {} effects at time {} without the clock advancing, the last one being {:?}",
+                process,
+                count,
+                self.format_time(self.time),
+                effect
+            );
+        }
+    }
+
+    /// Schedule an event at the absolute time `time`, ordering it among
+    /// the events at the same time according to the tie-break policy.
+    ///
+    /// When the wake-ups are de-duplicated, the event is dropped if the
+    /// process already has a pending event at the same time.
+    fn schedule_at(&mut self, time: f64, process: ProcessId, state: T) {
+        self.schedule_woken(time, process, state, WakeReason::Scheduled)
+    }
+
+    /// Schedule an event like `schedule_at`, resuming the process for
+    /// the reason `wake`.
+    fn schedule_woken(&mut self, time: f64, process: ProcessId, state: T, wake: WakeReason) {
+        self.push_event(time, process, state, wake, None)
+    }
+
+    /// Schedule an event like `schedule_woken`, with the label `label`.
+    fn push_event(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        state: T,
+        wake: WakeReason,
+        label: Option<LabelId>,
+    ) {
+        let seq = self.next_seq;
+        if self.push_event_with_seq(time, process, state, wake, label, seq) {
+            self.next_seq += 1;
+        }
+    }
+
+    /// Push an event with the sequence number `seq` in the queue. Returns
+    /// `false` if it was dropped, because its process was killed or,
+    /// while the wake-ups are de-duplicated, is already woken at `time`.
+    pub(crate) fn push_event_with_seq(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        state: T,
+        wake: WakeReason,
+        label: Option<LabelId>,
+        seq: u64,
+    ) -> bool {
+        if !self.killed.is_empty() && self.killed.contains(&process) {
+            return false;
+        }
+        if let Some(pending) = &mut self.pending_wakeups {
+            let count = pending.entry((process, time.to_bits())).or_insert(0);
+            if *count > 0 {
+                return false;
+            }
+            *count += 1;
+        }
+        if self.scheduled.len() <= process {
+            self.scheduled.resize(process + 1, false);
+        }
+        if !self.scheduled[process] {
+            self.scheduled[process] = true;
+            self.started += 1;
+        }
+        let key = match self.tiebreak {
+            TieBreak::Fifo => (0, seq),
+            TieBreak::Random => (0, (self.draw() * (1u64 << 53) as f64) as u64),
+            TieBreak::Priority => {
+                let priority = self.priorities.get(process).copied().unwrap_or(0);
+                (-i64::from(priority), seq)
+            }
+            TieBreak::ProcessId => (process as i64, seq),
+        };
+        self.future_events.push(Queued(Event {
+            time,
+            process,
+            state,
+            key,
+            seq,
+            wake,
+            label,
+        }));
+        true
+    }
+
+    /// Schedule an event caused by an effect, applying the infinite time
+    /// policy if `time` is infinite, also when it overflowed in the sum
+    /// with the current time.
+    fn schedule_finite(&mut self, time: f64, process: ProcessId, state: T) {
+        self.schedule_finite_labeled(time, process, state, None)
+    }
+
+    /// Schedule an event like `schedule_finite`, with the label `label`.
+    fn schedule_finite_labeled(
+        &mut self,
+        time: f64,
+        process: ProcessId,
+        state: T,
+        label: Option<LabelId>,
+    ) {
+        if time.is_infinite() {
+            match self.infinite_time {
+                InfiniteTimePolicy::Reject => panic!(
+                    "An event of process {} was scheduled at infinite time",
+                    process
+                ),
+                InfiniteTimePolicy::Skip => return,
+            }
+        }
+        self.push_event(time, process, state, WakeReason::Scheduled, label);
+    }
+
+    /// Grant the available units of resource `r` to the waiting processes,
+    /// as long as its allocator and the leases take them.
+    fn grant_available(&mut self, r: ResourceId) {
+        if self.resources[r].batch.is_some() {
+            self.grant_batch(r);
+            return;
+        }
+        while self.resources[r].available > 0 && (self.grant_queued(r) || self.grant_lease_queue(r))
+        {
+        }
+    }
+
+    /// Run `release`, that can resume the processes waiting for what was
+    /// released, and resume the releasing `process` with `state`, in the
+    /// order set with `set_release_order`.
+    fn resume_releaser<F>(&mut self, process: ProcessId, state: T, release: F)
+    where
+        F: FnOnce(&mut Simulation<T, G>),
+    {
+        match self.release_order {
+            ReleaseOrder::WaiterFirst => {
+                release(self);
+                self.schedule_at(self.time, process, state);
+            }
+            ReleaseOrder::ReleaserFirst => {
+                self.schedule_at(self.time, process, state);
+                release(self);
+            }
+        }
+    }
+
+    /// Handle the request of a unit of resource `r` by the process of
+    /// `event`, that is resumed with `state` when it is granted one.
+    ///
+    /// Returns `state` back if the allocator rejected the request.
+    fn request_unit(&mut self, r: ResourceId, event: Event<T>, state: T) -> Option<T> {
+        if self.resources[r].batch.is_some() {
+            self.request_batch(r, event, state);
+            return None;
+        }
+        if self.resources[r].providers.is_some() {
+            self.meet_provider(r, event, state);
+            return None;
+        }
+        let decision = if self.resources[r].failed {
+            AllocDecision::Enqueue
+        } else {
+            self.allocators[r].on_request(&ResourceState::new(&self.resources[r]), event.process)
+        };
+        let res = &mut self.resources[r];
+        let full = res.queue_cap.is_some_and(|cap| res.queue.len() >= cap);
+        match decision {
+            AllocDecision::Enqueue if full => return Some(state),
+            AllocDecision::Enqueue => res.queue.push_back(Event { state, ..event }),
+            AllocDecision::Grant => {
+                // the process can use the resource immediately
+                assert!(
+                    res.available > 0,
+                    "The allocator of resource {} granted a unit while none was available",
+                    r
+                );
+                res.available -= 1;
+                res.record_grant(0.0);
+                res.holders.push((event.process, self.time));
+                let wake = WakeReason::ResourceAcquired {
+                    resource: r,
+                    unit: res.take_unit(event.process),
+                    waited: 0.0,
+                };
+                self.schedule_woken(self.time, event.process, state, wake);
+                self.start_slice(r, event.process);
+            }
+            AllocDecision::Reject => return Some(state),
+        }
+        None
+    }
+
+    /// Grant a unit of resource `r` to the process in its queue chosen by
+    /// its allocator, if any, resuming it at the current time.
+    ///
+    /// Returns `false` if no process was chosen.
+    fn grant_queued(&mut self, r: ResourceId) -> bool {
+        if self.resources[r].queue.is_empty() {
+            return false;
+        }
+        let chosen = self.allocators[r].on_release(&ResourceState::new(&self.resources[r]));
+        let res = &mut self.resources[r];
+        match chosen {
+            Some(i) => {
+                let request_event = res.queue.remove(i).unwrap_or_else(|| {
+                    panic!(
+                        "The allocator of resource {} chose position {} of a queue of {} processes",
+                        r,
+                        i,
+                        res.queue.len()
+                    )
+                });
+                res.available -= 1;
+                let waited = self.time - request_event.time;
+                res.record_grant(waited);
+                res.holders.push((request_event.process, self.time));
+                let unit = res.take_unit(request_event.process);
+                if !self.resume_preempted(r, request_event.process) {
+                    let wake = WakeReason::ResourceAcquired {
+                        resource: r,
+                        unit,
+                        waited,
+                    };
+                    let Event { process, state, .. } = request_event;
+                    self.schedule_woken(self.time, process, state, wake);
+                }
+                self.start_slice(r, request_event.process);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start a lease on resource `r` shared by all the processes waiting
+    /// for it, resuming them at the current time.
+    ///
+    /// Returns `false` if no process was waiting.
+    fn grant_lease_queue(&mut self, r: ResourceId) -> bool {
+        let waiting = core::mem::take(&mut self.resources[r].lease_queue);
+        if waiting.is_empty() {
+            return false;
+        }
+        self.resources[r].available -= 1;
+        self.resources[r].lease_holders = waiting.len();
+        for request_event in waiting {
+            self.resources[r].record_grant(self.time - request_event.time);
+            self.schedule_at(self.time, request_event.process, request_event.state);
+        }
+        true
+    }
+
+    /// Return a unit of resource `r`, granting it to the waiting
+    /// processes if the resource is not failed.
+    fn free_unit(&mut self, r: ResourceId) {
+        let res = &mut self.resources[r];
+        if res.retiring > 0 {
+            // the capacity shrank while the unit was in use
+            res.retiring -= 1;
+            res.trim_units();
+            return;
+        }
+        assert!(
+            res.available < res.allocated,
+            "Resource {} was released more times than it was requested",
+            r
+        );
+        res.available += 1;
+        // a failed resource keeps its queues until repaired
+        if !res.failed {
+            self.grant_available(r);
+        }
+    }
+
+    /// Enable checking after each step, also in release builds, that the
+    /// state of the simulation is consistent, panicking with a message
+    /// describing the broken invariant and the step that broke it.
+    ///
+    /// Besides the checks always done in debug builds, it verifies that
+    /// the processes holding a resource and those with pending events did
+    /// not complete, that no process waits for a resource it already holds,
+    /// and that the clock never goes back. Disabled by default.
+    pub fn enable_invariant_checks(&mut self, enabled: bool) {
+        self.invariant_checks = enabled;
+    }
+
+    /// Check the invariants after a step that started at `time_before`,
+    /// if enabled or in debug builds.
+    fn check_step(&self, time_before: f64) {
+        if !(cfg!(debug_assertions) || self.invariant_checks) {
+            return;
+        }
+        if let Some(violation) = self.find_violation(time_before) {
+            match &self.last_step {
+                Some((traced, _)) => panic!(
+                    "{}, after resuming process {} at time {}",
+                    violation,
+                    traced.process,
+                    self.format_time(traced.time)
+                ),
+                None => panic!("{}, after step {}", violation, self.steps),
+            }
+        }
+    }
+
+    /// Verify the internal consistency of the simulation, returning the
+    /// description of the first invariant that was violated, if any.
+    ///
+    /// Only the cheapest checks are done unless the invariant checks were
+    /// enabled with `enable_invariant_checks`.
+    fn find_violation(&self, time_before: f64) -> Option<String> {
+        let completed = |p: ProcessId| !matches!(self.processes.get(p), Some(Some(_)));
+        for (id, res) in self.resources.iter().enumerate() {
+            if res.available > res.allocated {
+                return Some(format!(
+                    "Resource {} has {} units available but only {} allocated",
+                    id, res.available, res.allocated
+                ));
+            }
+            for event in res.queue.iter().chain(res.lease_queue.iter()) {
+                if self.processes[event.process].is_none() {
+                    return Some(format!(
+                        "Resource {} has completed process {} in its queue",
+                        id, event.process
+                    ));
+                }
+            }
+            if !self.invariant_checks {
+                continue;
+            }
+            for &(holder, _) in res.holders.iter() {
+                if completed(holder) {
+                    return Some(format!(
+                        "Resource {} is held by process {}, that does not exist or completed",
+                        id, holder
+                    ));
+                }
+                if res.queue.iter().any(|e| e.process == holder) {
+                    return Some(format!(
+                        "Process {} is waiting for resource {}, that it already holds",
+                        holder, id
+                    ));
+                }
+            }
+        }
+        if !self.invariant_checks {
+            return None;
+        }
+        if let Some(event) = self.pending_events().find(|e| completed(e.process)) {
+            return Some(format!(
+                "An event at time {} targets process {}, that does not exist or completed",
+                self.format_time(event.time),
+                event.process
+            ));
+        }
+        if self.time < time_before {
+            return Some(format!(
+                "The clock went back from {} to {}",
+                self.format_time(time_before),
+                self.format_time(self.time)
+            ));
+        }
+        None
+    }
+
+    /// Panic if the simulation has events left to process or processes
+    /// waiting for a resource or a lease on it, listing all of them.
+    ///
+    /// Useful in tests, to check that a run reached a clean terminal state.
+    pub fn assert_no_pending(&self) {
+        let mut report = String::new();
+        let mut events: Vec<&Event<T>> = self.pending_events().collect();
+        events.sort_by(|a, b| a.queue_order(b));
+        for event in events {
+            writeln!(
+                report,
+                "  event at time {} for process {}",
+                self.format_time(event.time),
+                event.process
+            )
+            .unwrap();
+        }
+        for (id, res) in self.resources.iter().enumerate() {
+            for event in res.queue.iter() {
+                writeln!(
+                    report,
+                    "  process {} waiting for resource {}",
+                    event.process, id
+                )
+                .unwrap();
+            }
+            for event in res.lease_queue.iter() {
+                writeln!(
+                    report,
+                    "  process {} waiting for a lease on resource {}",
+                    event.process, id
+                )
+                .unwrap();
+            }
+        }
+        assert!(
+            report.is_empty(),
+            "The simulation has pending work at time {}:\n{}",
+            self.format_time(self.time),
+            report
+        );
+    }
+
+    /// Run the simulation until and ending condition is met.
+    pub fn run(mut self, until: EndCondition<T>) -> Simulation<T, G> {
+        self.run_steps(&until, |s| {
+            s.step();
+            true
+        });
+        self
+    }
+
+    /// Run the simulation until an ending condition is met, like `run`,
+    /// returning only the events logged during this call.
+    ///
+    /// The events are still added to the log returned by `processed_events`.
+    pub fn run_collecting(&mut self, until: EndCondition<T>) -> Vec<(Event<T>, T)> {
+        let start = self.processed_events.len();
+        self.run_steps(&until, |s| {
+            s.step();
+            true
+        });
+        self.processed_events[start..].to_vec()
+    }
+
+    /// Call `step` until `until` is met, recording the counters of the
+    /// run. This is the loop of all the runs with an `EndCondition`, that
+    /// differ only by how they step; `step` returns `false` to stop the
+    /// run early, e.g. when its events cannot be delivered anymore.
+    pub(crate) fn run_steps<F>(&mut self, until: &EndCondition<T>, mut step: F)
+    where
+        F: FnMut(&mut Simulation<T, G>) -> bool,
+    {
+        self.watch_last_event(until);
+        self.measure_run(|s| {
+            let steps_before = s.steps;
+            let exact_time = until.exact_time();
+            while !s.check_ending_condition(until) {
+                if exact_time.is_some_and(|t| s.run_internal_event_until(t)) {
+                    continue;
+                }
+                if !step(s) {
+                    return;
+                }
+            }
+            s.settle_exact_time(until, steps_before);
+        });
+    }
+
+    /// Run the simulation until one of the labeled conditions is met.
+    ///
+    /// The conditions are checked in order after each step, and the
+    /// reason associated with the first one that holds is returned.
+    /// If none holds once there are no more events to process, the run
+    /// stops with `StopReason::no_events()`.
+    pub fn run_until(&mut self, conditions: Vec<StopCondition<T, G>>) -> StopReason {
+        let reason = self.measure_run(|s| loop {
+            s.step();
+            for (reason, condition) in conditions.iter() {
+                if condition(s) {
+                    return reason.clone();
+                }
+            }
+            if s.check_ending_condition(&EndCondition::NoEvents) {
+                return StopReason::no_events();
+            }
+        });
+        self.stop_reason = Some(reason.clone());
+        reason
+    }
+
+    /*
+        pub fn nonblocking_run(mut self, until: EndCondition) -> thread::JoinHandle<Simulation> {
+            thread::spawn(move || {
+                self.run(until)
+            })
+        }
+    */
+
+    /// Move the simulation time to the target of the `TimeExact`
+    /// conditions in `until` that hold, if no step was executed since
+    /// `steps_before`.
+    fn settle_exact_time(&mut self, until: &EndCondition<T>, steps_before: usize) {
+        if self.steps != steps_before || !self.check_ending_condition(until) {
+            return;
+        }
+        match until {
+            EndCondition::TimeExact(t) if *t > self.time => self.time = *t,
+            EndCondition::Any(conditions) | EndCondition::All(conditions) => {
+                for condition in conditions {
+                    self.settle_exact_time(condition, steps_before);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Keep the last processed event if `until` needs it.
+    fn watch_last_event(&mut self, until: &EndCondition<T>) {
+        self.track_last_event = until.watches_last_event();
+    }
+
+    /// Return `true` if the ending condition was met, `false` otherwise.
+    fn check_ending_condition(&self, ending_condition: &EndCondition<T>) -> bool {
+        match &ending_condition {
+            EndCondition::Time(t) => self.time >= *t,
+            EndCondition::TimeExact(t) => {
+                let next_event = self.future_events.peek().map(|Queued(e)| e.time);
+                let next_internal = self.internal_events.peek().map(|Reverse(e)| e.time);
+                [next_event, next_internal]
+                    .iter()
+                    .flatten()
+                    .all(|next| next > t)
+            }
+            EndCondition::NoEvents => {
+                self.future_events.is_empty() && self.next_awaited_shift().is_none()
+            }
+            EndCondition::NSteps(n) => self.steps == *n,
+            EndCondition::AllProcessesCompleted => self.completed == self.started,
+            EndCondition::LoggedEvents(n) => self.logged_events >= *n,
+            EndCondition::LastEvent(predicate) => match &self.last_event {
+                Some((event, state)) => predicate(event, state),
+                None => false,
+            },
+            EndCondition::Any(conditions) => {
+                conditions.iter().any(|c| self.check_ending_condition(c))
+            }
+            EndCondition::All(conditions) => {
+                conditions.iter().all(|c| self.check_ending_condition(c))
+            }
+        }
+    }
+}
+
+impl<T> Resource<T> {
+    fn new(n: usize) -> Resource<T> {
+        Resource {
+            allocated: n,
+            available: n,
+            queue: VecDeque::new(),
+            queue_cap: None,
+            batch: None,
+            providers: None,
+            grants: 0,
+            total_wait: 0.0,
+            waits: None,
+            wait_quantiles: Vec::new(),
+            failed: false,
+            lease_holders: 0,
+            lease_queue: VecDeque::new(),
+            holders: Vec::new(),
+            units: alloc::vec![None; n],
+            preferred: BTreeMap::new(),
+            hold_log: Vec::new(),
+            retiring: 0,
+            capacity_log: Vec::new(),
+            schedule: None,
+            quantum: None,
+            preempted: BTreeMap::new(),
+            cost: Cost::default(),
+        }
+    }
+
+    fn record_grant(&mut self, wait: f64) {
+        self.grants += 1;
+        self.total_wait += wait;
+        if let Some(waits) = &mut self.waits {
+            waits.push(wait);
+        }
+        for estimator in self.wait_quantiles.iter_mut() {
+            estimator.add(wait);
+        }
+    }
+
+    /// Close the hold of `process` started by its earliest granted request.
+    /// Nothing is logged if the process was not holding the resource.
+    fn record_release(&mut self, process: ProcessId, time: f64) {
+        if let Some(i) = self.holders.iter().position(|&(p, _)| p == process) {
+            let (_, since) = self.holders.remove(i);
+            self.hold_log.push((since, time, process));
+            if let Some(unit) = self.units.iter().position(|&u| u == Some(process)) {
+                self.units[unit] = None;
+            }
+        }
+    }
+
+    /// Drop the free units at the end, beyond the capacity.
+    fn trim_units(&mut self) {
+        while self.units.len() > self.allocated && self.units.last() == Some(&None) {
+            self.units.pop();
+        }
+    }
+
+    /// Returns the integral of the capacity from `start` to `now`.
+    fn capacity_time(&self, start: f64, now: f64) -> f64 {
+        if self.capacity_log.is_empty() {
+            return self.allocated as f64 * (now - start);
+        }
+        let ends = self.capacity_log.iter().skip(1).map(|&(t, _)| t);
+        self.capacity_log
+            .iter()
+            .zip(ends.chain(core::iter::once(now)))
+            .map(|(&(start, units), end)| units as f64 * (end - start))
+            .sum()
+    }
+
+    /// Assign a unit to `process`, granted the resource: the one it
+    /// preferred if it is free, otherwise the first free one.
+    fn take_unit(&mut self, process: ProcessId) -> usize {
+        let unit = match self.preferred.remove(&process) {
+            Some(unit) if self.units[unit].is_none() => unit,
+            _ => self
+                .units
+                .iter()
+                .position(Option::is_none)
+                .expect("A unit was granted while all of them were held"),
+        };
+        self.units[unit] = Some(process);
+        unit
+    }
+}
+
+impl TimeDisplay {
+    /// Format a time according to this setting.
+    pub fn format(&self, time: f64) -> String {
+        match self {
+            TimeDisplay::Raw => time.to_string(),
+            TimeDisplay::Unit(unit) => format!("{} {}", time, unit),
+            TimeDisplay::HoursMinutesSeconds { seconds_per_unit } => {
+                // round to whole milliseconds before splitting, so that
+                // the seconds carry into the minutes and hours; adding a half
+                // and truncating rounds a non-negative value without the
+                // float functions of std
+                let millis = ((time * seconds_per_unit).abs() * 1000.0 + 0.5) as u64;
+                let hours = millis / 3_600_000;
+                let minutes = millis / 60_000 % 60;
+                let seconds = millis / 1000 % 60;
+                let sign = if time < 0.0 && millis > 0 { "-" } else { "" };
+                format!(
+                    "{}{:02}:{:02}:{:02}.{:03}",
+                    sign,
+                    hours,
+                    minutes,
+                    seconds,
+                    millis % 1000
+                )
+            }
+            #[cfg(feature = "chrono")]
+            TimeDisplay::DateTime(clock) => clock.format(time),
+        }
+    }
+}
+
+impl ResumeStats {
+    #[cfg(feature = "std")]
+    fn record(&mut self, elapsed: Duration) {
+        self.total += elapsed;
+        self.count += 1;
+        self.max = self.max.max(elapsed);
+    }
+}
+
+impl<T> SimContext<T> {
+    /// Returns current simulation time, as observed by the process if its
+    /// clock is skewed with `Simulation::set_clock_skew`.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the absolute time that is `delta` time units after the
+    /// current simulation time.
+    ///
+    /// Times in effects like `Effect::TimeOut` and `Effect::Event` are
+    /// relative to the current time, while `Simulation::schedule_event_at`
+    /// takes an absolute time, that can be computed with this method.
+    pub fn now_plus(&self, delta: f64) -> f64 {
+        self.global_time + delta
+    }
+
+    /// Returns the `State` that caused the process to wake up
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    /// Returns why the process was resumed.
+    pub fn wake_reason(&self) -> WakeReason {
+        self.wake
+    }
+
+    /// Returns the unit of the resource granted to the process, if it was
+    /// resumed because its request was granted.
+    pub fn granted_unit(&self) -> Option<usize> {
+        match self.wake {
+            WakeReason::ResourceAcquired { unit, .. } => Some(unit),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the process was resumed because its request for
+    /// a resource was rejected by the allocator of the resource or because
+    /// its queue was full, after giving up retrying for an
+    /// `Effect::RequestWithBackoff`.
+    pub fn rejected(&self) -> bool {
+        self.rejected
+    }
+
+    /// Returns the timer set by the process, if it was resumed after
+    /// yielding `Effect::SetTimer`.
+    pub fn timer_set(&self) -> Option<TimerId> {
+        match self.timer {
+            Some(TimerReply::Set(timer)) => Some(timer),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the process was resumed after yielding
+    /// `Effect::CancelTimer` or `Effect::RestartTimer` for a timer that
+    /// was still to expire, `false` if it had expired or was canceled.
+    pub fn timer_was_armed(&self) -> bool {
+        matches!(self.timer, Some(TimerReply::Reset { armed: true }))
+    }
+
+    /// Returns the number of available units and the number of processes
+    /// waiting for a unit of each of the resources `ids`, as they were when
+    /// the process was resumed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the resources does not exist.
+    pub fn resources_snapshot(&self, ids: &[ResourceId]) -> Vec<(usize, usize)> {
+        ids.iter()
+            .map(|&id| {
+                *self
+                    .resources
+                    .get(id)
+                    .unwrap_or_else(|| panic!("Resource {} does not exist", id))
+            })
+            .collect()
+    }
+
+    /// Returns the events that were scheduled next when the process was
+    /// resumed, as many as set with `Simulation::set_context_lookahead`.
+    pub fn upcoming_events(&self) -> &[PendingEvent] {
+        &self.upcoming
+    }
+
+    /// Returns the `State` that caused the process to wake up
+    pub fn into_state(self) -> T {
+        self.state
+    }
+}
+
+impl<T> Event<T> {
+    /// Create an event at the absolute time `time`, resuming `process`
+    /// with `state`, e.g. to compare it with the logged ones.
+    ///
+    /// The event is not scheduled: it has no label and its wake reason is
+    /// `WakeReason::Scheduled`, unless set with `with_label` and
+    /// `with_wake_reason`.
+    pub fn new(time: f64, process: ProcessId, state: T) -> Event<T> {
+        Event {
+            time,
+            process,
+            state,
+            key: (0, 0),
+            seq: 0,
+            wake: WakeReason::default(),
+            label: None,
+        }
+    }
+
+    /// Returns the event with the label `label`.
+    pub fn with_label(self, label: LabelId) -> Event<T> {
+        Event {
+            label: Some(label),
+            ..self
+        }
+    }
+
+    /// Returns the event with the wake reason `wake`.
+    pub fn with_wake_reason(self, wake: WakeReason) -> Event<T> {
+        Event { wake, ..self }
+    }
+
+    /// Returns whether the events have the same time and process, like
+    /// `==`, and their states are equal according to `state_eq`.
+    pub fn eq_by<F>(&self, other: &Event<T>, state_eq: F) -> bool
+    where
+        F: FnOnce(&T, &T) -> bool,
+    {
+        self == other && state_eq(&self.state, &other.state)
+    }
+
+    /// Compares the events in the order they are processed: by time, then
+    /// priority key, then scheduling order.
+    pub(crate) fn queue_order(&self, other: &Event<T>) -> Ordering {
+        match self.time.partial_cmp(&other.time) {
+            Some(o) => o
+                .then(self.key.cmp(&other.key))
+                .then(self.seq.cmp(&other.seq)),
+            None => panic!("Event time was uncomparable. Maybe a NaN"),
+        }
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn process(&self) -> ProcessId {
+        self.process
+    }
+
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    /// Returns why the process is resumed by the event.
+    pub fn wake_reason(&self) -> WakeReason {
+        self.wake
+    }
+
+    /// Returns the label given when the event was scheduled, if any.
+    pub fn label(&self) -> Option<LabelId> {
+        self.label
+    }
+}
+
+impl<T: SimState + Clone> Default for Simulation<T> {
+    fn default() -> Self {
+        Simulation::empty()
+    }
+}
+
+impl<T: SimState + Clone, G> Simulation<T, G> {
+    fn empty() -> Simulation<T, G> {
+        Simulation {
+            time: 0.0,
+            start_time: 0.0,
+            steps: 0,
+            processes: Vec::default(),
+            future_events: BinaryHeap::default(),
+            processed_events: Vec::default(),
+            log_index: Vec::default(),
+            statuses: Vec::default(),
+            effect_history: None,
+            force_log_all: false,
+            pending_wakeups: None,
+            suspended: BTreeMap::new(),
+            killed: BTreeSet::new(),
+            dependents: BTreeMap::new(),
+            canceled: BTreeSet::new(),
+            dropped_dependents: Vec::new(),
+            scheduled: Vec::default(),
+            started: 0,
+            completed: 0,
+            logged_events: 0,
+            debug_events: Vec::default(),
+            resources: Vec::default(),
+            allocators: Vec::default(),
+            rwlocks: Vec::default(),
+            channels: Vec::default(),
+            containers: Vec::default(),
+            deliveries: BTreeMap::new(),
+            rejected: BTreeSet::new(),
+            timers: Vec::new(),
+            timer_replies: BTreeMap::new(),
+            backoffs: BTreeMap::new(),
+            batch_timeouts: BTreeMap::new(),
+            #[cfg(feature = "std")]
+            subscribers: Vec::default(),
+            #[cfg(feature = "std")]
+            dropped_events: 0,
+            resume_profile: None,
+            tiebreak: TieBreak::Fifo,
+            infinite_time: InfiniteTimePolicy::Reject,
+            release_order: ReleaseOrder::WaiterFirst,
+            livelock_limit: Some(10_000),
+            invariant_checks: false,
+            livelock_counts: BTreeMap::new(),
+            livelock_time: 0.0,
+            watchdog: Watchdog::default(),
+            max_queue_depth: 0,
+            perf_counters: Vec::new(),
+            process_data: BTreeMap::new(),
+            process_names: BTreeMap::new(),
+            clock_skews: BTreeMap::new(),
+            queue_depth_sum: 0,
+            rewind: Rewind::default(),
+            stop_reason: None,
+            simulated_before_reset: 0.0,
+            next_seq: 0,
+            priorities: Vec::default(),
+            rng: Rng::new(0),
+            seed: 0,
+            rng_log: None,
+            rng_replay: VecDeque::new(),
+            log_filter: None,
+            effect_middleware: Vec::new(),
+            event_selector: None,
+            context_lookahead: 0,
+            resources_snapshot: Arc::from(Vec::new()),
+            labels: Vec::new(),
+            markers: Vec::new(),
+            log_levels: Vec::default(),
+            time_display: TimeDisplay::Raw,
+            internal_events: BinaryHeap::default(),
+            probes: Vec::default(),
+            last_step: None,
+            last_event: None,
+            track_last_event: false,
+            event_source: None,
+            source_pending: None,
+            time_scale: 1.0,
+            inputs: Vec::default(),
+            output_filter: None,
+            outputs: Vec::default(),
+        }
+    }
+}
+
+impl PartialEq for InternalEvent {
+    fn eq(&self, other: &InternalEvent) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for InternalEvent {}
+
+impl PartialOrd for InternalEvent {
+    fn partial_cmp(&self, other: &InternalEvent) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InternalEvent {
+    fn cmp(&self, other: &InternalEvent) -> Ordering {
+        match self.time.partial_cmp(&other.time) {
+            Some(o) => o.then(self.seq.cmp(&other.seq)),
+            None => panic!("Event time was uncomparable. Maybe a NaN"),
+        }
+    }
+}
+
+/// Events are equal if they have the same time and process, whatever
+/// their states and the order they were scheduled in. Use `Event::eq_by`
+/// to compare the states too.
+impl<T> PartialEq for Event<T> {
+    fn eq(&self, other: &Event<T>) -> bool {
+        self.time == other.time && self.process == other.process
+    }
+}
+
+impl<T> Eq for Event<T> {}
+
+/// An event in the queue of the future events, ordered so that the queue
+/// pops first the event with the earliest time, then the smallest priority
+/// key, then the earliest scheduling.
+pub(crate) struct Queued<T>(pub(crate) Event<T>);
+
+impl<T> PartialEq for Queued<T> {
+    fn eq(&self, other: &Queued<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Queued<T> {}
+
+impl<T> PartialOrd for Queued<T> {
+    fn partial_cmp(&self, other: &Queued<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Queued<T> {
+    fn cmp(&self, other: &Queued<T>) -> Ordering {
+        other.0.queue_order(&self.0)
+    }
+}
+
+impl SimState for Effect {
+    fn effect(&self) -> Cow<'_, Effect> {
+        Cow::Borrowed(self)
+    }
+    fn set_effect(&mut self, e: Effect) {
+        *self = e;
+    }
+    fn should_log(&self) -> bool {
+        true
+    }
+}
+
+/// An effect with a payload opaque to the simulation, always logged.
+impl<U: Clone> SimState for (Effect, U) {
+    fn effect(&self) -> Cow<'_, Effect> {
+        Cow::Borrowed(&self.0)
+    }
+    fn set_effect(&mut self, e: Effect) {
+        self.0 = e;
+    }
+    fn should_log(&self) -> bool {
+        true
+    }
+}
+
+/// An effect with a payload opaque to the simulation, logged if the flag
+/// is `true`.
+impl<U: Clone> SimState for (Effect, U, bool) {
+    fn effect(&self) -> Cow<'_, Effect> {
+        Cow::Borrowed(&self.0)
+    }
+    fn set_effect(&mut self, e: Effect) {
+        self.0 = e;
+    }
+    fn should_log(&self) -> bool {
+        self.2
+    }
+}
+
+// The tests use the generators syntax, that cannot even be parsed
+// without the nightly feature, so they are kept in their own files.
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,137 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Markers annotating the timeline of the simulation.
+
+use crate::{LabelId, ProcessId, SimState, Simulation, StaticProcess};
+use alloc::string::String;
+use core::fmt::Write;
+
+/// A labeled instant of the timeline, recorded with
+/// `Simulation::record_marker` or by a process yielding
+/// `Effect::LabeledTrace`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Marker {
+    /// Simulation time of the marker
+    pub time: f64,
+    /// Number of steps processed before the marker
+    pub step: usize,
+    /// Label of the marker
+    pub label: LabelId,
+    /// The process that recorded the marker, or `None` if it was recorded
+    /// from outside of the processes
+    pub process: Option<ProcessId>,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Record a marker with `label` at the current time, e.g. to show in
+    /// the timeline where the parameters of the model were changed between
+    /// two runs.
+    ///
+    /// Markers are kept apart from the log of processed events, so they
+    /// are recorded regardless of `should_log()` and of the log filters.
+    pub fn record_marker(&mut self, label: LabelId) {
+        self.push_marker(label, None);
+    }
+
+    pub(crate) fn push_marker(&mut self, label: LabelId, process: Option<ProcessId>) {
+        self.markers.push(Marker {
+            time: self.time,
+            step: self.steps,
+            label,
+            process,
+        });
+    }
+
+    /// Returns the markers, in the order they were recorded.
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Returns the markers as JSON instant events of the Chrome trace
+    /// event format, e.g.
+    /// `[{"name":"phase 2","ph":"i","s":"g","ts":100,"pid":0,"tid":3}]`.
+    ///
+    /// The `tid` is the process that recorded the marker, and is omitted
+    /// for the markers recorded from outside of the processes. The `ts` is
+    /// the simulation time.
+    pub fn markers_json(&self) -> String {
+        let mut out = String::from("[");
+        for (i, marker) in self.markers.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let name = self
+                .label_name(marker.label)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            write!(
+                out,
+                "{{\"name\":\"{}\",\"ph\":\"i\",\"s\":\"g\",\"ts\":{},\"pid\":0",
+                name, marker.time
+            )
+            .unwrap();
+            if let Some(process) = marker.process {
+                write!(out, ",\"tid\":{}", process).unwrap();
+            }
+            out.push('}');
+        }
+        out.push(']');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, EndCondition, SimContext, Simulation, Step};
+
+    #[test]
+    fn markers() {
+        let mut s = Simulation::new();
+        let tick = s.intern_label("tick");
+        let phase = s.intern_label("phase \"2\"");
+        let mut step = 0;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            step += 1;
+            match step {
+                1 => Step::Yield(Effect::TimeOut(1.0)),
+                2 => Step::Yield(Effect::LabeledTrace(tick)),
+                _ => Step::Done,
+            }
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+        // filtered out of the log, but not of the markers
+        s.set_log_filter(|_, _| false);
+        let mut s = s.run(EndCondition::NSteps(1));
+        s.record_marker(phase);
+        let s = s.run(EndCondition::NoEvents);
+        assert!(s.processed_events().is_empty());
+        let markers: Vec<_> = s
+            .markers()
+            .iter()
+            .map(|m| (m.time, m.step, m.label, m.process))
+            .collect();
+        assert_eq!(
+            markers,
+            vec![(0.0, 1, phase, None), (1.0, 2, tick, Some(p))]
+        );
+        assert_eq!(
+            s.markers_json(),
+            "[{\"name\":\"phase \\\"2\\\"\",\"ph\":\"i\",\"s\":\"g\",\"ts\":0,\"pid\":0},\
+             {\"name\":\"tick\",\"ph\":\"i\",\"s\":\"g\",\"ts\":1,\"pid\":0,\"tid\":0}]"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Composition of the logs of separate runs into a single timeline.
+
+use crate::Event;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// The log of a run, as returned by `Simulation::processed_events`, with
+/// the offset added to the time of its events by `merge_logs`.
+pub type OffsetLog<T> = (f64, Vec<(Event<T>, T)>);
+
+/// Merge the logs of separate runs, as returned by
+/// `Simulation::processed_events`, into a single log ordered by time.
+///
+/// The offset of each log is, e.g., the time at which its run starts on
+/// the common timeline. Events at the same time keep their order within a
+/// log, and the events of earlier logs come first. The processes of each
+/// log keep their ids, so runs that should be told apart need disjoint
+/// ranges of processes.
+pub fn merge_logs<T>(logs: Vec<OffsetLog<T>>) -> Vec<(Event<T>, T)> {
+    let mut merged: Vec<_> = logs
+        .into_iter()
+        .flat_map(|(offset, log)| {
+            log.into_iter().map(move |(mut event, state)| {
+                event.time += offset;
+                (event, state)
+            })
+        })
+        .collect();
+    // the sort is stable, so ties keep the order they were collected in
+    merged.sort_by(|a, b| a.0.time.partial_cmp(&b.0.time).unwrap_or(Ordering::Equal));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_logs;
+    use crate::{Effect, Event};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn merge_offset_logs() {
+        let log = |process, times: &[f64]| -> Vec<(Event<Effect>, Effect)> {
+            times
+                .iter()
+                .map(|&t| (Event::new(t, process, Effect::Trace), Effect::Trace))
+                .collect()
+        };
+        let merged = merge_logs(vec![
+            (10.0, log(0, &[0.0, 2.0, 5.0])),
+            (11.0, log(1, &[0.0, 1.0, 6.0])),
+        ]);
+
+        let times: Vec<_> = merged.iter().map(|(e, _)| e.time()).collect();
+        assert_eq!(times, vec![10.0, 11.0, 12.0, 12.0, 15.0, 17.0]);
+        let processes: Vec<_> = merged.iter().map(|(e, _)| e.process()).collect();
+        assert_eq!(processes, vec![0, 1, 0, 1, 0, 1]);
+        assert!(merge_logs::<Effect>(vec![]).is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Export of the simulation metrics in the Prometheus text format.
+
+use crate::{Resource, SimState, Simulation, StaticProcess};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Name, help text and value of a per-resource gauge.
+type ResourceGauge<T> = (&'static str, &'static str, fn(&Resource<T>) -> f64);
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Returns a snapshot of the simulation metrics in the Prometheus
+    /// text exposition format.
+    ///
+    /// The output contains the step and logged event counters, the current
+    /// time, the number of pending events and its maximum over the steps,
+    /// and for each resource the available and allocated units, the queue
+    /// length and a summary of the time waited by the granted requests,
+    /// with the quantiles tracked with `track_wait_time_quantile`, labeled
+    /// with the resource id.
+    pub fn metrics_text(&self) -> String {
+        let mut out = String::new();
+        family(
+            &mut out,
+            "desim_steps_total",
+            "counter",
+            "Number of steps executed.",
+        );
+        sample(&mut out, "desim_steps_total", &[], self.steps as f64);
+        family(
+            &mut out,
+            "desim_processed_events_total",
+            "counter",
+            "Number of events added to the log.",
+        );
+        sample(
+            &mut out,
+            "desim_processed_events_total",
+            &[],
+            self.processed_events.len() as f64,
+        );
+        family(&mut out, "desim_time", "gauge", "Current simulation time.");
+        sample(&mut out, "desim_time", &[], self.time);
+        family(
+            &mut out,
+            "desim_pending_events",
+            "gauge",
+            "Number of events scheduled in the future.",
+        );
+        sample(
+            &mut out,
+            "desim_pending_events",
+            &[],
+            self.pending_event_count() as f64,
+        );
+        family(
+            &mut out,
+            "desim_max_pending_events",
+            "gauge",
+            "Largest number of events scheduled in the future at a step.",
+        );
+        sample(
+            &mut out,
+            "desim_max_pending_events",
+            &[],
+            self.max_queue_depth as f64,
+        );
+
+        if self.resources.is_empty() {
+            return out;
+        }
+        let ids: Vec<String> = (0..self.resources.len()).map(|id| id.to_string()).collect();
+        let gauges: [ResourceGauge<T>; 3] = [
+            (
+                "desim_resource_available",
+                "Number of available units of the resource.",
+                |r| r.available as f64,
+            ),
+            (
+                "desim_resource_allocated",
+                "Number of units of the resource.",
+                |r| r.allocated as f64,
+            ),
+            (
+                "desim_resource_queue_length",
+                "Number of processes waiting for the resource.",
+                |r| r.queue.len() as f64,
+            ),
+        ];
+        for (name, help, value) in gauges.iter() {
+            family(&mut out, name, "gauge", help);
+            for (id, res) in ids.iter().zip(self.resources.iter()) {
+                sample(&mut out, name, &[("resource", id)], value(res));
+            }
+        }
+        family(
+            &mut out,
+            "desim_resource_wait_time",
+            "summary",
+            "Time waited in queue by the granted requests.",
+        );
+        for (id, res) in ids.iter().zip(self.resources.iter()) {
+            for estimator in res.wait_quantiles.iter() {
+                let quantile = estimator.quantile().to_string();
+                sample(
+                    &mut out,
+                    "desim_resource_wait_time",
+                    &[("resource", id), ("quantile", &quantile)],
+                    estimator.estimate(),
+                );
+            }
+            let labels = [("resource", id.as_str())];
+            sample(
+                &mut out,
+                "desim_resource_wait_time_sum",
+                &labels,
+                res.total_wait,
+            );
+            sample(
+                &mut out,
+                "desim_resource_wait_time_count",
+                &labels,
+                res.grants as f64,
+            );
+        }
+        out
+    }
+
+    /// Returns the largest number of events scheduled in the future at
+    /// the start of a step.
+    ///
+    /// A depth growing with the steps shows that the events are scheduled
+    /// faster than they are processed.
+    pub fn max_queue_depth(&self) -> usize {
+        self.max_queue_depth
+    }
+
+    /// Returns the mean number of events scheduled in the future at the
+    /// start of each step, or NaN before the first step.
+    pub fn mean_queue_depth(&self) -> f64 {
+        self.queue_depth_sum as f64 / self.steps as f64
+    }
+
+    /// Sample the number of events scheduled in the future, at the start
+    /// of a step.
+    pub(crate) fn record_queue_depth(&mut self) {
+        let depth = self.pending_event_count();
+        self.max_queue_depth = self.max_queue_depth.max(depth);
+        self.queue_depth_sum += depth as u64;
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        out.push('{');
+        for (i, (label, value)) in labels.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write!(out, "{}=\"{}\"", label, escape_label_value(value)).unwrap();
+        }
+        out.push('}');
+    }
+    writeln!(out, " {}", format_value(value)).unwrap();
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests;
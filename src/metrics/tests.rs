@@ -0,0 +1,146 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use super::escape_label_value;
+use std::collections::HashMap;
+
+/// Parse the exposition text, checking that every sample belongs to
+/// a declared family, and return the value of each sample by its
+/// name and labels.
+fn parse(text: &str) -> HashMap<String, f64> {
+    let mut types = HashMap::new();
+    let mut samples = HashMap::new();
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("# HELP ") {
+            assert!(rest.split(' ').count() >= 2, "bad HELP line: {}", line);
+        } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+            let parts: Vec<_> = rest.split(' ').collect();
+            assert_eq!(parts.len(), 2, "bad TYPE line: {}", line);
+            assert!(["counter", "gauge", "summary"].contains(&parts[1]));
+            types.insert(parts[0].to_string(), parts[1].to_string());
+        } else {
+            let (series, value) = line.rsplit_once(' ').expect("missing value");
+            let name = series.split('{').next().unwrap();
+            assert!(name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':'));
+            let family = name
+                .strip_suffix("_sum")
+                .or_else(|| name.strip_suffix("_count"))
+                .filter(|f| types.get(*f).map(String::as_str) == Some("summary"))
+                .unwrap_or(name);
+            assert!(types.contains_key(family), "undeclared metric: {}", name);
+            if let Some(labels) = series.strip_prefix(name) {
+                if !labels.is_empty() {
+                    assert!(labels.starts_with('{') && labels.ends_with('}'));
+                }
+            }
+            let value = match value {
+                "NaN" => f64::NAN,
+                "+Inf" => f64::INFINITY,
+                "-Inf" => f64::NEG_INFINITY,
+                v => v.parse().expect("bad value"),
+            };
+            samples.insert(series.to_string(), value);
+        }
+    }
+    samples
+}
+
+#[test]
+fn metrics_text() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    for &q in [0.5, 0.9, 0.95, 0.99].iter() {
+        s.track_wait_time_quantile(r, q);
+    }
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(3.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(2.0, p2, Effect::TimeOut(2.));
+    let s = s.run(NoEvents);
+
+    let metrics = parse(&s.metrics_text());
+    assert_eq!(metrics["desim_time"], 10.0);
+    assert_eq!(metrics["desim_pending_events"], 0.0);
+    assert_eq!(metrics["desim_max_pending_events"], 2.0);
+    assert_eq!(
+        metrics["desim_processed_events_total"],
+        s.processed_events().len() as f64
+    );
+    assert_eq!(metrics["desim_resource_available{resource=\"0\"}"], 1.0);
+    assert_eq!(metrics["desim_resource_queue_length{resource=\"0\"}"], 0.0);
+    // p2 waited from 2.0 to 7.0
+    assert_eq!(metrics["desim_resource_wait_time_sum{resource=\"0\"}"], 5.0);
+    assert_eq!(
+        metrics["desim_resource_wait_time_count{resource=\"0\"}"],
+        2.0
+    );
+    for q in ["0.5", "0.9", "0.95", "0.99"] {
+        let series = format!(
+            "desim_resource_wait_time{{resource=\"0\",quantile=\"{}\"}}",
+            q
+        );
+        assert_eq!(
+            Some(metrics[&series]),
+            s.wait_time_quantile(r, q.parse().unwrap()),
+            "{}",
+            series
+        );
+    }
+}
+
+#[test]
+fn queue_depth() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    // each timer is an event scheduled far in the future, while the
+    // process is resumed right away
+    let p = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::SetTimer(1000.0)).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    assert!(s.mean_queue_depth().is_nan());
+    for _ in 0..10 {
+        s.step();
+    }
+    let (max, mean) = (s.max_queue_depth(), s.mean_queue_depth());
+    for _ in 0..10 {
+        s.step();
+    }
+    assert!(s.max_queue_depth() > max);
+    assert!(s.mean_queue_depth() > mean);
+    // one more pending timer at the start of each step
+    assert_eq!(s.max_queue_depth(), 20);
+    assert_eq!(s.mean_queue_depth(), 10.5);
+}
+
+#[test]
+fn escape() {
+    assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+}
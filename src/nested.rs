@@ -0,0 +1,124 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Hierarchical models: a simulation running as a process of another one.
+
+use crate::{Effect, Event, ProcessId, SimState, Simulation, StaticProcess};
+use alloc::rc::Rc;
+use core::cell::RefCell;
+
+impl<T: SimState + Clone + 'static, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a process running the `child` simulation in lockstep with
+    /// this one.
+    ///
+    /// Each time the process is resumed it processes the events of the
+    /// child up to the current time, then waits until the time of the next
+    /// event of the child. The process completes when the child has no more
+    /// events. The clock of the child is kept equal to the one of this
+    /// simulation, so it must not be ahead of it when the process starts.
+    ///
+    /// The outputs of the child, selected by its `set_output_filter`, are
+    /// mapped by `forward` to states yielded by the process, which are added
+    /// to the log of this simulation when the child processes them. Their
+    /// effect must be `Effect::Trace`, so that the process continues right
+    /// away.
+    ///
+    /// The child is shared with the caller, to inspect it during and after
+    /// the run. It must not be borrowed while this simulation is running.
+    pub fn create_child_process<F>(
+        &mut self,
+        child: Rc<RefCell<Simulation<T>>>,
+        forward: F,
+    ) -> ProcessId
+    where
+        T: From<Effect>,
+        F: Fn(&Event<T>, &T) -> T + 'static,
+    {
+        self.create_async_process(move |handle| async move {
+            loop {
+                let report = child.borrow_mut().advance_bounded(handle.time());
+                for (event, state) in report.outputs.iter() {
+                    let output = forward(event, state);
+                    assert!(
+                        matches!(*output.effect(), Effect::Trace),
+                        "The output of child process {} must be forwarded as an Effect::Trace",
+                        handle.id()
+                    );
+                    handle.yield_state(output).await;
+                }
+                let next = match report.next_event {
+                    Some(next) => next,
+                    None => return,
+                };
+                // an event is not affected by the time scale, so the clocks
+                // stay in lockstep
+                let wake_up = T::from(Effect::Event {
+                    time: next - handle.time(),
+                    process: handle.id(),
+                });
+                handle.yield_state(wake_up).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    #[test]
+    fn child_process() {
+        let mut child = Simulation::new();
+        let worker = child.create_async_process(|handle| async move {
+            for _ in 0..3 {
+                handle.timeout(2.0).await;
+            }
+        });
+        child.schedule_event(1.0, worker, Effect::TimeOut(0.));
+        child.set_output_filter(|_, _| true);
+        let child = Rc::new(RefCell::new(child));
+
+        let mut s = Simulation::new();
+        let other = s.create_async_process(|handle| async move {
+            handle.timeout(2.5).await;
+        });
+        let p = s.create_child_process(child.clone(), |_, _| Effect::Trace);
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s.schedule_event(0.0, other, Effect::TimeOut(0.));
+        let s = s.run(NoEvents);
+
+        let child = child.borrow();
+        // the child processed its events at the same times as on its own,
+        // completing at time 7, and the parent followed it
+        let child_times: Vec<f64> = child
+            .processed_events()
+            .iter()
+            .map(|(e, _)| e.time)
+            .collect();
+        assert_eq!(child_times, vec![1.0, 3.0, 5.0]);
+        assert_eq!(child.time(), 7.0);
+        assert_eq!(s.time(), 7.0);
+        // each output of the child was forwarded when it was processed
+        let forwarded: Vec<f64> = s
+            .processed_events()
+            .iter()
+            .filter(|(e, y)| e.process() == p && matches!(y, Effect::Trace))
+            .map(|(e, _)| e.time)
+            .collect();
+        assert_eq!(forwarded, vec![1.0, 3.0, 5.0]);
+    }
+}
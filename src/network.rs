@@ -0,0 +1,718 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Networks of queueing stations, with entities routed between them.
+//!
+//! A `NetworkBuilder` describes the stations, the sources of entities and
+//! how entities are routed, and creates the resources and the processes
+//! that run the network in a simulation. An entity leaving a station without
+//! a next station goes to the sink of the network, that records how long
+//! it stayed in the network. A `QueueNetwork` builds the common tandem and
+//! Jackson networks from a routing matrix.
+
+use crate::rng::Rng;
+use crate::{Effect, JitterSpec, ProcessId, ResourceId, SimContext, SimState, Simulation, Step};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+/// Identifies a station of a network, in the order they were added to
+/// the builder.
+pub type StationId = usize;
+
+/// A delay between two events of a network, e.g. the service time of a
+/// station or the time between two arrivals of a source.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Delay {
+    /// Always the same delay
+    Fixed(f64),
+    /// A delay drawn around `base` from the random number generator of
+    /// the simulation, like the one of `Effect::TimeOutJitter`.
+    Jitter {
+        /// The delay around which the realized one is drawn
+        base: f64,
+        /// The distribution of the noise added to `base`
+        dist: JitterSpec,
+    },
+}
+
+impl Delay {
+    pub(crate) fn effect(&self) -> Effect {
+        match *self {
+            Delay::Fixed(t) => Effect::TimeOut(t),
+            Delay::Jitter { base, dist } => Effect::TimeOutJitter { base, dist },
+        }
+    }
+}
+
+/// Chooses the station an entity goes to next, or `None` to send it to
+/// the sink.
+pub enum Router {
+    /// Go to each station with the given probability, and to the sink with
+    /// the remaining one. The probabilities must sum at most to 1.
+    Probabilities(Vec<(StationId, f64)>),
+    /// Go where the closure says, given the current simulation time.
+    Rule(Box<dyn FnMut(f64) -> Option<StationId>>),
+}
+
+impl Router {
+    /// Route to the sink only.
+    pub fn sink() -> Router {
+        Router::Probabilities(Vec::new())
+    }
+
+    /// Route to `station` only.
+    pub fn to(station: StationId) -> Router {
+        Router::Probabilities(alloc::vec![(station, 1.0)])
+    }
+
+    fn next(&mut self, time: f64, rng: &mut Rng) -> Option<StationId> {
+        match self {
+            Router::Probabilities(p) => {
+                let mut u = rng.next_f64();
+                for &(station, prob) in p.iter() {
+                    if u < prob {
+                        return Some(station);
+                    }
+                    u -= prob;
+                }
+                None
+            }
+            Router::Rule(f) => f(time),
+        }
+    }
+
+    fn check(&self, stations: usize) {
+        if let Router::Probabilities(p) = self {
+            let mut total = 0.0;
+            for &(station, prob) in p.iter() {
+                assert!(
+                    station < stations,
+                    "Station {} is not part of the network",
+                    station
+                );
+                assert!(prob >= 0.0, "Routing probability {} is negative", prob);
+                total += prob;
+            }
+            assert!(
+                total <= 1.0 + 1e-9,
+                "Routing probabilities sum to {}, more than 1",
+                total
+            );
+        }
+    }
+}
+
+/// A station of a network: a queue served by a number of servers, each
+/// serving one entity at a time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Station {
+    /// Number of servers, i.e. units of the resource of the station
+    pub servers: usize,
+    /// The time it takes to serve an entity
+    pub service: Delay,
+}
+
+/// The number of entities arriving together.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchSize {
+    /// Always the same number of entities
+    Fixed(usize),
+    /// Each number of entities with a probability proportional to its weight
+    Weights(Vec<(usize, f64)>),
+}
+
+impl BatchSize {
+    fn sample(&self, rng: &mut Rng) -> usize {
+        match self {
+            BatchSize::Fixed(k) => *k,
+            BatchSize::Weights(w) => {
+                let total: f64 = w.iter().map(|&(_, weight)| weight).sum();
+                let mut u = rng.next_f64() * total;
+                for &(k, weight) in w.iter() {
+                    if u < weight {
+                        return k;
+                    }
+                    u -= weight;
+                }
+                // only reached by rounding errors
+                w.last().map_or(0, |&(k, _)| k)
+            }
+        }
+    }
+
+    fn check(&self) {
+        if let BatchSize::Weights(w) = self {
+            assert!(
+                w.iter().all(|&(_, weight)| weight >= 0.0),
+                "The weights of the batch sizes must not be negative"
+            );
+            assert!(
+                w.iter().any(|&(_, weight)| weight > 0.0),
+                "The weights of the batch sizes must not be all zero"
+            );
+        }
+    }
+}
+
+/// A source of a fixed number of entities, arriving in batches one after
+/// the other starting from the time the network is built.
+pub struct Source {
+    /// The time between two arrivals of a batch
+    pub interarrival: Delay,
+    /// The number of entities of each batch. The last batch is smaller
+    /// if there are not enough entities left.
+    pub batch: BatchSize,
+    /// Where each arriving entity goes
+    pub router: Router,
+    /// Number of entities created by the source
+    pub entities: usize,
+}
+
+/// The entities that left the network.
+#[derive(Debug, Clone, Default)]
+pub struct Sink {
+    /// Time spent in the network by each entity, in the order they left it
+    pub sojourn_times: Vec<f64>,
+}
+
+impl Sink {
+    /// Returns the number of entities that left the network.
+    pub fn departures(&self) -> usize {
+        self.sojourn_times.len()
+    }
+
+    /// Returns the mean time spent in the network by the entities that
+    /// left it, or NaN if none did.
+    pub fn mean_sojourn_time(&self) -> f64 {
+        self.sojourn_times.iter().sum::<f64>() / self.sojourn_times.len() as f64
+    }
+}
+
+/// Statistics of a station, returned by `Network::station_stats`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StationStats {
+    /// Entities served per unit of time
+    pub throughput: f64,
+    /// Mean time waited in queue by the entities that started service
+    pub mean_wait: f64,
+    /// Fraction of the time the servers were busy
+    pub utilization: f64,
+}
+
+/// State shared by the processes of a network.
+struct Shared {
+    rng: Rng,
+    routers: Vec<Router>,
+    sink: Sink,
+}
+
+/// Describes a network, and builds it in a simulation.
+pub struct NetworkBuilder {
+    stations: Vec<Station>,
+    routers: Vec<Router>,
+    sources: Vec<Source>,
+    seed: u64,
+}
+
+impl Default for NetworkBuilder {
+    fn default() -> Self {
+        NetworkBuilder::new()
+    }
+}
+
+impl NetworkBuilder {
+    /// Create the builder of an empty network.
+    pub fn new() -> NetworkBuilder {
+        NetworkBuilder {
+            stations: Vec::new(),
+            routers: Vec::new(),
+            sources: Vec::new(),
+            seed: 0,
+        }
+    }
+
+    /// Add a station, from which the entities go to the sink until a
+    /// different router is set with `route`.
+    pub fn station(&mut self, station: Station) -> StationId {
+        assert!(station.servers > 0, "A station needs at least one server");
+        self.stations.push(station);
+        self.routers.push(Router::sink());
+        self.stations.len() - 1
+    }
+
+    /// Set where the entities go after being served by `station`.
+    pub fn route(&mut self, station: StationId, router: Router) {
+        assert!(
+            station < self.stations.len(),
+            "Station {} is not part of the network",
+            station
+        );
+        self.routers[station] = router;
+    }
+
+    /// Add a source of entities.
+    pub fn source(&mut self, source: Source) {
+        self.sources.push(source);
+    }
+
+    /// Seed the random number generator used by the probabilistic routers.
+    pub fn seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Create the resources and the processes of the network in `sim`,
+    /// scheduling them to start at the current time.
+    ///
+    /// Each entity is a process, that waits for its source to make it
+    /// arrive, and then requests, holds for the service time and releases
+    /// the resource of each station it is routed to.
+    pub fn build<T>(self, sim: &mut Simulation<T>) -> Network
+    where
+        T: SimState + Clone + From<Effect> + 'static,
+    {
+        for router in self.routers.iter() {
+            router.check(self.stations.len());
+        }
+        for source in self.sources.iter() {
+            source.router.check(self.stations.len());
+            source.batch.check();
+        }
+        let resources: Rc<Vec<ResourceId>> = Rc::new(
+            self.stations
+                .iter()
+                .map(|s| sim.create_resource(s.servers))
+                .collect(),
+        );
+        let services: Rc<Vec<Effect>> =
+            Rc::new(self.stations.iter().map(|s| s.service.effect()).collect());
+        let shared = Rc::new(RefCell::new(Shared {
+            // scramble the seed, so that the routing draws are not the
+            // numbers drawn by the simulation when both get the same seed
+            rng: Rng::new(Rng::new(self.seed).next_u64()),
+            routers: self.routers,
+            sink: Sink::default(),
+        }));
+
+        let mut sources = Vec::new();
+        for source in self.sources {
+            let router = Rc::new(RefCell::new(source.router));
+            let arrivals = sim.create_channel::<()>(source.entities.max(1));
+            for _ in 0..source.entities {
+                let mut entity = Entity {
+                    phase: Phase::Created,
+                    arrival: 0.0,
+                    source: router.clone(),
+                    shared: shared.clone(),
+                    resources: resources.clone(),
+                    services: services.clone(),
+                    recv: arrivals.recv(),
+                };
+                let p = sim.create_sm_process(Box::new(move |ctx: SimContext<T>| {
+                    entity
+                        .resume(ctx.time())
+                        .map_or(Step::Done, |e| Step::Yield(T::from(e)))
+                }));
+                sim.schedule_event(0.0, p, T::from(Effect::TimeOut(0.0)));
+            }
+            let entities = source.entities;
+            let batch = source.batch;
+            let mut sent = 0;
+            // entities of the current batch still to send
+            let mut left = 0;
+            let mut waited = false;
+            let interarrival = source.interarrival.effect();
+            let shared = shared.clone();
+            let p = sim.create_sm_process(Box::new(move |_: SimContext<T>| {
+                if waited {
+                    waited = false;
+                    return Step::Yield(T::from(interarrival));
+                }
+                if sent == entities {
+                    return Step::Done;
+                }
+                if left == 0 {
+                    left = batch
+                        .sample(&mut shared.borrow_mut().rng)
+                        .min(entities - sent);
+                    if left == 0 {
+                        // an empty batch
+                        return Step::Yield(T::from(interarrival));
+                    }
+                }
+                sent += 1;
+                left -= 1;
+                waited = left == 0;
+                Step::Yield(T::from(arrivals.send(())))
+            }));
+            sim.schedule_event(0.0, p, T::from(Effect::TimeOut(0.0)));
+            sources.push(p);
+        }
+        Network {
+            resources: resources.to_vec(),
+            sources,
+            shared,
+        }
+    }
+}
+
+/// A queueing network described by its stations and a routing matrix,
+/// the common way to describe tandem and Jackson networks.
+///
+/// It is a shorthand for a `NetworkBuilder` with a probabilistic router
+/// for each station and a source for each stream of external arrivals.
+pub struct QueueNetwork {
+    builder: NetworkBuilder,
+}
+
+impl QueueNetwork {
+    /// Create a network where an entity served by station `i` goes to
+    /// station `j` with probability `routing[i][j]`, and to the sink with
+    /// the remaining one.
+    pub fn jackson(stations: Vec<Station>, routing: Vec<Vec<f64>>) -> QueueNetwork {
+        assert_eq!(
+            routing.len(),
+            stations.len(),
+            "The routing matrix needs a row for each station"
+        );
+        let mut builder = NetworkBuilder::new();
+        for station in stations {
+            builder.station(station);
+        }
+        for (i, row) in routing.into_iter().enumerate() {
+            assert_eq!(
+                row.len(),
+                builder.stations.len(),
+                "Row {} of the routing matrix needs a column for each station",
+                i
+            );
+            let p = row
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, prob)| prob != 0.0)
+                .collect();
+            builder.route(i, Router::Probabilities(p));
+        }
+        QueueNetwork { builder }
+    }
+
+    /// Create a network where the entities are served by each station in
+    /// order, and then go to the sink.
+    pub fn tandem(stations: Vec<Station>) -> QueueNetwork {
+        let n = stations.len();
+        let routing = (0..n)
+            .map(|i| (0..n).map(|j| if j == i + 1 { 1.0 } else { 0.0 }).collect())
+            .collect();
+        QueueNetwork::jackson(stations, routing)
+    }
+
+    /// Add a stream of `entities` external arrivals at `station`, one
+    /// every `interarrival` starting from the time the network is built.
+    pub fn arrivals(&mut self, station: StationId, interarrival: Delay, entities: usize) {
+        assert!(
+            station < self.builder.stations.len(),
+            "Station {} is not part of the network",
+            station
+        );
+        self.builder.source(Source {
+            interarrival,
+            batch: BatchSize::Fixed(1),
+            router: Router::to(station),
+            entities,
+        });
+    }
+
+    /// Seed the random number generator used for the routing.
+    pub fn seed(&mut self, seed: u64) {
+        self.builder.seed(seed);
+    }
+
+    /// Create the resources and the processes of the network in `sim`,
+    /// like `NetworkBuilder::build`.
+    pub fn build<T>(self, sim: &mut Simulation<T>) -> Network
+    where
+        T: SimState + Clone + From<Effect> + 'static,
+    {
+        self.builder.build(sim)
+    }
+}
+
+/// Where an entity is in the network.
+#[derive(Debug, Copy, Clone)]
+enum Phase {
+    /// Not resumed yet
+    Created,
+    /// Waiting to be sent by its source
+    Arriving,
+    /// Waiting for a server of the station
+    Queued(StationId),
+    /// Being served by the station
+    Served(StationId),
+    /// Having released the server of the station
+    Released(StationId),
+}
+
+struct Entity {
+    phase: Phase,
+    arrival: f64,
+    source: Rc<RefCell<Router>>,
+    shared: Rc<RefCell<Shared>>,
+    resources: Rc<Vec<ResourceId>>,
+    services: Rc<Vec<Effect>>,
+    recv: Effect,
+}
+
+impl Entity {
+    /// Returns the next effect of the entity, or `None` when it leaves
+    /// the network.
+    fn resume(&mut self, time: f64) -> Option<Effect> {
+        let next = match self.phase {
+            Phase::Created => {
+                self.phase = Phase::Arriving;
+                return Some(self.recv);
+            }
+            Phase::Arriving => {
+                self.arrival = time;
+                let rng = &mut self.shared.borrow_mut().rng;
+                self.source.borrow_mut().next(time, rng)
+            }
+            Phase::Queued(station) => {
+                self.phase = Phase::Served(station);
+                return Some(self.services[station]);
+            }
+            Phase::Served(station) => {
+                self.phase = Phase::Released(station);
+                return Some(Effect::Release(self.resources[station]));
+            }
+            Phase::Released(station) => {
+                let shared = &mut *self.shared.borrow_mut();
+                shared.routers[station].next(time, &mut shared.rng)
+            }
+        };
+        match next {
+            Some(station) => {
+                self.phase = Phase::Queued(station);
+                Some(Effect::Request(self.resources[station]))
+            }
+            None => {
+                let sink = &mut self.shared.borrow_mut().sink;
+                sink.sojourn_times.push(time - self.arrival);
+                None
+            }
+        }
+    }
+}
+
+/// A network built in a simulation by `NetworkBuilder::build`.
+pub struct Network {
+    resources: Vec<ResourceId>,
+    sources: Vec<ProcessId>,
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl Network {
+    /// Returns the resource of `station`.
+    pub fn resource(&self, station: StationId) -> ResourceId {
+        self.resources[station]
+    }
+
+    /// Returns the process of each source, in the order they were added.
+    pub fn sources(&self) -> &[ProcessId] {
+        &self.sources
+    }
+
+    /// Returns a copy of the sink of the network.
+    pub fn sink(&self) -> Sink {
+        self.shared.borrow().sink.clone()
+    }
+
+    /// Returns the statistics of `station` from the start time of `sim` to
+    /// its current time, computed from the holds and the waits of its
+    /// resource.
+    pub fn station_stats<T: SimState + Clone>(
+        &self,
+        sim: &Simulation<T>,
+        station: StationId,
+    ) -> StationStats {
+        let id = self.resources[station];
+        let res = &sim.resources[id];
+        StationStats {
+            throughput: res.hold_log.len() as f64 / (sim.time() - sim.start_time()),
+            mean_wait: res.total_wait / res.grants as f64,
+            utilization: sim.resource_utilization(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatchSize, Delay, NetworkBuilder, QueueNetwork, Router, Source, Station};
+    #[cfg(feature = "std")]
+    use crate::JitterSpec;
+    use crate::{Effect, EndCondition, Simulation};
+
+    #[cfg(feature = "std")]
+    fn exponential(mean: f64) -> Delay {
+        Delay::Jitter {
+            base: 0.0,
+            dist: JitterSpec::Exponential(mean),
+        }
+    }
+
+    /// An open Jackson network: arrivals at rate 1 to A, that sends half
+    /// of the entities to B, that sends a fifth of them back to A.
+    #[cfg(feature = "std")]
+    #[test]
+    fn jackson_network() {
+        let (mean_a, mean_b) = (0.5, 0.4);
+        let mut sim = Simulation::<Effect>::new();
+        sim.set_seed(7);
+        let mut net = NetworkBuilder::new();
+        net.seed(11);
+        let a = net.station(Station {
+            servers: 1,
+            service: exponential(mean_a),
+        });
+        let b = net.station(Station {
+            servers: 1,
+            service: exponential(mean_b),
+        });
+        net.route(a, Router::Probabilities(vec![(b, 0.5)]));
+        net.route(b, Router::Probabilities(vec![(a, 0.2)]));
+        net.source(Source {
+            interarrival: exponential(1.0),
+            batch: BatchSize::Fixed(1),
+            router: Router::to(a),
+            entities: 20000,
+        });
+        let net = net.build(&mut sim);
+        let sim = sim.run(EndCondition::NoEvents);
+
+        // traffic equations: la = 1 + 0.2 lb, lb = 0.5 la
+        let la = 1.0 / 0.9;
+        let lb = 0.5 * la;
+        let (rho_a, rho_b) = (la * mean_a, lb * mean_b);
+        // each station is an M/M/1 queue
+        let wait_a = rho_a * mean_a / (1.0 - rho_a);
+        let wait_b = rho_b * mean_b / (1.0 - rho_b);
+        // Little's law, with the external arrival rate of 1
+        let sojourn = rho_a / (1.0 - rho_a) + rho_b / (1.0 - rho_b);
+
+        let close = |x: f64, y: f64| (x - y).abs() < 0.05 * y.max(0.2);
+        let sa = net.station_stats(&sim, a);
+        let sb = net.station_stats(&sim, b);
+        assert!(close(sa.throughput, la), "{:?}", sa);
+        assert!(close(sb.throughput, lb), "{:?}", sb);
+        assert!(close(sa.utilization, rho_a), "{:?}", sa);
+        assert!(close(sb.utilization, rho_b), "{:?}", sb);
+        assert!(close(sa.mean_wait, wait_a), "{:?}", sa);
+        assert!(close(sb.mean_wait, wait_b), "{:?}", sb);
+        let sink = net.sink();
+        assert_eq!(sink.departures(), 20000);
+        assert!(
+            close(sink.mean_sojourn_time(), sojourn),
+            "{}",
+            sink.mean_sojourn_time()
+        );
+    }
+
+    #[test]
+    fn rule_router() {
+        let mut sim = Simulation::<Effect>::new();
+        let mut net = NetworkBuilder::new();
+        let fast = net.station(Station {
+            servers: 2,
+            service: Delay::Fixed(1.0),
+        });
+        let slow = net.station(Station {
+            servers: 1,
+            service: Delay::Fixed(3.0),
+        });
+        net.route(
+            fast,
+            Router::Rule(Box::new(move |t| (t < 2.0).then_some(slow))),
+        );
+        net.source(Source {
+            interarrival: Delay::Fixed(1.0),
+            batch: BatchSize::Fixed(1),
+            router: Router::to(fast),
+            entities: 3,
+        });
+        let net = net.build(&mut sim);
+        let sim = sim.run(EndCondition::NoEvents);
+        // only the first entity leaves fast before time 2, and goes to slow
+        // until time 4, after the other two left the network
+        assert_eq!(sim.resource_hold_log(net.resource(slow)).len(), 1);
+        assert_eq!(net.sink().sojourn_times, vec![1.0, 1.0, 4.0]);
+        assert_eq!(sim.time(), 4.0);
+    }
+
+    #[test]
+    fn batch_arrivals() {
+        let mut sim = Simulation::<Effect>::new();
+        let mut net = NetworkBuilder::new();
+        let station = net.station(Station {
+            servers: 10,
+            service: Delay::Fixed(0.5),
+        });
+        net.source(Source {
+            interarrival: Delay::Fixed(1.0),
+            batch: BatchSize::Fixed(3),
+            router: Router::to(station),
+            entities: 7,
+        });
+        net.source(Source {
+            interarrival: Delay::Fixed(1.0),
+            batch: BatchSize::Weights(vec![(0, 1.0), (2, 1.0)]),
+            router: Router::sink(),
+            entities: 100,
+        });
+        let net = net.build(&mut sim);
+        let sim = sim.run(EndCondition::NoEvents);
+        let starts: Vec<f64> = sim
+            .resource_hold_log(net.resource(station))
+            .iter()
+            .map(|&(start, _, _)| start)
+            .collect();
+        // the last batch has only the entity left
+        assert_eq!(starts, vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 2.0]);
+        assert_eq!(net.sink().departures(), 107);
+    }
+
+    #[test]
+    fn tandem() {
+        let mut sim = Simulation::<Effect>::new();
+        let mut net = QueueNetwork::tandem(vec![
+            Station {
+                servers: 1,
+                service: Delay::Fixed(1.0),
+            },
+            Station {
+                servers: 2,
+                service: Delay::Fixed(3.0),
+            },
+        ]);
+        net.arrivals(0, Delay::Fixed(2.0), 5);
+        let net = net.build(&mut sim);
+        let sim = sim.run(EndCondition::NoEvents);
+        for station in 0..2 {
+            assert_eq!(sim.resource_hold_log(net.resource(station)).len(), 5);
+        }
+        // no entity waits at either station, as both keep up with the arrivals
+        assert_eq!(net.sink().sojourn_times, vec![4.0; 5]);
+        assert_eq!(sim.time(), 12.0);
+    }
+}
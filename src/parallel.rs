@@ -0,0 +1,367 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Parallel resumption of independent processes at the same time.
+
+use crate::process::{Process, ProcessState, Step};
+use crate::timer::TimerReply;
+use crate::{
+    EndCondition, Event, PendingEvent, ProcessId, SimContext, SimState, Simulation,
+    StateMachineProcess, StaticProcess, TieBreak, WakeReason,
+};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(feature = "nightly")]
+use core::ops::{Generator, GeneratorState};
+#[cfg(feature = "nightly")]
+use core::pin::Pin;
+use rayon::prelude::*;
+
+/// A generator that can be resumed on another thread by
+/// `Simulation::step_parallel`.
+#[cfg(feature = "nightly")]
+pub type SendSimGen<T> = dyn Generator<SimContext<T>, Yield = T, Return = ()> + Send + Unpin;
+
+/// A process that can be resumed on another thread.
+pub(crate) enum SendProcess<T> {
+    #[cfg(feature = "nightly")]
+    Generator(Box<SendSimGen<T>>),
+    StateMachine(Box<dyn StateMachineProcess<T> + Send>),
+}
+
+impl<T> SendProcess<T> {
+    pub(crate) fn resume(&mut self, context: SimContext<T>) -> ProcessState<T> {
+        match self {
+            #[cfg(feature = "nightly")]
+            SendProcess::Generator(generator) => {
+                match Pin::new(generator.as_mut()).resume(context) {
+                    GeneratorState::Yielded(y) => ProcessState::Yielded(y),
+                    GeneratorState::Complete(()) => ProcessState::Complete,
+                }
+            }
+            SendProcess::StateMachine(machine) => match machine.resume(context) {
+                Step::Yield(y) => ProcessState::Yielded(y),
+                Step::Done => ProcessState::Complete,
+            },
+        }
+    }
+}
+
+/// The context of a process resumed on another thread. Unlike a
+/// `SimContext`, it cannot hold a message.
+struct SendContext<T> {
+    time: f64,
+    global_time: f64,
+    state: T,
+    rejected: bool,
+    timer: Option<TimerReply>,
+    wake: WakeReason,
+    resources: Arc<[(usize, usize)]>,
+    upcoming: Vec<PendingEvent>,
+}
+
+impl<T> SendContext<T> {
+    fn new(context: SimContext<T>) -> SendContext<T> {
+        debug_assert!(context.message.is_none());
+        SendContext {
+            time: context.time,
+            global_time: context.global_time,
+            state: context.state,
+            rejected: context.rejected,
+            timer: context.timer,
+            wake: context.wake,
+            resources: context.resources,
+            upcoming: context.upcoming,
+        }
+    }
+
+    fn into_context(self) -> SimContext<T> {
+        SimContext {
+            time: self.time,
+            global_time: self.global_time,
+            state: self.state,
+            message: None,
+            rejected: self.rejected,
+            timer: self.timer,
+            wake: self.wake,
+            resources: self.resources,
+            upcoming: self.upcoming,
+        }
+    }
+}
+
+impl<T: SimState + Clone + Send, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a new process, like `create_process`, that can be resumed
+    /// on another thread by `step_parallel`.
+    #[cfg(feature = "nightly")]
+    pub fn create_parallel_process(&mut self, process: Box<SendSimGen<T>>) -> ProcessId {
+        self.add_process(Process::Parallel(SendProcess::Generator(process)))
+    }
+
+    /// Create a new process, like `create_sm_process`, that can be
+    /// resumed on another thread by `step_parallel`.
+    pub fn create_parallel_sm_process(
+        &mut self,
+        process: Box<dyn StateMachineProcess<T> + Send>,
+    ) -> ProcessId {
+        self.add_process(Process::Parallel(SendProcess::StateMachine(process)))
+    }
+
+    /// Process the next events at the same time that do not conflict with
+    /// each other, resuming their processes in parallel on the rayon
+    /// thread pool, and return how many were processed.
+    ///
+    /// Two events conflict if `conflict_key` returns the same key for
+    /// them, e.g. because their processes use the same resources, or if
+    /// they resume the same process. The batch is the longest run of
+    /// events, in the order `step` would process them, that do not
+    /// conflict and resume processes created with `create_parallel_process`
+    /// or `create_parallel_sm_process`. After the resumes, what the
+    /// processes yielded is applied in the order of the events, so the
+    /// outcome is the same as processing the batch one `step` at a time,
+    /// as long as the processes do not depend on the `resources` and
+    /// `upcoming` fields of their context, that reflect the simulation
+    /// before the batch.
+    ///
+    /// If the next event cannot be resumed in parallel, or the same-time
+    /// events are not processed in the order they were scheduled, i.e.
+    /// the tie break is not `TieBreak::Fifo` or an event selector is set,
+    /// this is the same as `step`. The resumes in parallel are not profiled.
+    pub fn step_parallel<K, F>(&mut self, conflict_key: F) -> usize
+    where
+        K: Ord,
+        F: Fn(&Event<T>) -> K,
+    {
+        self.step_batch(&conflict_key, usize::MAX)
+    }
+
+    /// Like `step_parallel`, processing at most `limit` events.
+    fn step_batch<K, F>(&mut self, conflict_key: &F, limit: usize) -> usize
+    where
+        K: Ord,
+        F: Fn(&Event<T>) -> K,
+    {
+        let time_before = self.time;
+        self.begin_step();
+        let first = self.pop_next_event();
+        let first = match first {
+            Some(event) if self.in_parallel(&event) => event,
+            Some(_) => {
+                self.finish_step(first, time_before);
+                return 1;
+            }
+            None => {
+                self.finish_step(None, time_before);
+                return 0;
+            }
+        };
+        let mut keys = BTreeSet::new();
+        keys.insert(conflict_key(&first));
+        let mut batch = alloc::vec![];
+        self.add_to_batch(&mut batch, first);
+        while let Some(crate::Queued(next)) = self.future_events.peek() {
+            if batch.len() >= limit
+                || next.time != self.time
+                || !self.in_parallel(next)
+                || batch
+                    .iter()
+                    .any(|(e, _): &(Event<T>, _)| e.process == next.process)
+                || !keys.insert(conflict_key(next))
+            {
+                break;
+            }
+            self.begin_step();
+            let next = self.pop_event().unwrap();
+            self.add_to_batch(&mut batch, next);
+        }
+
+        let mut contexts: BTreeMap<ProcessId, SendContext<T>> = BTreeMap::new();
+        let mut events = Vec::with_capacity(batch.len());
+        for (event, context) in batch {
+            contexts.insert(event.process, context);
+            events.push(event);
+        }
+        let jobs: Vec<_> = self
+            .processes
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(id, process)| match process {
+                Some(Process::Parallel(process)) => {
+                    contexts.remove(&id).map(|context| (id, process, context))
+                }
+                _ => None,
+            })
+            .collect();
+        let mut states: BTreeMap<ProcessId, ProcessState<T>> = jobs
+            .into_par_iter()
+            .map(|(id, process, context)| (id, process.resume(context.into_context())))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect();
+
+        let processed = events.len();
+        let mut time_before = time_before;
+        for event in events {
+            let state = states.remove(&event.process).unwrap();
+            self.apply_resumed(event, state);
+            self.check_step(time_before);
+            self.take_snapshot();
+            time_before = self.time;
+        }
+        processed
+    }
+
+    /// Run the simulation until an ending condition is met, like `run`,
+    /// processing the events with `step_parallel`.
+    ///
+    /// The condition is checked between the batches of events, that are
+    /// kept small enough for `EndCondition::NSteps` and
+    /// `EndCondition::LoggedEvents` to stop at the same event as `run`.
+    /// With `EndCondition::LastEvent` any event could be the one to stop
+    /// at, so the processes are resumed one at a time.
+    pub fn run_parallel<K, F>(mut self, until: EndCondition<T>, conflict_key: F) -> Simulation<T, G>
+    where
+        K: Ord,
+        F: Fn(&Event<T>) -> K,
+    {
+        self.run_steps(&until, |s| {
+            let limit = s.batch_limit(&until);
+            s.step_batch(&conflict_key, limit);
+            true
+        });
+        self
+    }
+
+    /// The number of events that can be processed in a batch without
+    /// skipping past the point where `until` holds.
+    fn batch_limit(&self, until: &EndCondition<T>) -> usize {
+        match until {
+            EndCondition::NSteps(n) => n.saturating_sub(self.steps).max(1),
+            // an event is logged at most once
+            EndCondition::LoggedEvents(n) => n.saturating_sub(self.logged_events).max(1),
+            EndCondition::LastEvent(_) => 1,
+            EndCondition::Any(conditions) | EndCondition::All(conditions) => conditions
+                .iter()
+                .map(|c| self.batch_limit(c))
+                .min()
+                .unwrap_or(usize::MAX),
+            _ => usize::MAX,
+        }
+    }
+
+    /// Whether `event` can be processed in a batch of `step_parallel`.
+    fn in_parallel(&self, event: &Event<T>) -> bool {
+        self.tiebreak == TieBreak::Fifo
+            && self.event_selector.is_none()
+            && matches!(self.processes[event.process], Some(Process::Parallel(_)))
+            && !self.is_retry(event)
+            && !self.is_batch_timeout(event)
+            && !self.deliveries.contains_key(&event.process)
+    }
+
+    fn add_to_batch(&mut self, batch: &mut Vec<(Event<T>, SendContext<T>)>, event: Event<T>) {
+        // the event is neither a retry nor a batch timeout
+        let (event, context) = self.prepare_resume(event).unwrap();
+        batch.push((event, SendContext::new(context)));
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Effect, EndCondition, Event, SimContext, Simulation, Step};
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::sync::Arc;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    /// Eight processes, each sharing a resource with the process four
+    /// ids apart.
+    fn build(parallel: bool) -> Simulation<Effect> {
+        let mut s = Simulation::new();
+        let resources: Vec<_> = (0..4).map(|_| s.create_resource(1)).collect();
+        for p in 0..8 {
+            let r = resources[p % 4];
+            let mut step = 0;
+            let process = move |_: SimContext<Effect>| {
+                step += 1;
+                let cycle = [
+                    Effect::Request(r),
+                    Effect::TimeOut(1.0 + p as f64 % 3.0),
+                    Effect::Release(r),
+                    Effect::TimeOut(0.5),
+                ];
+                if step > 12 {
+                    Step::Done
+                } else {
+                    Step::Yield(cycle[(step - 1) % cycle.len()])
+                }
+            };
+            let id = if parallel {
+                s.create_parallel_sm_process(Box::new(process))
+            } else {
+                s.create_sm_process(Box::new(process))
+            };
+            s.schedule_event(0.0, id, Effect::TimeOut(0.0));
+        }
+        s
+    }
+
+    fn fingerprint(s: &Simulation<Effect>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for (event, state) in s.processed_events() {
+            format!("{:?} {:?}", event, state).hash(&mut hasher);
+        }
+        s.time().to_bits().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn parallel_matches_sequential() {
+        let key = |e: &Event<Effect>| e.process() % 4;
+        let sequential = build(false).run(EndCondition::NoEvents);
+
+        let mut parallel = build(true);
+        // the first events of the processes of different resources
+        assert_eq!(parallel.step_parallel(key), 4);
+        let parallel = parallel.run_parallel(EndCondition::NoEvents, key);
+        assert_eq!(fingerprint(&parallel), fingerprint(&sequential));
+        assert_eq!(parallel.live_process_count(), 0);
+        assert_eq!(build(true).step_parallel(key), 4);
+        assert_eq!(Simulation::<Effect>::new().step_parallel(key), 0);
+    }
+
+    #[test]
+    fn parallel_stops_like_sequential() {
+        let key = |e: &Event<Effect>| e.process();
+        let conditions = || {
+            vec![
+                EndCondition::NSteps(2),
+                EndCondition::NSteps(11),
+                EndCondition::LoggedEvents(7),
+                EndCondition::LastEvent(Arc::new(|e: &Event<Effect>, state: &Effect| {
+                    e.process() == 5 && matches!(state, Effect::Release(_))
+                })),
+            ]
+        };
+        for (sequential, parallel) in conditions().into_iter().zip(conditions()) {
+            let sequential = build(false).run(sequential);
+            let parallel = build(true).run_parallel(parallel, key);
+            assert_eq!(fingerprint(&parallel), fingerprint(&sequential));
+        }
+    }
+}
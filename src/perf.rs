@@ -0,0 +1,91 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Throughput of the engine measured over each run.
+
+use crate::{SimState, Simulation, StaticProcess};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// What a run of the simulation, e.g. a call to `Simulation::run` or
+/// `run_until`, processed, and how long it took, returned by `Simulation::perf_counters`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunCounters {
+    /// Number of steps executed, each processing at most an event
+    pub steps: usize,
+    /// Number of events added to the log
+    pub logged_events: usize,
+    /// Wall-clock time of the run
+    pub elapsed: Duration,
+}
+
+impl RunCounters {
+    /// Returns the number of steps executed per second of wall-clock time.
+    pub fn events_per_second(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Returns the counters of each run, with `run`, `run_collecting`,
+    /// `run_until`, `run_parallel` or `run_streaming`, in the order they
+    /// were made, to monitor the throughput of the engine.
+    ///
+    /// Without the `std` feature there is no clock, and no run is recorded.
+    pub fn perf_counters(&self) -> &[RunCounters] {
+        &self.perf_counters
+    }
+
+    /// Call `run`, recording its counters.
+    pub(crate) fn measure_run<R>(&mut self, run: impl FnOnce(&mut Simulation<T, G>) -> R) -> R {
+        #[cfg(feature = "std")]
+        {
+            let (steps, logged) = (self.steps, self.logged_events);
+            let start = Instant::now();
+            let result = run(self);
+            self.perf_counters.push(RunCounters {
+                steps: self.steps - steps,
+                logged_events: self.logged_events - logged,
+                elapsed: start.elapsed(),
+            });
+            result
+        }
+        // without a clock the runs cannot be measured
+        #[cfg(not(feature = "std"))]
+        run(self)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::{Effect, EndCondition, Simulation, Step};
+
+    #[test]
+    fn perf_counters() {
+        let mut s = Simulation::new();
+        let p = s.create_sm_process(Box::new(|_| Step::Yield(Effect::TimeOut(1.0))));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s.set_log_filter(|e, _| e.time() < 5.0);
+        s.run_collecting(EndCondition::NSteps(10));
+        let s = s.run(EndCondition::NSteps(15));
+        let runs = s.perf_counters();
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[0].steps, runs[0].logged_events), (10, 5));
+        assert_eq!((runs[1].steps, runs[1].logged_events), (5, 0));
+        assert!(runs[0].events_per_second() > 0.0);
+    }
+}
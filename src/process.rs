@@ -0,0 +1,990 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! The backends running the processes of a simulation.
+//!
+//! A process is either a generator, with the `nightly` feature, a future
+//! built from an async block, that suspends itself awaiting the futures
+//! returned by its `ProcessHandle`, or a state machine implementing
+//! `StateMachineProcess`. The simulation resumes all kinds in the same way,
+//! so they can be freely mixed.
+
+use crate::rng::Rng;
+use crate::{Effect, ProcessId, ResourceId, SimContext, SimState, Simulation};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+#[cfg(any(test, feature = "nightly"))]
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::future::Future;
+use core::ops::Range;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+#[cfg(feature = "rayon")]
+use crate::parallel::SendProcess;
+#[cfg(feature = "nightly")]
+use crate::SimGen;
+#[cfg(feature = "nightly")]
+use core::ops::{Generator, GeneratorState};
+
+/// A process of the simulation, whatever backend it is written with.
+pub(crate) enum Process<T, G> {
+    #[cfg(feature = "nightly")]
+    Generator(Box<SimGen<T>>),
+    Async(AsyncProcess<T>),
+    StateMachine(Box<dyn StateMachineProcess<T>>),
+    /// A generator stored in a pool of generators of the same type, at
+    /// the given index.
+    #[cfg(feature = "nightly")]
+    Pooled(Rc<RefCell<dyn GeneratorPool<T>>>, usize),
+    /// A process that can be resumed on another thread.
+    #[cfg(feature = "rayon")]
+    Parallel(SendProcess<T>),
+    /// A process of the static type of the simulation, stored inline.
+    Static(G),
+}
+
+/// The result of resuming a process.
+pub(crate) enum ProcessState<T> {
+    /// The process suspended itself, yielding this state.
+    Yielded(T),
+    /// The process completed.
+    Complete,
+}
+
+impl<T, G: StaticProcess<T>> Process<T, G> {
+    pub(crate) fn resume(&mut self, context: SimContext<T>) -> ProcessState<T> {
+        match self {
+            #[cfg(feature = "nightly")]
+            Process::Generator(generator) => match Pin::new(generator.as_mut()).resume(context) {
+                GeneratorState::Yielded(y) => ProcessState::Yielded(y),
+                GeneratorState::Complete(()) => ProcessState::Complete,
+            },
+            Process::Async(process) => process.resume(context),
+            Process::StateMachine(machine) => match machine.resume(context) {
+                Step::Yield(y) => ProcessState::Yielded(y),
+                Step::Done => ProcessState::Complete,
+            },
+            #[cfg(feature = "nightly")]
+            Process::Pooled(pool, index) => pool.borrow_mut().resume(*index, context),
+            #[cfg(feature = "rayon")]
+            Process::Parallel(process) => process.resume(context),
+            Process::Static(process) => match process.resume(context) {
+                Step::Yield(y) => ProcessState::Yielded(y),
+                Step::Done => ProcessState::Complete,
+            },
+        }
+    }
+}
+
+/// Generators of a single concrete type, stored next to each other.
+///
+/// Resuming one of them takes a single dynamic call, to the pool, after
+/// which the generator is resumed with static dispatch.
+#[cfg(feature = "nightly")]
+pub(crate) trait GeneratorPool<T> {
+    fn resume(&mut self, index: usize, context: SimContext<T>) -> ProcessState<T>;
+}
+
+#[cfg(feature = "nightly")]
+struct Pool<G> {
+    generators: Vec<Option<G>>,
+}
+
+#[cfg(feature = "nightly")]
+impl<T, G> GeneratorPool<T> for Pool<G>
+where
+    G: Generator<SimContext<T>, Yield = T, Return = ()> + Unpin,
+{
+    fn resume(&mut self, index: usize, context: SimContext<T>) -> ProcessState<T> {
+        let generator = self.generators[index]
+            .as_mut()
+            .expect("ERROR. Tried to resume a completed process.");
+        match Pin::new(generator).resume(context) {
+            GeneratorState::Yielded(y) => ProcessState::Yielded(y),
+            GeneratorState::Complete(()) => {
+                // drop the generator together with the process
+                self.generators[index] = None;
+                ProcessState::Complete
+            }
+        }
+    }
+}
+
+/// A process written as a state machine, for example by a code generator,
+/// that does not need generators nor async blocks.
+///
+/// Closures taking the context and returning a `Step` implement it.
+pub trait StateMachineProcess<T> {
+    /// Run the process from where it was last suspended, with the context
+    /// it is resumed with, until it yields a state or completes.
+    fn resume(&mut self, ctx: SimContext<T>) -> Step<T>;
+}
+
+impl<T, F: FnMut(SimContext<T>) -> Step<T>> StateMachineProcess<T> for F {
+    fn resume(&mut self, ctx: SimContext<T>) -> Step<T> {
+        self(ctx)
+    }
+}
+
+/// A state machine yielding the states of `cycle` in order, over and over,
+/// that completes after yielding `steps` states. The tests use it to run
+/// the same scenarios on stable as with generator processes.
+#[cfg(test)]
+pub(crate) fn cycle_process<T: Clone>(
+    cycle: &[T],
+    steps: usize,
+) -> impl FnMut(SimContext<T>) -> Step<T> {
+    let cycle: Vec<T> = cycle.to_vec();
+    let mut step = 0;
+    move |_| {
+        if step == steps {
+            return Step::Done;
+        }
+        step += 1;
+        Step::Yield(cycle[(step - 1) % cycle.len()].clone())
+    }
+}
+
+/// The type of the processes a `Simulation<T, G>` stores inline, in the
+/// vector of its processes, and resumes with static dispatch.
+///
+/// With the `nightly` feature, it is implemented by the generators. Models
+/// whose processes almost all have the same type, e.g. many customers
+/// returned by the same function, save the allocation of a box per process
+/// and the dynamic call of each resume by creating them with
+/// `Simulation::create_static_process`.
+pub trait StaticProcess<T> {
+    /// Run the process from where it was last suspended, with the context
+    /// it is resumed with, until it yields a state or completes.
+    fn resume(&mut self, ctx: SimContext<T>) -> Step<T>;
+}
+
+#[cfg(feature = "nightly")]
+impl<T, G> StaticProcess<T> for G
+where
+    G: Generator<SimContext<T>, Yield = T, Return = ()> + Unpin,
+{
+    fn resume(&mut self, ctx: SimContext<T>) -> Step<T> {
+        match Pin::new(self).resume(ctx) {
+            GeneratorState::Yielded(y) => Step::Yield(y),
+            GeneratorState::Complete(()) => Step::Done,
+        }
+    }
+}
+
+/// The static process type of a `Simulation` whose processes are all
+/// created boxed, the default. It has no values.
+pub enum NoStaticProcess {}
+
+impl<T> StaticProcess<T> for NoStaticProcess {
+    fn resume(&mut self, _: SimContext<T>) -> Step<T> {
+        match *self {}
+    }
+}
+
+/// What a `StateMachineProcess` does when it is resumed.
+#[derive(Debug, Clone)]
+pub enum Step<T> {
+    /// Suspend the process yielding this state, like a generator yields it.
+    Yield(T),
+    /// Complete the process. It is not resumed again.
+    Done,
+}
+
+/// What the simulation and an async process exchange when it is resumed
+/// and when it suspends itself.
+struct Slot<T> {
+    process: ProcessId,
+    time: f64,
+    context: Option<SimContext<T>>,
+    yielded: Option<T>,
+}
+
+pub(crate) struct AsyncProcess<T> {
+    future: Pin<Box<dyn Future<Output = ()>>>,
+    slot: Rc<RefCell<Slot<T>>>,
+}
+
+impl<T> AsyncProcess<T> {
+    fn resume(&mut self, context: SimContext<T>) -> ProcessState<T> {
+        {
+            let mut slot = self.slot.borrow_mut();
+            slot.time = context.time;
+            slot.context = Some(context);
+        }
+        // the futures of the handle never need to be woken up: the
+        // process is polled again when the simulation resumes it
+        let mut cx = Context::from_waker(Waker::noop());
+        match self.future.as_mut().poll(&mut cx) {
+            Poll::Ready(()) => ProcessState::Complete,
+            Poll::Pending => match self.slot.borrow_mut().yielded.take() {
+                Some(y) => ProcessState::Yielded(y),
+                None => panic!(
+                    "An async process can only suspend itself awaiting the futures of its ProcessHandle"
+                ),
+            },
+        }
+    }
+}
+
+/// The handle given to a process created with `create_async_process`,
+/// used to interact with the simulation.
+///
+/// Each interaction returns a future that suspends the process until the
+/// simulation resumes it, yielding the corresponding effect. The futures
+/// must be awaited one at a time: awaiting two of them at once, e.g. joining
+/// them, panics. When the process completes its future is dropped, together
+/// with everything it owns.
+pub struct ProcessHandle<T> {
+    slot: Rc<RefCell<Slot<T>>>,
+}
+
+impl<T> ProcessHandle<T> {
+    /// Suspend the process yielding `state` to the simulation, like a
+    /// generator process yields it.
+    ///
+    /// The returned future resolves to the context the process is resumed
+    /// with. It must be awaited right away, and is the only kind of future
+    /// an async process can wait for.
+    pub fn yield_state(&self, state: T) -> YieldState<T> {
+        YieldState {
+            slot: self.slot.clone(),
+            state: Some(state),
+        }
+    }
+
+    /// Returns the simulation time at which the process was last resumed.
+    pub fn time(&self) -> f64 {
+        self.slot.borrow().time
+    }
+
+    /// Returns the identifier of the process.
+    pub fn id(&self) -> ProcessId {
+        self.slot.borrow().process
+    }
+}
+
+/// Shorthands for the effects, available when the state of the simulation
+/// can be built from an `Effect` alone, as `Effect` itself.
+impl<T: From<Effect>> ProcessHandle<T> {
+    /// Suspend the process yielding `effect`.
+    pub fn effect(&self, effect: Effect) -> YieldState<T> {
+        self.yield_state(effect.into())
+    }
+
+    /// Suspend the process for `time` time units, yielding
+    /// `Effect::TimeOut`.
+    pub fn timeout(&self, time: f64) -> YieldState<T> {
+        self.effect(Effect::TimeOut(time))
+    }
+
+    /// Request a unit of resource `r`, resuming the process once it is
+    /// granted.
+    pub fn request(&self, r: ResourceId) -> YieldState<T> {
+        self.effect(Effect::Request(r))
+    }
+
+    /// Release a unit of resource `r`. The process is resumed right away.
+    pub fn release(&self, r: ResourceId) -> YieldState<T> {
+        self.effect(Effect::Release(r))
+    }
+
+    /// Suspend the process until an event scheduled for it occurs,
+    /// yielding `Effect::Wait`.
+    pub fn wait(&self) -> YieldState<T> {
+        self.effect(Effect::Wait)
+    }
+}
+
+/// The future returned by `ProcessHandle::yield_state` and the other
+/// methods of the handle, resolving to the context the process is
+/// resumed with.
+#[must_use = "the effect is only yielded when the future is awaited"]
+pub struct YieldState<T> {
+    slot: Rc<RefCell<Slot<T>>>,
+    state: Option<T>,
+}
+
+// the future is never pinned in place: its fields are moved freely
+impl<T> Unpin for YieldState<T> {}
+
+impl<T> Future for YieldState<T> {
+    type Output = SimContext<T>;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<SimContext<T>> {
+        match self.state.take() {
+            Some(state) => {
+                let mut slot = self.slot.borrow_mut();
+                assert!(
+                    slot.yielded.is_none(),
+                    "Process {} awaited more than one effect at once",
+                    slot.process
+                );
+                slot.yielded = Some(state);
+                Poll::Pending
+            }
+            None => Poll::Ready(
+                self.slot
+                    .borrow_mut()
+                    .context
+                    .take()
+                    .expect("A process was polled without being resumed by the simulation"),
+            ),
+        }
+    }
+}
+
+impl<T: SimState + Clone + 'static, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a new process written as an async block, that does not need
+    /// the nightly `generators` feature.
+    ///
+    /// `process` is called with the handle of the new process, and the
+    /// future it returns is run each time the process is resumed, until it
+    /// awaits `ProcessHandle::yield_state`. Yielding a state this way has the
+    /// same effect as yielding it from a generator process created with
+    /// `create_process`.
+    ///
+    /// ```
+    /// use desim::{Effect, EndCondition, Simulation};
+    ///
+    /// let mut sim = Simulation::<Effect>::new();
+    /// let cpu = sim.create_resource(1);
+    /// let p = sim.create_async_process(move |handle| async move {
+    ///     for _ in 0..3 {
+    ///         handle.request(cpu).await;
+    ///         handle.timeout(1.0).await;
+    ///         handle.release(cpu).await;
+    ///     }
+    /// });
+    /// sim.schedule_event(0.0, p, Effect::TimeOut(0.0));
+    /// let sim = sim.run(EndCondition::NoEvents);
+    /// assert_eq!(sim.time(), 3.0);
+    /// ```
+    pub fn create_async_process<F, Fut>(&mut self, process: F) -> ProcessId
+    where
+        F: FnOnce(ProcessHandle<T>) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let slot = Rc::new(RefCell::new(Slot {
+            process: self.processes.len(),
+            time: self.time(),
+            context: None,
+            yielded: None,
+        }));
+        let future = Box::pin(process(ProcessHandle { slot: slot.clone() }));
+        self.add_process(Process::Async(AsyncProcess { future, slot }))
+    }
+
+    /// Create a new process from a state machine, that behaves like a
+    /// generator process yielding the same states.
+    ///
+    /// ```
+    /// use desim::{Effect, EndCondition, Simulation, Step};
+    ///
+    /// let mut sim = Simulation::<Effect>::new();
+    /// let mut remaining = 3;
+    /// let p = sim.create_sm_process(Box::new(move |_| {
+    ///     if remaining == 0 {
+    ///         return Step::Done;
+    ///     }
+    ///     remaining -= 1;
+    ///     Step::Yield(Effect::TimeOut(1.0))
+    /// }));
+    /// sim.schedule_event(0.0, p, Effect::TimeOut(0.0));
+    /// let sim = sim.run(EndCondition::NoEvents);
+    /// assert_eq!(sim.time(), 3.0);
+    /// ```
+    pub fn create_sm_process(&mut self, process: Box<dyn StateMachineProcess<T>>) -> ProcessId {
+        self.add_process(Process::StateMachine(process))
+    }
+
+    /// Create a process for each of `generators`, that all have the same
+    /// concrete type, e.g. being returned by the same function.
+    ///
+    /// The processes behave like the ones created with `create_process`,
+    /// but the generators are stored together instead of each in its own
+    /// box, which saves an allocation per process. Each resume still makes
+    /// a dynamic call, to the pool: when almost all the processes have the
+    /// same type, `create_static_process` also avoids it.
+    ///
+    /// Returns the identifiers of the processes, in the order of the
+    /// generators.
+    #[cfg(feature = "nightly")]
+    pub fn create_process_pool<P, I>(&mut self, generators: I) -> Range<ProcessId>
+    where
+        P: Generator<SimContext<T>, Yield = T, Return = ()> + Unpin + 'static,
+        I: IntoIterator<Item = P>,
+    {
+        let generators: Vec<Option<P>> = generators.into_iter().map(Some).collect();
+        let n = generators.len();
+        let pool: Rc<RefCell<dyn GeneratorPool<T>>> = Rc::new(RefCell::new(Pool { generators }));
+        let start = self.processes.len();
+        for index in 0..n {
+            self.add_process(Process::Pooled(pool.clone(), index));
+        }
+        start..start + n
+    }
+
+    /// Create a new process of the static type `G` of the simulation,
+    /// stored inline in the vector of the processes and resumed with
+    /// static dispatch. Otherwise it behaves like the processes created
+    /// with `create_process`, that can be mixed with it.
+    ///
+    /// The simulation must be created with `with_static_processes`, whose
+    /// type parameter `G` is usually inferred from this call.
+    pub fn create_static_process(&mut self, process: G) -> ProcessId {
+        self.add_process(Process::Static(process))
+    }
+
+    /// Create `n` processes, the `i`-th one with the generator returned by
+    /// `factory` for the `i`-th `Indexed`, e.g. to create many customers
+    /// that differ only by their index.
+    ///
+    /// The `i`-th process is named `prefix#i`, e.g. `customer#42`, as
+    /// returned by `process_name`. Returns the identifiers of the
+    /// processes, that are contiguous and in the order of the indices.
+    ///
+    /// The factory gets an `Indexed` rather than the bare index so that it
+    /// can use the name and the seed of the process too, and the
+    /// identifiers are returned as a `Range` since they are contiguous;
+    /// `collect` turns it into a `Vec`. Like `create_process`, this needs
+    /// the nightly toolchain: `create_sm_processes` and
+    /// `create_async_processes` create the processes with the stable
+    /// backends.
+    #[cfg(feature = "nightly")]
+    pub fn create_processes<F>(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        mut factory: F,
+    ) -> Range<ProcessId>
+    where
+        F: FnMut(&Indexed) -> Box<SimGen<T>>,
+    {
+        self.create_indexed(prefix, n, |sim, indexed| {
+            sim.add_process(Process::Generator(factory(indexed)))
+        })
+    }
+
+    /// Create processes like `create_processes`, with the state machines
+    /// returned by `factory`.
+    pub fn create_sm_processes<F>(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        mut factory: F,
+    ) -> Range<ProcessId>
+    where
+        F: FnMut(&Indexed) -> Box<dyn StateMachineProcess<T>>,
+    {
+        self.create_indexed(prefix, n, |sim, indexed| {
+            sim.create_sm_process(factory(indexed))
+        })
+    }
+
+    /// Create processes like `create_processes`, with the futures
+    /// returned by `factory` for the `Indexed` and the handle of each
+    /// process, like `create_async_process`.
+    ///
+    /// ```
+    /// use desim::{Effect, EndCondition, Simulation};
+    ///
+    /// let mut sim = Simulation::<Effect>::new();
+    /// let customers = sim.create_async_processes("customer", 3, |indexed, handle| {
+    ///     let wait = indexed.index as f64;
+    ///     async move {
+    ///         handle.timeout(wait).await;
+    ///     }
+    /// });
+    /// sim.schedule_processes(customers.clone(), |_| 0.0);
+    /// assert_eq!(sim.process_name(customers.start + 2), Some("customer#2"));
+    /// let sim = sim.run(EndCondition::NoEvents);
+    /// assert_eq!(sim.time(), 2.0);
+    /// ```
+    pub fn create_async_processes<F, Fut>(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        mut factory: F,
+    ) -> Range<ProcessId>
+    where
+        F: FnMut(&Indexed, ProcessHandle<T>) -> Fut,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.create_indexed(prefix, n, |sim, indexed| {
+            sim.create_async_process(|handle| factory(indexed, handle))
+        })
+    }
+
+    /// Create processes like `create_processes`, and schedule the `i`-th
+    /// one to start `start(i)` time units from now.
+    #[cfg(feature = "nightly")]
+    pub fn create_scheduled_processes<F, S>(
+        &mut self,
+        prefix: &str,
+        n: usize,
+        factory: F,
+        start: S,
+    ) -> Range<ProcessId>
+    where
+        F: FnMut(&Indexed) -> Box<SimGen<T>>,
+        S: FnMut(usize) -> f64,
+        T: From<Effect>,
+    {
+        let ids = self.create_processes(prefix, n, factory);
+        self.schedule_processes(ids.clone(), start);
+        ids
+    }
+
+    /// Schedule the `i`-th process of `ids`, e.g. created together with
+    /// `create_sm_processes`, to start `start(i)` time units from now.
+    pub fn schedule_processes<S>(&mut self, ids: Range<ProcessId>, mut start: S)
+    where
+        S: FnMut(usize) -> f64,
+        T: From<Effect>,
+    {
+        for (i, p) in ids.enumerate() {
+            self.schedule_event(start(i), p, T::from(Effect::TimeOut(0.0)));
+        }
+    }
+
+    /// Returns the name of `process`, if it was created with
+    /// `create_processes` or its variants.
+    pub fn process_name(&self, process: ProcessId) -> Option<&str> {
+        self.process_names.get(&process).map(String::as_str)
+    }
+
+    /// Create `n` processes with `create`, naming them and deriving their
+    /// seeds from their index.
+    fn create_indexed<F>(&mut self, prefix: &str, n: usize, mut create: F) -> Range<ProcessId>
+    where
+        F: FnMut(&mut Self, &Indexed) -> ProcessId,
+    {
+        let start = self.processes.len();
+        for index in 0..n {
+            let name = format!("{}#{}", prefix, index);
+            // FNV-1a of the name, so that the seeds do not depend on the
+            // order the processes are created in
+            let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325, |h: u64, b| {
+                (h ^ u64::from(b)).wrapping_mul(0x100_0000_01b3)
+            });
+            let indexed = Indexed {
+                index,
+                seed: Rng::new(self.seed ^ hash).next_u64(),
+                name,
+            };
+            let p = create(self, &indexed);
+            self.process_names.insert(p, indexed.name);
+        }
+        start..start + n
+    }
+}
+
+/// A process created with `create_processes` or its variants, with what
+/// it derives from its index among them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Indexed {
+    /// The index of the process among the ones created together, from 0.
+    pub index: usize,
+    /// The name of the process, the prefix followed by `#` and the index.
+    pub name: String,
+    /// A seed of a random number generator of the process, derived from
+    /// the seed of the simulation and the name, so that each process has
+    /// its own stream of random numbers.
+    ///
+    /// It is derived once, when the process is created: the seed of the
+    /// simulation must be set with `set_seed` before, as setting it later
+    /// does not change the seeds of the processes already created.
+    pub seed: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cycle_process;
+    use crate::{Effect, EndCondition::NoEvents, ProcessId, Simulation, WakeReason};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn async_process() {
+        let mut s = Simulation::new();
+        let p = s.create_async_process(|handle| async move {
+            let mut a = 0.0;
+            for _ in 0..3 {
+                a += 1.0;
+                let ctx = handle.yield_state(Effect::TimeOut(a)).await;
+                assert_eq!(ctx.time(), handle.time());
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        let s = s.run(NoEvents);
+        let times: Vec<_> = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+        assert_eq!(times, vec![0.0, 1.0, 3.0]);
+        assert_eq!(s.time(), 6.0);
+        assert_eq!(s.live_process_count(), 0);
+    }
+
+    #[test]
+    fn indexed_processes() {
+        let mut s = Simulation::new();
+        let r = s.create_resource(1);
+        let seeds = |s: &mut Simulation<Effect>, prefix| {
+            let mut seeds = Vec::new();
+            s.create_sm_processes(prefix, 3, |c| {
+                seeds.push(c.seed);
+                let cycle = [
+                    Effect::Request(r),
+                    Effect::TimeOut(1.0 + c.index as f64),
+                    Effect::Release(r),
+                ];
+                Box::new(cycle_process(&cycle, cycle.len()))
+            });
+            seeds
+        };
+        let customers = seeds(&mut s, "customer");
+        let servers = seeds(&mut s, "server");
+        s.schedule_processes(0..3, |i| 10.0 * i as f64);
+        assert_eq!(s.process_name(1), Some("customer#1"));
+        assert_eq!(s.process_name(3), Some("server#0"));
+        assert_eq!(s.process_name(6), None);
+        // each process has its own seed, that depends on its name only
+        assert!(customers.iter().all(|seed| !servers.contains(seed)));
+        assert_ne!(customers[0], customers[1]);
+        let mut other = Simulation::new();
+        assert_eq!(seeds(&mut other, "server"), servers);
+        other.set_seed(1);
+        assert_ne!(seeds(&mut other, "server"), servers);
+        let s = s.run(NoEvents);
+        assert_eq!(
+            s.resource_hold_log(r),
+            &[(0.0, 1.0, 0), (10.0, 12.0, 1), (20.0, 23.0, 2)]
+        );
+    }
+
+    /// Creates a process yielding the effects in order.
+    type Script = fn(&mut Simulation<Effect>, Vec<Effect>) -> ProcessId;
+
+    fn async_script(s: &mut Simulation<Effect>, effects: Vec<Effect>) -> ProcessId {
+        s.create_async_process(move |handle| async move {
+            for effect in effects {
+                handle.yield_state(effect).await;
+            }
+        })
+    }
+
+    /// The process backends the core tests are run with.
+    fn backends() -> Vec<Script> {
+        #[allow(unused_mut)]
+        let mut backends: Vec<Script> = vec![async_script];
+        #[cfg(feature = "nightly")]
+        backends.push(crate::tests::generators::generator_script);
+        backends
+    }
+
+    #[test]
+    fn backend_resource() {
+        for script in backends() {
+            let mut s = Simulation::new();
+            let r = s.create_resource(1);
+            let p1 = script(
+                &mut s,
+                vec![Effect::Request(r), Effect::TimeOut(7.0), Effect::Release(r)],
+            );
+            let p2 = script(
+                &mut s,
+                vec![Effect::Request(r), Effect::TimeOut(3.0), Effect::Release(r)],
+            );
+            s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+            s.schedule_event(2.0, p2, Effect::TimeOut(0.));
+            let s = s.run(NoEvents);
+            let log: Vec<_> = s
+                .processed_events()
+                .iter()
+                .map(|(e, _)| (e.time(), e.process()))
+                .collect();
+            assert_eq!(
+                log,
+                vec![(0.0, 0), (0.0, 0), (2.0, 1), (7.0, 0), (7.0, 1), (10.0, 1)]
+            );
+            // p2 waits from 2.0 and only holds the resource after p1 released it
+            assert_eq!(s.resource_hold_log(r), &[(0.0, 7.0, p1), (7.0, 10.0, p2)]);
+            assert_eq!(s.time(), 10.0);
+        }
+    }
+
+    #[test]
+    fn backend_timeouts() {
+        for script in backends() {
+            let mut s = Simulation::new();
+            let p = script(
+                &mut s,
+                vec![
+                    Effect::TimeOut(1.0),
+                    Effect::TimeOut(2.0),
+                    Effect::TimeOut(3.0),
+                ],
+            );
+            s.schedule_event(0.0, p, Effect::TimeOut(0.));
+            let s = s.run(NoEvents);
+            let times: Vec<_> = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+            assert_eq!(times, vec![0.0, 1.0, 3.0]);
+            assert_eq!(s.time(), 6.0);
+        }
+    }
+
+    #[test]
+    fn backend_logging() {
+        use crate::LogLevel;
+
+        for script in backends() {
+            let mut s = Simulation::new();
+            let traced = script(&mut s, vec![Effect::Trace, Effect::TimeOut(1.0)]);
+            let quiet = script(&mut s, vec![Effect::TimeOut(1.0)]);
+            s.set_process_log_level(traced, LogLevel::Effects);
+            s.set_process_log_level(quiet, LogLevel::Off);
+            s.schedule_event(0.0, traced, Effect::TimeOut(0.));
+            s.schedule_event(0.0, quiet, Effect::TimeOut(0.));
+            let s = s.run(NoEvents);
+            let log: Vec<_> = s
+                .processed_events()
+                .iter()
+                .map(|(e, state)| (e.time(), e.process(), state.code()))
+                .collect();
+            // the trace of `traced` and all the events of `quiet` are not logged
+            assert_eq!(
+                log,
+                vec![(0.0, traced, Effect::TimeOut(1.0).code())],
+                "{:?}",
+                s.processed_events()
+            );
+        }
+    }
+
+    #[test]
+    fn backend_completion() {
+        use crate::EndCondition::AllProcessesCompleted;
+
+        for script in backends() {
+            let mut s = Simulation::new();
+            let short = script(&mut s, vec![Effect::TimeOut(1.0)]);
+            let long = script(&mut s, vec![Effect::TimeOut(5.0)]);
+            let endless = script(&mut s, vec![Effect::Wait]);
+            s.schedule_event(0.0, short, Effect::TimeOut(0.));
+            s.schedule_event(0.0, long, Effect::TimeOut(0.));
+            s.step();
+            s.step();
+            assert_eq!(s.live_process_count(), 3);
+            let s = s.run(AllProcessesCompleted);
+            // `endless` never had an event, so it is not waited for
+            assert_eq!(s.time(), 5.0);
+            assert_eq!(s.live_process_count(), 1);
+            assert!(s.processes[endless].is_some());
+        }
+    }
+
+    #[test]
+    fn async_process_dropped_on_completion() {
+        use std::rc::Rc;
+
+        let mut s = Simulation::new();
+        let owned = Rc::new(());
+        let held = owned.clone();
+        let p = s.create_async_process(move |handle| async move {
+            let _held = held;
+            assert_eq!(handle.id(), 0);
+            handle.timeout(1.0).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s.step();
+        assert_eq!(Rc::strong_count(&owned), 2);
+        s.step();
+        assert_eq!(s.live_process_count(), 0);
+        assert_eq!(Rc::strong_count(&owned), 1);
+    }
+
+    #[test]
+    fn sm_resource_hold_log() {
+        use crate::{SimContext, StateMachineProcess, Step};
+
+        /// Requests the resource, holds it and releases it.
+        struct User {
+            r: usize,
+            hold: f64,
+            pc: usize,
+        }
+
+        impl StateMachineProcess<Effect> for User {
+            fn resume(&mut self, _: SimContext<Effect>) -> Step<Effect> {
+                self.pc += 1;
+                match self.pc {
+                    1 => Step::Yield(Effect::Request(self.r)),
+                    2 => Step::Yield(Effect::TimeOut(self.hold)),
+                    3 => Step::Yield(Effect::Release(self.r)),
+                    _ => Step::Done,
+                }
+            }
+        }
+
+        let mut s = Simulation::new();
+        let r = s.create_resource(1);
+        let p1 = s.create_sm_process(Box::new(User {
+            r,
+            hold: 7.0,
+            pc: 0,
+        }));
+        let p2 = s.create_sm_process(Box::new(User {
+            r,
+            hold: 3.0,
+            pc: 0,
+        }));
+        s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+        s.schedule_event(2.0, p2, Effect::TimeOut(0.));
+        let s = s.run(NoEvents);
+        // p2 waits from 2.0 and only holds the resource after p1 released it
+        assert_eq!(s.resource_hold_log(r), &[(0.0, 7.0, p1), (7.0, 10.0, p2)]);
+        assert_eq!(s.processed_events().len(), 6);
+        assert_eq!(s.live_process_count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "awaited more than one effect at once")]
+    fn async_process_joining_effects() {
+        use std::future::Future;
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        // polls both futures, like a `join` would
+        struct Join<A, B>(A, B);
+        impl<A: Future + Unpin, B: Future + Unpin> Future for Join<A, B> {
+            type Output = ();
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+                let _ = Pin::new(&mut self.0).poll(cx);
+                let _ = Pin::new(&mut self.1).poll(cx);
+                Poll::Pending
+            }
+        }
+
+        let mut s = Simulation::new();
+        let p = s.create_async_process(|handle| async move {
+            Join(handle.timeout(1.0), handle.timeout(2.0)).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s.step();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "An async process can only suspend itself awaiting the futures of its ProcessHandle"
+    )]
+    fn async_process_awaiting_other_futures() {
+        let mut s = Simulation::<Effect>::new();
+        let p = s.create_async_process(|_| std::future::pending());
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s.step();
+    }
+
+    /// Runs the simulation and returns the time and the wake reason the
+    /// process `watched` saw at each of its resumptions, after the first.
+    fn wake_reasons<F>(build: F) -> Vec<(f64, WakeReason)>
+    where
+        F: FnOnce(&mut Simulation<Effect>, Rc<RefCell<Vec<(f64, WakeReason)>>>),
+    {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut s = Simulation::new();
+        build(&mut s, log.clone());
+        s.run(NoEvents);
+        log.take()
+    }
+
+    #[test]
+    fn async_wake_reason_timeout() {
+        let log = wake_reasons(|s, log| {
+            let p = s.create_async_process(move |handle| async move {
+                let ctx = handle.timeout(2.0).await;
+                log.borrow_mut().push((ctx.time(), ctx.wake_reason()));
+            });
+            s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        });
+        assert_eq!(log, vec![(2.0, WakeReason::Scheduled)]);
+    }
+
+    #[test]
+    fn async_wake_reason_resource_granted() {
+        let mut resource = None;
+        let log = wake_reasons(|s, log| {
+            let r = s.create_resource(1);
+            resource = Some(r);
+            let holder = s.create_async_process(move |handle| async move {
+                handle.request(r).await;
+                handle.timeout(3.0).await;
+                handle.release(r).await;
+            });
+            let waiter = s.create_async_process(move |handle| async move {
+                let ctx = handle.request(r).await;
+                log.borrow_mut().push((ctx.time(), ctx.wake_reason()));
+                handle.release(r).await;
+            });
+            s.schedule_event(0.0, holder, Effect::TimeOut(0.));
+            s.schedule_event(1.0, waiter, Effect::TimeOut(0.));
+        });
+        let granted = WakeReason::ResourceAcquired {
+            resource: resource.unwrap(),
+            unit: 0,
+            waited: 2.0,
+        };
+        assert_eq!(log, vec![(3.0, granted)]);
+    }
+
+    #[test]
+    fn async_wake_reason_interrupt() {
+        let mut timer = None;
+        let log = wake_reasons(|s, log| {
+            let timer_set = Rc::new(RefCell::new(None));
+            let seen = timer_set.clone();
+            let waiter = s.create_async_process(move |handle| async move {
+                let ctx = handle.effect(Effect::SetTimer(10.0)).await;
+                *timer_set.borrow_mut() = ctx.timer_set();
+                // interrupted by the other process before the timer expires
+                let ctx = handle.wait().await;
+                log.borrow_mut().push((ctx.time(), ctx.wake_reason()));
+                let ctx = handle.wait().await;
+                log.borrow_mut().push((ctx.time(), ctx.wake_reason()));
+            });
+            let interrupter = s.create_async_process(move |handle| async move {
+                handle
+                    .effect(Effect::Event {
+                        time: 0.0,
+                        process: waiter,
+                    })
+                    .await;
+            });
+            s.schedule_event(0.0, waiter, Effect::TimeOut(0.));
+            s.schedule_event(2.0, interrupter, Effect::TimeOut(0.));
+            timer = Some(seen);
+        });
+        let timer = timer.unwrap().take().expect("the timer is set");
+        assert_eq!(
+            log,
+            vec![
+                (2.0, WakeReason::Scheduled),
+                (10.0, WakeReason::TimerExpired { timer })
+            ]
+        );
+    }
+}
@@ -0,0 +1,146 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Streaming estimation of quantiles.
+
+/// Estimates a quantile of a stream of samples in constant memory,
+/// using the P² algorithm by Jain and Chlamtac.
+///
+/// The estimator keeps five markers whose heights approximate the minimum,
+/// the `p/2`, `p`, `(1+p)/2` quantiles and the maximum of the samples,
+/// adjusting them with a piecewise-parabolic interpolation at each sample.
+#[derive(Debug, Clone)]
+pub(crate) struct P2Quantile {
+    p: f64,
+    count: usize,
+    /// Marker heights
+    heights: [f64; 5],
+    /// Actual marker positions
+    positions: [f64; 5],
+    /// Desired marker positions
+    desired: [f64; 5],
+    /// Increments of the desired positions at each sample
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    pub(crate) fn new(p: f64) -> P2Quantile {
+        assert!((0.0..=1.0).contains(&p), "Quantile {} is not in [0, 1]", p);
+        P2Quantile {
+            p,
+            count: 0,
+            heights: [0.0; 5],
+            positions: [0.0, 1.0, 2.0, 3.0, 4.0],
+            desired: [0.0, 2.0 * p, 4.0 * p, 2.0 + 2.0 * p, 4.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    pub(crate) fn quantile(&self) -> f64 {
+        self.p
+    }
+
+    pub(crate) fn add(&mut self, x: f64) {
+        if self.count < 5 {
+            // the first samples are stored as they are
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            }
+            return;
+        }
+        self.count += 1;
+
+        let h = &mut self.heights;
+        let k = if x < h[0] {
+            h[0] = x;
+            0
+        } else if x >= h[4] {
+            h[4] = x;
+            3
+        } else {
+            (0..4).find(|&i| x < h[i + 1]).unwrap()
+        };
+        for n in self.positions[k + 1..].iter_mut() {
+            *n += 1.0;
+        }
+        for (d, inc) in self.desired.iter_mut().zip(self.increments.iter()) {
+            *d += inc;
+        }
+
+        for i in 1..4 {
+            let n = &mut self.positions;
+            let d = self.desired[i] - n[i];
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let parabolic = h[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (h[i + 1] - h[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (h[i] - h[i - 1]) / (n[i] - n[i - 1]));
+                h[i] = if h[i - 1] < parabolic && parabolic < h[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    h[i] + d * (h[j] - h[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    /// Returns the current estimate, or NaN if there are no samples.
+    pub(crate) fn estimate(&self) -> f64 {
+        match self.count {
+            0 => f64::NAN,
+            c if c < 5 => {
+                let mut samples = self.heights[..c].to_vec();
+                samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                // round to the nearest sample, without the float functions of std
+                samples[((c - 1) as f64 * self.p + 0.5) as usize]
+            }
+            _ => self.heights[2],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::P2Quantile;
+
+    #[test]
+    fn p2_quantile() {
+        let mut p50 = P2Quantile::new(0.5);
+        let mut p95 = P2Quantile::new(0.95);
+        assert!(p50.estimate().is_nan());
+        // a permutation of 0..1000
+        for i in 0..1000 {
+            let x = ((i * 7919) % 1000) as f64;
+            p50.add(x);
+            p95.add(x);
+        }
+        assert!((p50.estimate() - 500.0).abs() < 10.0, "{}", p50.estimate());
+        assert!((p95.estimate() - 950.0).abs() < 10.0, "{}", p95.estimate());
+    }
+
+    #[test]
+    fn few_samples() {
+        let mut q = P2Quantile::new(0.5);
+        for x in [3.0, 1.0, 2.0].iter() {
+            q.add(*x);
+        }
+        assert_eq!(q.estimate(), 2.0);
+    }
+}
@@ -0,0 +1,174 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Grouping and aggregation of the log of processed events.
+
+use crate::{Event, ProcessId, SimState, Simulation, StaticProcess};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// A read-only view over the log of processed events, returned by
+/// `Simulation::log`, providing common grouping and aggregation queries.
+///
+/// Queries borrow the logged events instead of copying them.
+pub struct LogView<'a, T> {
+    events: &'a [(Event<T>, T)],
+    start: f64,
+}
+
+/// The log of processed events split into buckets of equal duration,
+/// returned by `LogView::bucketed`.
+pub struct Buckets<'a, T> {
+    events: &'a [(Event<T>, T)],
+    start: f64,
+    width: f64,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Returns a view over the log of processed events that can be used
+    /// to query it.
+    pub fn log(&self) -> LogView<'_, T> {
+        LogView {
+            events: self.processed_events(),
+            start: self.start_time(),
+        }
+    }
+}
+
+impl<'a, T> LogView<'a, T> {
+    /// Iterate over the logged events in the order they were processed.
+    pub fn iter(&self) -> impl Iterator<Item = &'a (Event<T>, T)> + 'a {
+        self.events.iter()
+    }
+
+    /// Returns the logged events of each process, in the order they were
+    /// processed.
+    pub fn group_by_process(&self) -> BTreeMap<ProcessId, Vec<&'a (Event<T>, T)>> {
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for entry in self.events.iter() {
+            groups.entry(entry.0.process).or_default().push(entry);
+        }
+        groups
+    }
+
+    /// Returns the number of logged events of each process.
+    pub fn count_by_process(&self) -> BTreeMap<ProcessId, usize> {
+        let mut counts = BTreeMap::new();
+        for (event, _) in self.events.iter() {
+            *counts.entry(event.process).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the number of logged events for each kind of effect,
+    /// identified by `Effect::code()`.
+    pub fn count_by_effect(&self) -> BTreeMap<u8, usize>
+    where
+        T: SimState,
+    {
+        let mut counts = BTreeMap::new();
+        for (_, state) in self.events.iter() {
+            *counts.entry(state.effect().code()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Split the log in buckets of `width` time units, starting from the
+    /// start time of the simulation.
+    pub fn bucketed(&self, width: f64) -> Buckets<'a, T> {
+        assert!(width > 0.0, "The width of the buckets must be positive");
+        Buckets {
+            events: self.events,
+            start: self.start,
+            width,
+        }
+    }
+
+    /// Returns, for each event matching `start`, the process and the time
+    /// elapsed until the next event of the same process matching `end`.
+    ///
+    /// For example, the time each process held a resource can be computed
+    /// passing a predicate matching its requests and one matching its releases.
+    /// Events matching `start` that are not followed by an event matching
+    /// `end` are ignored.
+    pub fn durations_between<S, E>(&self, start: S, end: E) -> Vec<(ProcessId, f64)>
+    where
+        S: Fn(&Event<T>, &T) -> bool,
+        E: Fn(&Event<T>, &T) -> bool,
+    {
+        let mut open: BTreeMap<ProcessId, f64> = BTreeMap::new();
+        let mut durations = Vec::new();
+        for (event, state) in self.events.iter() {
+            if let Some(since) = open.get(&event.process).copied() {
+                if end(event, state) {
+                    durations.push((event.process, event.time - since));
+                    open.remove(&event.process);
+                    continue;
+                }
+            }
+            if start(event, state) {
+                open.entry(event.process).or_insert(event.time);
+            }
+        }
+        durations
+    }
+}
+
+impl<'a, T> Buckets<'a, T> {
+    fn index(&self, time: f64) -> usize {
+        ((time - self.start) / self.width) as usize
+    }
+
+    /// Returns the start time and the number of events of each bucket,
+    /// from the first one to the last non-empty one.
+    pub fn count(&self) -> Vec<(f64, usize)> {
+        let mut counts: Vec<usize> = Vec::new();
+        for (event, _) in self.events.iter() {
+            let i = self.index(event.time);
+            if counts.len() <= i {
+                counts.resize(i + 1, 0);
+            }
+            counts[i] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, c)| (self.start + i as f64 * self.width, c))
+            .collect()
+    }
+
+    /// Returns the start time of each bucket and the sum of `f` over
+    /// its events, from the first bucket to the last non-empty one.
+    pub fn sum<F>(&self, f: F) -> Vec<(f64, f64)>
+    where
+        F: Fn(&Event<T>, &T) -> f64,
+    {
+        let mut sums: Vec<f64> = Vec::new();
+        for (event, state) in self.events.iter() {
+            let i = self.index(event.time);
+            if sums.len() <= i {
+                sums.resize(i + 1, 0.0);
+            }
+            sums[i] += f(event, state);
+        }
+        sums.into_iter()
+            .enumerate()
+            .map(|(i, s)| (self.start + i as f64 * self.width, s))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,75 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+fn resource_example() -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(3.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(2.0, p2, Effect::TimeOut(2.));
+    s.run(NoEvents)
+}
+
+#[test]
+fn group_and_count() {
+    let s = resource_example();
+    let log = s.log();
+    let groups = log.group_by_process();
+    assert_eq!(groups.len(), 2);
+    let times: Vec<_> = groups[&1].iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(times, vec![2.0, 7.0, 10.0]);
+    assert_eq!(
+        log.count_by_process().into_iter().collect::<Vec<_>>(),
+        vec![(0, 3), (1, 3)]
+    );
+
+    let by_effect = log.count_by_effect();
+    assert_eq!(by_effect[&Effect::Request(0).code()], 2);
+    assert_eq!(by_effect[&Effect::TimeOut(0.0).code()], 2);
+    assert_eq!(by_effect[&Effect::Release(0).code()], 2);
+}
+
+#[test]
+fn buckets() {
+    let s = resource_example();
+    let buckets = s.log().bucketed(5.0);
+    assert_eq!(buckets.count(), vec![(0.0, 3), (5.0, 2), (10.0, 1)]);
+    let timeouts = buckets.sum(|_, state| match state {
+        Effect::TimeOut(t) => *t,
+        _ => 0.0,
+    });
+    assert_eq!(timeouts, vec![(0.0, 7.0), (5.0, 3.0), (10.0, 0.0)]);
+}
+
+#[test]
+fn durations_between() {
+    let s = resource_example();
+    let holds = s.log().durations_between(
+        |_, state| matches!(state, Effect::Request(_)),
+        |_, state| matches!(state, Effect::Release(_)),
+    );
+    assert_eq!(holds, vec![(0, 7.0), (1, 8.0)]);
+}
@@ -0,0 +1,69 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Rendezvous between the processes requesting and providing a resource.
+
+use crate::{Event, ResourceId, SimState, Simulation, StaticProcess};
+use alloc::collections::VecDeque;
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a resource without units, where a process yielding
+    /// `Effect::Request` meets a process yielding `Effect::Provide`.
+    ///
+    /// The first of the two waits for the other one to arrive, then both
+    /// are resumed at the same time, the waiting one first. Requesters and
+    /// providers are met in the order they arrive. A requester does not
+    /// hold anything after the meeting, and must not release the resource.
+    pub fn create_rendezvous(&mut self) -> ResourceId {
+        let id = self.create_resource(0);
+        self.resources[id].providers = Some(VecDeque::new());
+        id
+    }
+
+    /// Meet the first provider waiting for rendezvous `r`, or wait for one.
+    pub(crate) fn meet_provider(&mut self, r: ResourceId, event: Event<T>, state: T) {
+        let res = &mut self.resources[r];
+        match res.providers.as_mut().unwrap().pop_front() {
+            Some(provider) => {
+                res.record_grant(0.0);
+                self.schedule_at(self.time, provider.process, provider.state);
+                self.schedule_at(self.time, event.process, state);
+            }
+            None => res.queue.push_back(Event { state, ..event }),
+        }
+    }
+
+    /// Meet the first requester waiting for rendezvous `r`, or wait for one.
+    pub(crate) fn meet_requester(&mut self, r: ResourceId, event: Event<T>) {
+        let res = &mut self.resources[r];
+        let providers = res.providers.as_mut().unwrap_or_else(|| {
+            panic!(
+                "Process {} yielded Effect::Provide on resource {}, that is not a rendezvous",
+                event.process, r
+            )
+        });
+        match res.queue.pop_front() {
+            Some(requester) => {
+                res.record_grant(self.time - requester.time);
+                self.schedule_at(self.time, requester.process, requester.state);
+                self.schedule_at(self.time, event.process, event.state);
+            }
+            None => providers.push_back(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,64 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+#[test]
+fn meet_at_the_latest_arrival() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_rendezvous();
+    s.record_wait_samples(r, true);
+    let consumer = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Request(r)).await;
+        assert_eq!(ctx.time(), 7.0);
+    });
+    let producer = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Provide(r)).await;
+        assert_eq!(ctx.time(), 7.0);
+    });
+    s.schedule_event(3.0, consumer, Effect::TimeOut(0.));
+    s.schedule_event(7.0, producer, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+    assert_eq!(s.time(), 7.0);
+    assert_eq!(s.completed, 2);
+    // the consumer waited from 3 to 7
+    assert_eq!(s.wait_time_cdf(r), vec![(4.0, 1.0)]);
+}
+
+#[test]
+fn providers_wait_in_order() {
+    use crate::{Effect, EndCondition::NoEvents, ProcessId, SimContext, Simulation};
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    let mut s = Simulation::new();
+    let r = s.create_rendezvous();
+    let met: Rc<RefCell<Vec<(f64, ProcessId)>>> = Rc::new(RefCell::new(Vec::new()));
+    for (i, &(time, effect)) in [(0.0, Effect::Provide(r)), (1.0, Effect::Provide(r))]
+        .iter()
+        .chain([(2.0, Effect::Request(r)), (5.0, Effect::Request(r))].iter())
+        .enumerate()
+    {
+        let met = met.clone();
+        let p = s.create_async_process(move |handle| async move {
+            let ctx: SimContext<Effect> = handle.yield_state(effect).await;
+            met.borrow_mut().push((ctx.time(), i));
+        });
+        s.schedule_event(time, p, Effect::TimeOut(0.));
+    }
+    s.run(NoEvents).assert_no_pending();
+    // the waiting provider is resumed before the arriving requester
+    assert_eq!(*met.borrow(), vec![(2.0, 0), (2.0, 2), (5.0, 1), (5.0, 3)]);
+}
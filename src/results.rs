@@ -0,0 +1,69 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! The results of finished runs, and ensembles of runs with many seeds.
+
+#[cfg(feature = "rayon")]
+use crate::EndCondition;
+use crate::{Event, SimState, Simulation, StaticProcess};
+use alloc::vec::Vec;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// What remains of a simulation after a run, returned by
+/// `Simulation::into_results`. Unlike the simulation, it holds no
+/// processes, so it can be sent to other threads.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimResults<T> {
+    /// Simulation time at the end of the run
+    pub time: f64,
+    /// Number of steps performed
+    pub steps: usize,
+    /// The log of processed events
+    pub events: Vec<(Event<T>, T)>,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Consume the simulation, keeping only its results.
+    pub fn into_results(self) -> SimResults<T> {
+        SimResults {
+            time: self.time,
+            steps: self.steps,
+            events: self.processed_events,
+        }
+    }
+}
+
+/// Run a simulation for each seed in parallel, until `until` is met,
+/// returning their results in the order of the seeds.
+///
+/// `build` creates the simulation for a seed, and should pass it to
+/// `Simulation::set_seed`. Each simulation is built and run on a single
+/// thread, so that the results of a seed are the same as in a serial run.
+#[cfg(feature = "rayon")]
+pub fn run_ensemble<T, F>(build: F, seeds: &[u64], until: EndCondition<T>) -> Vec<SimResults<T>>
+where
+    T: SimState + Clone + Send,
+    F: Fn(u64) -> Simulation<T> + Sync,
+{
+    seeds
+        .par_iter()
+        .map(|&seed| build(seed).run(until.clone()).into_results())
+        .collect()
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests;
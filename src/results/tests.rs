@@ -0,0 +1,59 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{run_ensemble, Effect, EndCondition, JitterSpec, Simulation};
+
+fn build(seed: u64) -> Simulation<Effect> {
+    let mut s = Simulation::new();
+    s.set_seed(seed);
+    let r = s.create_resource(1);
+    for _ in 0..3 {
+        let p = s.create_async_process(move |handle| async move {
+            for _ in 0..5 {
+                handle.yield_state(Effect::Request(r)).await;
+                handle
+                    .yield_state(Effect::TimeOutJitter {
+                        base: 1.0,
+                        dist: JitterSpec::Exponential(1.0),
+                    })
+                    .await;
+                handle.yield_state(Effect::Release(r)).await;
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    s
+}
+
+#[test]
+fn ensemble_matches_serial_runs() {
+    let seeds: Vec<u64> = (0..100).collect();
+    let ensemble = run_ensemble(build, &seeds, EndCondition::NoEvents);
+    assert_eq!(ensemble.len(), seeds.len());
+    for (&seed, results) in seeds.iter().zip(ensemble.iter()) {
+        let serial = build(seed).run(EndCondition::NoEvents).into_results();
+        assert_eq!(results.time, serial.time);
+        assert_eq!(results.steps, serial.steps);
+        let times = |r: &crate::SimResults<Effect>| -> Vec<(f64, usize)> {
+            r.events
+                .iter()
+                .map(|(e, _)| (e.time(), e.process()))
+                .collect()
+        };
+        assert_eq!(times(results), times(&serial));
+    }
+    // the seeds lead to different runs
+    assert!(ensemble.iter().any(|r| r.time != ensemble[0].time));
+}
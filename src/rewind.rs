@@ -0,0 +1,158 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Rewinding a simulation to a previous step.
+//!
+//! Generators cannot be copied or rewound, so a simulation is rewound by
+//! building it again with the function set with `Simulation::set_rebuild`
+//! and replaying its steps from the start. The snapshots taken every few
+//! steps only record the scheduled events, not the state of the processes,
+//! so the replay cannot resume from them: they are compared with the ones
+//! of the replay to detect that it diverged.
+
+use crate::{NoStaticProcess, ProcessId, SimState, Simulation, StaticProcess};
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+/// The type of the function set with `Simulation::set_rebuild`
+pub type Rebuild<T, G = NoStaticProcess> = dyn Fn() -> Simulation<T, G>;
+
+/// The scheduled events after a step, taken every
+/// `set_replay_check_interval` steps.
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    step: usize,
+    time: f64,
+    /// Time and process of the scheduled events, in the order they run
+    pending: Vec<(f64, ProcessId)>,
+}
+
+pub(crate) struct Rewind<T: SimState + Clone, G> {
+    interval: Option<usize>,
+    snapshots: Vec<Snapshot>,
+    rebuild: Option<Rc<Rebuild<T, G>>>,
+}
+
+impl<T: SimState + Clone, G> Default for Rewind<T, G> {
+    fn default() -> Self {
+        Rewind {
+            interval: None,
+            snapshots: Vec::new(),
+            rebuild: None,
+        }
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Take a snapshot of the scheduled events every `n` steps, from the
+    /// next one on, to check the replay of `rewind_to_step`. With `None`,
+    /// the default, no snapshot is taken.
+    ///
+    /// The snapshots are only a divergence check: `rewind_to_step` always
+    /// replays from the start, whatever the interval.
+    pub fn set_replay_check_interval(&mut self, n: Option<usize>) {
+        assert!(n != Some(0), "The replay check interval must be positive");
+        self.rewind.interval = n;
+    }
+
+    /// Set the function building the simulation again from the start,
+    /// used by `rewind_to_step` and `reset`.
+    ///
+    /// The rebuilt simulation must be the same as this one was when it
+    /// was created: the same processes, created in the same order, with
+    /// the same events scheduled and the same seed, so that replaying
+    /// its steps reconstructs the state of its processes.
+    pub fn set_rebuild(&mut self, rebuild: Box<Rebuild<T, G>>) {
+        self.rewind.rebuild = Some(Rc::from(rebuild));
+    }
+
+    /// Bring the simulation back to the state it had after `step` steps,
+    /// e.g. to undo the last steps.
+    ///
+    /// The simulation is built again with the function set with
+    /// `set_rebuild`, keeping it and the replay check interval, and its
+    /// first `step` steps are replayed, so rewinding costs as much as
+    /// running up to `step` again. The snapshots up to `step` are compared
+    /// with the ones of the replay, panicking if they differ, since then
+    /// the processes were not reconstructed.
+    pub fn rewind_to_step(&mut self, step: usize) {
+        assert!(
+            step <= self.steps,
+            "Cannot rewind to step {}, after the current step {}",
+            step,
+            self.steps
+        );
+        let rebuild = self
+            .rewind
+            .rebuild
+            .clone()
+            .expect("Rewinding needs the function set with Simulation::set_rebuild");
+        let mut sim = rebuild();
+        sim.rewind = Rewind {
+            interval: self.rewind.interval,
+            snapshots: Vec::new(),
+            rebuild: Some(rebuild),
+        };
+        while sim.steps < step {
+            sim.step();
+        }
+        let kept = self.rewind.snapshots.iter().take_while(|s| s.step <= step);
+        for (replayed, old) in sim.rewind.snapshots.iter().zip(kept) {
+            assert!(
+                replayed == old,
+                "The replay diverged from the snapshot of step {}: the processes were not reconstructed",
+                old.step
+            );
+        }
+        *self = sim;
+    }
+
+    /// Bring the simulation back to its start to run it again, e.g. for
+    /// another replication, adding the time simulated so far to
+    /// `total_simulated_time`.
+    ///
+    /// The simulation is built again with the function set with
+    /// `set_rebuild`, like `rewind_to_step(0)`, so its time goes back to
+    /// the start time.
+    pub fn reset(&mut self) {
+        let total = self.total_simulated_time();
+        self.rewind_to_step(0);
+        self.simulated_before_reset = total;
+    }
+
+    /// Returns the time simulated since the start, summed over the runs
+    /// before each `reset`. Unlike `time`, it is not brought back by
+    /// `reset`.
+    pub fn total_simulated_time(&self) -> f64 {
+        self.simulated_before_reset + self.time - self.start_time
+    }
+
+    /// Take a snapshot after the current step, if it is due.
+    pub(crate) fn take_snapshot(&mut self) {
+        match self.rewind.interval {
+            Some(n) if self.steps % n == 0 => {}
+            _ => return,
+        }
+        let mut pending: Vec<_> = self.pending_events().collect();
+        pending.sort_by(|a, b| a.queue_order(b));
+        let snapshot = Snapshot {
+            step: self.steps,
+            time: self.time,
+            pending: pending.iter().map(|e| (e.time, e.process)).collect(),
+        };
+        self.rewind.snapshots.push(snapshot);
+    }
+}
@@ -0,0 +1,163 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Reader-writer locks: shared by many readers or held by one writer.
+
+use crate::{Event, ProcessId, RwLockId, SimState, Simulation, StaticProcess};
+use alloc::collections::VecDeque;
+
+/// The kind of access a queued process is waiting for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+pub(crate) struct RwLock<T> {
+    /// Number of processes holding the lock for reading
+    readers: usize,
+    /// Whether a process holds the lock for writing
+    writer: bool,
+    /// Processes waiting to acquire the lock, in FIFO order
+    queue: VecDeque<(Event<T>, Access)>,
+    /// A reader waiting for the other readers to release the lock, to
+    /// upgrade its access to writing
+    upgrade: Option<Event<T>>,
+}
+
+impl<T> RwLock<T> {
+    fn new() -> RwLock<T> {
+        RwLock {
+            readers: 0,
+            writer: false,
+            queue: VecDeque::new(),
+            upgrade: None,
+        }
+    }
+
+    /// Drop the requests of `process` waiting for the lock.
+    pub(crate) fn remove_waiter(&mut self, process: ProcessId) {
+        self.queue.retain(|(e, _)| e.process != process);
+        if matches!(&self.upgrade, Some(e) if e.process == process) {
+            self.upgrade = None;
+        }
+    }
+
+    /// Whether a new request for `access` can be granted right away:
+    /// requests are granted in FIFO order, and new readers do not overtake
+    /// a pending upgrade.
+    fn can_grant(&self, access: Access) -> bool {
+        if !self.queue.is_empty() || self.writer {
+            return false;
+        }
+        match access {
+            Access::Read => self.upgrade.is_none(),
+            Access::Write => self.readers == 0,
+        }
+    }
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a reader-writer lock, that can be held by many processes
+    /// yielding `Effect::AcquireRead` or by one yielding
+    /// `Effect::AcquireWrite`.
+    ///
+    /// Returns the identifier of the lock.
+    pub fn create_rwlock(&mut self) -> RwLockId {
+        let id = self.rwlocks.len();
+        self.rwlocks.push(RwLock::new());
+        id
+    }
+
+    pub(crate) fn acquire_read(&mut self, id: RwLockId, event: Event<T>) {
+        let lock = &mut self.rwlocks[id];
+        if lock.can_grant(Access::Read) {
+            lock.readers += 1;
+            self.schedule_at(self.time, event.process, event.state);
+        } else {
+            lock.queue.push_back((event, Access::Read));
+        }
+    }
+
+    pub(crate) fn acquire_write(&mut self, id: RwLockId, event: Event<T>) {
+        let lock = &mut self.rwlocks[id];
+        if lock.can_grant(Access::Write) {
+            lock.writer = true;
+            self.schedule_at(self.time, event.process, event.state);
+        } else {
+            lock.queue.push_back((event, Access::Write));
+        }
+    }
+
+    pub(crate) fn upgrade_to_write(&mut self, id: RwLockId, event: Event<T>) {
+        let lock = &mut self.rwlocks[id];
+        assert!(
+            lock.readers > 0 && !lock.writer,
+            "Process {} upgraded rwlock {} without holding it for reading",
+            event.process,
+            id
+        );
+        assert!(
+            lock.upgrade.is_none(),
+            "Two processes are upgrading rwlock {} at once, which would never complete",
+            id
+        );
+        lock.upgrade = Some(event);
+        self.grant_rwlock(id);
+    }
+
+    pub(crate) fn release_lock(&mut self, id: RwLockId) {
+        let lock = &mut self.rwlocks[id];
+        if lock.writer {
+            lock.writer = false;
+        } else {
+            assert!(
+                lock.readers > 0,
+                "RwLock {} was released more times than it was acquired",
+                id
+            );
+            lock.readers -= 1;
+        }
+        self.grant_rwlock(id);
+    }
+
+    /// Grant the lock to the pending upgrade, if the upgrading process is
+    /// the last reader, or else to the queued processes that can take it.
+    fn grant_rwlock(&mut self, id: RwLockId) {
+        let lock = &mut self.rwlocks[id];
+        if lock.upgrade.is_some() {
+            if lock.readers == 1 {
+                let event = lock.upgrade.take().unwrap();
+                lock.readers = 0;
+                lock.writer = true;
+                self.schedule_at(self.time, event.process, event.state);
+            }
+            return;
+        }
+        while let Some(access) = self.rwlocks[id].queue.front().map(|(_, a)| *a) {
+            let lock = &mut self.rwlocks[id];
+            match access {
+                Access::Read if !lock.writer => lock.readers += 1,
+                Access::Write if !lock.writer && lock.readers == 0 => lock.writer = true,
+                _ => return,
+            }
+            let (event, _) = lock.queue.pop_front().unwrap();
+            self.schedule_at(self.time, event.process, event.state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,90 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NoEvents, ProcessId, Simulation};
+
+/// Returns the time and the duration of the timeouts yielded by `process`.
+fn timeouts(s: &Simulation<Effect>, process: ProcessId) -> Vec<(f64, f64)> {
+    s.processed_events()
+        .iter()
+        .filter(|(e, _)| e.process == process)
+        .filter_map(|(e, y)| match y {
+            Effect::TimeOut(d) => Some((e.time, *d)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn writer_waits_for_readers() {
+    let mut s = Simulation::new();
+    let l = s.create_rwlock();
+    let mut readers = Vec::new();
+    for &hold in [5.0, 7.0].iter() {
+        readers.push(s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::AcquireRead(l)).await;
+            handle.yield_state(Effect::TimeOut(hold)).await;
+            handle.yield_state(Effect::ReleaseLock(l)).await;
+        }));
+    }
+    let writer = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::AcquireWrite(l)).await;
+        handle.yield_state(Effect::TimeOut(3.0)).await;
+        handle.yield_state(Effect::ReleaseLock(l)).await;
+    });
+    for &reader in readers.iter() {
+        s.schedule_event(0.0, reader, Effect::TimeOut(0.));
+    }
+    s.schedule_event(1.0, writer, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    // both readers hold the lock at once
+    assert_eq!(timeouts(&s, readers[0]), vec![(0.0, 5.0)]);
+    assert_eq!(timeouts(&s, readers[1]), vec![(0.0, 7.0)]);
+    // the writer acquires it after both readers released it
+    assert_eq!(timeouts(&s, writer), vec![(7.0, 3.0)]);
+}
+
+#[test]
+fn upgrade_to_write() {
+    let mut s = Simulation::new();
+    let l = s.create_rwlock();
+    let upgrader = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::AcquireRead(l)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::UpgradeToWrite(l)).await;
+        handle.yield_state(Effect::TimeOut(2.0)).await;
+        handle.yield_state(Effect::ReleaseLock(l)).await;
+    });
+    let reader = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::AcquireRead(l)).await;
+        handle.yield_state(Effect::TimeOut(4.0)).await;
+        handle.yield_state(Effect::ReleaseLock(l)).await;
+    });
+    let late_reader = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::AcquireRead(l)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::ReleaseLock(l)).await;
+    });
+    s.schedule_event(0.0, upgrader, Effect::TimeOut(0.));
+    s.schedule_event(0.0, reader, Effect::TimeOut(0.));
+    s.schedule_event(2.0, late_reader, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    // the upgrade waits for the other reader, and new readers wait for
+    // the upgraded writer
+    assert_eq!(timeouts(&s, upgrader), vec![(0.0, 1.0), (4.0, 2.0)]);
+    assert_eq!(timeouts(&s, late_reader), vec![(6.0, 1.0)]);
+}
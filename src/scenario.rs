@@ -0,0 +1,249 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Loading the initial schedule of a simulation from a trace of events.
+//!
+//! This module is only available with the `serde` and `std` features.
+
+use crate::{ProcessId, SimState, Simulation, StaticProcess};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::io::{self, Read};
+
+/// The format of a trace read by `Simulation::load_schedule`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScheduleFormat {
+    /// A record on each line, with the time, the process and the state
+    /// separated by commas, e.g. `1.5,customer,{"time_out":2.0}`. The state
+    /// is in JSON and takes the rest of the line, so it may contain commas.
+    /// A first line `time,process,state` is a header and is skipped.
+    Csv,
+    /// A JSON object on each line, with the fields `time`, `process` and
+    /// `state`, e.g. `{"time":1.5,"process":"customer","state":"wait"}`.
+    JsonLines,
+}
+
+/// A record of a trace that could not be scheduled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordError {
+    /// The line of the record, starting from 1
+    pub line: usize,
+    /// Why the record could not be scheduled
+    pub message: String,
+}
+
+/// The error returned by `Simulation::load_schedule`.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The trace could not be read
+    Io(io::Error),
+    /// Some records are not valid, and nothing was scheduled
+    Records(Vec<RecordError>),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "Cannot read the trace: {}", e),
+            LoadError::Records(errors) => {
+                write!(f, "{} invalid records", errors.len())?;
+                for e in errors.iter() {
+                    write!(f, "\nline {}: {}", e.line, e.message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+impl<T: SimState + Clone + DeserializeOwned, G: StaticProcess<T>> Simulation<T, G> {
+    /// Schedule the events of a trace read from `reader`, returning how
+    /// many were scheduled.
+    ///
+    /// Each record gives the time of an event, the name of the process it
+    /// resumes and the state it resumes it with, in JSON. `resolver`
+    /// returns the process with a given name, e.g. `ComponentHandles::get`
+    /// of a component, or `|p| p.parse().ok()` if the trace has the ids of
+    /// the processes. Times are absolute, and must not be before the
+    /// current time.
+    ///
+    /// The records are either all scheduled or none is: the invalid ones
+    /// are collected, with their line, in `LoadError::Records`.
+    pub fn load_schedule<R, F>(
+        &mut self,
+        mut reader: R,
+        format: ScheduleFormat,
+        mut resolver: F,
+    ) -> Result<usize, LoadError>
+    where
+        R: Read,
+        F: FnMut(&str) -> Option<ProcessId>,
+    {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut events = Vec::new();
+        let mut errors = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let header = format == ScheduleFormat::Csv && i == 0 && line == "time,process,state";
+            if line.is_empty() || header {
+                continue;
+            }
+            let record = match format {
+                ScheduleFormat::Csv => parse_csv(line),
+                ScheduleFormat::JsonLines => parse_json_line(line),
+            };
+            match record.and_then(|r| self.check_record(r, &mut resolver)) {
+                Ok(event) => events.push(event),
+                Err(message) => errors.push(RecordError {
+                    line: i + 1,
+                    message,
+                }),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(LoadError::Records(errors));
+        }
+        let scheduled = events.len();
+        for (time, process, state) in events {
+            self.schedule_event_at(time, process, state);
+        }
+        Ok(scheduled)
+    }
+
+    fn check_record<F>(
+        &self,
+        (time, name, state): (f64, String, T),
+        resolver: &mut F,
+    ) -> Result<(f64, ProcessId, T), String>
+    where
+        F: FnMut(&str) -> Option<ProcessId>,
+    {
+        if !time.is_finite() || time < self.time {
+            return Err(format!(
+                "Time {} is before the current time {}",
+                time, self.time
+            ));
+        }
+        let process = resolver(&name).ok_or_else(|| format!("Unknown process {}", name))?;
+        if process >= self.processes.len() {
+            return Err(format!("Process {} does not exist", process));
+        }
+        Ok((time, process, state))
+    }
+}
+
+fn parse_csv<T: DeserializeOwned>(line: &str) -> Result<(f64, String, T), String> {
+    let mut fields = line.splitn(3, ',');
+    let (time, process, state) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(time), Some(process), Some(state)) => (time, process, state),
+        _ => return Err("Expected time, process and state".to_string()),
+    };
+    let time = time
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid time {}", time.trim()))?;
+    let state = serde_json::from_str(state).map_err(|e| format!("Invalid state: {}", e))?;
+    Ok((time, process.trim().to_string(), state))
+}
+
+fn parse_json_line<T: DeserializeOwned>(line: &str) -> Result<(f64, String, T), String> {
+    let mut record: Value =
+        serde_json::from_str(line).map_err(|e| format!("Invalid record: {}", e))?;
+    let time = record
+        .get("time")
+        .and_then(Value::as_f64)
+        .ok_or("Expected a number for the time")?;
+    let process = match record.get("process") {
+        Some(Value::String(name)) => name.clone(),
+        Some(Value::Number(id)) => id.to_string(),
+        _ => return Err("Expected a name or an id for the process".to_string()),
+    };
+    let state = record
+        .get_mut("state")
+        .map(Value::take)
+        .ok_or("Expected a state")?;
+    let state = serde_json::from_value(state).map_err(|e| format!("Invalid state: {}", e))?;
+    Ok((time, process, state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LoadError, RecordError, ScheduleFormat};
+    use crate::{Effect, EndCondition, SimContext, Simulation, Step};
+
+    fn counter(s: &mut Simulation<Effect>) -> usize {
+        s.create_sm_process(Box::new(|_: SimContext<Effect>| Step::Yield(Effect::Wait)))
+    }
+
+    #[test]
+    fn load_schedule() {
+        let mut s = Simulation::new();
+        let customer = counter(&mut s);
+        let clerk = counter(&mut s);
+        let resolver = |name: &str| match name {
+            "customer" => Some(customer),
+            "clerk" => Some(clerk),
+            _ => None,
+        };
+        let csv = "time,process,state\n\
+                   1.5,customer,\"wait\"\n\
+                   soon,customer,\"wait\"\n\
+                   2,clerk,{\"time_out\":1.0}\n\
+                   3,manager,\"wait\"\n";
+        match s.load_schedule(csv.as_bytes(), ScheduleFormat::Csv, resolver) {
+            Err(LoadError::Records(errors)) => assert_eq!(
+                errors,
+                vec![
+                    RecordError {
+                        line: 3,
+                        message: "Invalid time soon".to_string()
+                    },
+                    RecordError {
+                        line: 5,
+                        message: "Unknown process manager".to_string()
+                    },
+                ]
+            ),
+            r => panic!("{:?}", r),
+        }
+        // nothing was scheduled
+        assert!(s.peek_events(1).is_empty());
+
+        let json = "{\"time\":1.5,\"process\":\"customer\",\"state\":\"wait\"}\n\
+                    \n\
+                    {\"time\":2.0,\"process\":1,\"state\":{\"time_out\":1.0}}\n";
+        let loaded = s.load_schedule(json.as_bytes(), ScheduleFormat::JsonLines, |p| {
+            resolver(p).or_else(|| p.parse().ok())
+        });
+        assert_eq!(loaded.unwrap(), 2);
+        let s = s.run(EndCondition::NoEvents);
+        let times: Vec<_> = s
+            .processed_events()
+            .iter()
+            .map(|(e, _)| (e.time(), e.process()))
+            .collect();
+        assert_eq!(times, vec![(1.5, customer), (2.0, clerk)]);
+    }
+}
@@ -0,0 +1,206 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Capacities of the resources changing on a schedule, like shifts.
+
+use crate::{Internal, ResourceId, SimState, Simulation, StaticProcess};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// The capacity schedule of a resource, set with
+/// `Simulation::set_resource_schedule`.
+#[derive(Debug, Clone)]
+pub(crate) struct Schedule {
+    changes: Vec<(f64, usize)>,
+    period: Option<f64>,
+    /// Incremented when the schedule is replaced, so that the changes
+    /// of the previous one are ignored
+    generation: u32,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Change the capacity of resource `r` on a schedule: at each time of
+    /// `changes`, sorted by time, its number of units becomes the given one.
+    ///
+    /// With a `period`, the schedule repeats: the times of `changes` must
+    /// be in `[0, period)`, and the `k`-th repetition happens `k * period`
+    /// time units later. The change points before the current time are
+    /// skipped. Setting a schedule replaces the previous one.
+    ///
+    /// When the capacity grows, the new units are granted to the waiting
+    /// processes. When it shrinks, the units in use are not taken away:
+    /// they are removed when they are released, and no unit is granted until
+    /// the number of units in use is below the new capacity.
+    ///
+    /// The capacity changes of a resource that processes are waiting for
+    /// are pending events, so the simulation does not run out of events
+    /// before them. A process waiting for a resource whose repeating
+    /// schedule never gives it a unit keeps the simulation running.
+    pub fn set_resource_schedule(
+        &mut self,
+        r: ResourceId,
+        changes: Vec<(f64, usize)>,
+        period: Option<f64>,
+    ) {
+        assert!(
+            changes.windows(2).all(|w| w[0].0 <= w[1].0),
+            "The capacity changes of resource {} are not sorted by time",
+            r
+        );
+        if let Some(period) = period {
+            assert!(period > 0.0, "The period of a schedule must be positive");
+            assert!(
+                changes.iter().all(|&(t, _)| 0.0 <= t && t < period),
+                "The capacity changes of resource {} are not within the period {}",
+                r,
+                period
+            );
+        }
+        let generation = self.resources[r]
+            .schedule
+            .as_ref()
+            .map_or(0, |s| s.generation + 1);
+        self.resources[r].schedule = Some(Schedule {
+            changes,
+            period,
+            generation,
+        });
+
+        // the first change point from now on
+        let start = match period {
+            Some(period) => (self.time / period) as u64,
+            None => 0,
+        };
+        let mut next = (0, start);
+        while let Some(time) = self.shift_time(r, next) {
+            if time >= self.time {
+                self.schedule_internal(
+                    time,
+                    Internal::Shift {
+                        resource: r,
+                        index: next.0,
+                        cycle: next.1,
+                        generation,
+                    },
+                );
+                break;
+            }
+            next = self.next_shift(r, next);
+        }
+    }
+
+    /// Apply the change `index` of the `cycle`-th repetition of the
+    /// schedule of resource `r`, and schedule the next one.
+    pub(crate) fn apply_shift(&mut self, r: ResourceId, index: usize, cycle: u64, generation: u32) {
+        let schedule = match &self.resources[r].schedule {
+            Some(schedule) if schedule.generation == generation => schedule,
+            _ => return,
+        };
+        let units = schedule.changes[index].1;
+        self.set_resource_capacity(r, units);
+        let next = self.next_shift(r, (index, cycle));
+        if let Some(time) = self.shift_time(r, next) {
+            self.schedule_internal(
+                time,
+                Internal::Shift {
+                    resource: r,
+                    index: next.0,
+                    cycle: next.1,
+                    generation,
+                },
+            );
+        }
+    }
+
+    /// Returns the time of the first scheduled capacity change of a
+    /// resource that processes are waiting for, if any.
+    pub(crate) fn next_awaited_shift(&self) -> Option<f64> {
+        self.internal_events
+            .iter()
+            .filter_map(|Reverse(internal)| match internal.action {
+                Internal::Shift {
+                    resource,
+                    generation,
+                    ..
+                } => {
+                    let res = &self.resources[resource];
+                    let current = res.schedule.as_ref().map(|s| s.generation) == Some(generation);
+                    let awaited = !res.queue.is_empty() || !res.lease_queue.is_empty();
+                    (current && awaited).then_some(internal.time)
+                }
+                _ => None,
+            })
+            .reduce(f64::min)
+    }
+
+    /// Returns the time of the change `index` of the `cycle`-th repetition
+    /// of the schedule of `r`, if it exists.
+    fn shift_time(&self, r: ResourceId, (index, cycle): (usize, u64)) -> Option<f64> {
+        let schedule = self.resources[r].schedule.as_ref()?;
+        let &(time, _) = schedule.changes.get(index)?;
+        match schedule.period {
+            Some(period) => Some(time + cycle as f64 * period),
+            None if cycle == 0 => Some(time),
+            None => None,
+        }
+    }
+
+    /// Returns the change following `index` in the `cycle`-th repetition
+    /// of the schedule of `r`.
+    fn next_shift(&self, r: ResourceId, (index, cycle): (usize, u64)) -> (usize, u64) {
+        let changes = self.resources[r].schedule.as_ref().unwrap().changes.len();
+        if index + 1 < changes {
+            (index + 1, cycle)
+        } else {
+            (0, cycle + 1)
+        }
+    }
+
+    /// Set the number of units of resource `r` to `units`.
+    fn set_resource_capacity(&mut self, r: ResourceId, units: usize) {
+        let (start, now) = (self.start_time, self.time);
+        let res = &mut self.resources[r];
+        if res.capacity_log.is_empty() {
+            res.capacity_log.push((start, res.allocated));
+        }
+        res.capacity_log.push((now, units));
+        if units >= res.allocated {
+            let added = units - res.allocated;
+            // the units still to remove are kept instead
+            let kept = added.min(res.retiring);
+            res.retiring -= kept;
+            res.available += added - kept;
+            res.allocated = units;
+            let free = res.units.iter().filter(|u| u.is_none()).count();
+            if free < res.available {
+                let len = res.units.len() + res.available - free;
+                res.units.resize(len, None);
+            }
+            if !res.failed {
+                self.grant_available(r);
+            }
+        } else {
+            let removed = res.allocated - units;
+            let idle = removed.min(res.available);
+            res.available -= idle;
+            res.retiring += removed - idle;
+            res.allocated = units;
+            res.trim_units();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,104 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, ProcessId, ResourceId, Simulation};
+
+/// Create processes arriving at `arrivals` that use `r` for `service`.
+fn customers(
+    s: &mut Simulation<Effect>,
+    r: ResourceId,
+    service: f64,
+    arrivals: &[f64],
+) -> Vec<ProcessId> {
+    let mut ids = Vec::new();
+    for &arrival in arrivals {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(service)).await;
+            handle.yield_state(Effect::Release(r)).await;
+        });
+        s.schedule_event(arrival, p, Effect::TimeOut(0.));
+        ids.push(p);
+    }
+    ids
+}
+
+/// Two shifts of 8 time units a day: 3 servers, then 1.
+#[test]
+fn two_shifts() {
+    use crate::EndCondition::NoEvents;
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(3);
+    s.set_resource_schedule(r, vec![(0.0, 3), (8.0, 1)], Some(16.0));
+    // a customer every time unit for two days, each served in 2
+    let arrivals: Vec<f64> = (0..32).map(f64::from).collect();
+    customers(&mut s, r, 2.0, &arrivals);
+    let s = s.run(NoEvents);
+
+    let grants_in = |start: f64, end: f64| {
+        s.resource_hold_log(r)
+            .iter()
+            .filter(|&&(t, _, _)| start <= t && t < end)
+            .count()
+    };
+    // by day every customer is served right away, by night one every 2
+    // time units, and the queue left by the night is served in the morning
+    assert_eq!(grants_in(0.0, 8.0), 8);
+    assert_eq!(grants_in(8.0, 16.0), 4);
+    assert_eq!(grants_in(16.0, 24.0), 12);
+    assert_eq!(grants_in(24.0, 32.0), 4);
+    assert_eq!(s.resource_hold_log(r).len(), 32);
+    // the queue left by the second night is served from 32, by 3 servers
+    assert!(32.0 < s.time() && s.time() < 40.0);
+    let busy: f64 = s.resource_hold_log(r).iter().map(|&(a, b, _)| b - a).sum();
+    let expected = busy / (2.0 * (3.0 * 8.0 + 1.0 * 8.0) + 3.0 * (s.time() - 32.0));
+    assert!((s.resource_utilization(r) - expected).abs() < 1e-12);
+}
+
+#[test]
+fn busy_units_finish() {
+    use crate::EndCondition::NoEvents;
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(2);
+    s.set_resource_schedule(r, vec![(1.0, 0), (6.0, 1)], None);
+    let p = customers(&mut s, r, 3.0, &[0.0, 0.0, 2.0]);
+    let s = s.run(NoEvents);
+    // the two customers being served at 1 finish, the third one waits
+    // for the capacity to come back
+    assert_eq!(
+        s.resource_hold_log(r),
+        &[(0.0, 3.0, p[0]), (0.0, 3.0, p[1]), (6.0, 9.0, p[2])]
+    );
+}
+
+#[test]
+fn replaced_schedule() {
+    use crate::EndCondition::NoEvents;
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    s.set_resource_schedule(r, vec![(2.0, 0)], None);
+    s.set_resource_schedule(r, vec![(4.0, 0)], None);
+    let p = customers(&mut s, r, 1.0, &[0.0, 3.0, 5.0]);
+    let s = s.run(NoEvents);
+    // only the second schedule applies; the last one never gets a unit
+    assert_eq!(
+        s.resource_hold_log(r),
+        &[(0.0, 1.0, p[0]), (3.0, 4.0, p[1])]
+    );
+    assert_eq!(s.processes_waiting_on(r), vec![p[2]]);
+}
@@ -0,0 +1,83 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Clocks of the processes that drift from the simulation time.
+
+use crate::{ProcessId, SimState, Simulation, StaticProcess};
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Skew the clock of process `id`, so that the time it observes with
+    /// `SimContext::time` is `rate * time + offset`, where `time` is the
+    /// simulation time.
+    ///
+    /// The events are still ordered and scheduled by the simulation time:
+    /// the delays of the process, e.g. of `Effect::TimeOut`, and the other
+    /// times of its context, like the upcoming events and `now_plus`, are
+    /// not skewed. An offset of 0 and a rate of 1 remove the skew.
+    pub fn set_clock_skew(&mut self, id: ProcessId, offset: f64, rate: f64) {
+        assert!(
+            offset.is_finite() && rate.is_finite() && rate > 0.0,
+            "The clock skew of process {} needs a finite offset and a positive rate, got {} and {}",
+            id,
+            offset,
+            rate
+        );
+        if offset == 0.0 && rate == 1.0 {
+            self.clock_skews.remove(&id);
+        } else {
+            self.clock_skews.insert(id, (offset, rate));
+        }
+    }
+
+    /// Returns the time observed by process `id` at the simulation time
+    /// `time`.
+    pub fn local_time(&self, id: ProcessId, time: f64) -> f64 {
+        match self.clock_skews.get(&id) {
+            Some(&(offset, rate)) => rate * time + offset,
+            None => time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Effect, EndCondition, SimContext, Simulation, Step};
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    #[test]
+    fn clock_skew() {
+        let mut s = Simulation::new();
+        let observed = Rc::new(RefCell::new(Vec::new()));
+        let mut nodes = Vec::new();
+        for _ in 0..3 {
+            let observed = observed.clone();
+            let p = s.create_sm_process(Box::new(move |ctx: SimContext<Effect>| {
+                observed.borrow_mut().push((ctx.time(), ctx.now_plus(1.0)));
+                Step::Done
+            }));
+            s.schedule_event(4.0, p, Effect::TimeOut(0.0));
+            nodes.push(p);
+        }
+        s.set_clock_skew(nodes[0], 1.0, 1.5);
+        s.set_clock_skew(nodes[1], -0.5, 0.5);
+        let s = s.run(EndCondition::NoEvents);
+        // all resumed at the same global time, observed differently
+        assert_eq!(s.time(), 4.0);
+        assert_eq!(*observed.borrow(), vec![(7.0, 5.0), (1.5, 5.0), (4.0, 5.0)]);
+        assert_eq!(s.local_time(nodes[0], 2.0), 4.0);
+        assert_eq!(s.local_time(nodes[2], 2.0), 2.0);
+    }
+}
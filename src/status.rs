@@ -0,0 +1,147 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Time spent by the processes in each status, and the effects they yielded.
+
+use crate::{Effect, ProcessId, SimState, Simulation, StaticProcess};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+/// What a process is doing, according to the last effect it yielded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ProcessStatus {
+    /// Waiting for an event of its own, e.g. during an `Effect::TimeOut`.
+    Running,
+    /// Waiting to acquire a resource, a lease, a lock, or to send to or
+    /// receive from a channel or a container.
+    WaitingResource,
+    /// Waiting to be resumed by another process, after `Effect::Wait` or
+    /// after scheduling an `Effect::Event` for another process.
+    Waiting,
+    /// The process completed.
+    Completed,
+}
+
+impl ProcessStatus {
+    fn of(process: ProcessId, effect: &Effect) -> ProcessStatus {
+        match *effect {
+            Effect::Wait => ProcessStatus::Waiting,
+            Effect::Event { process: p, .. } | Effect::LabeledEvent { process: p, .. }
+                if p != process =>
+            {
+                ProcessStatus::Waiting
+            }
+            Effect::Request(_)
+            | Effect::RequestWithBackoff { .. }
+            | Effect::RequestAffinity { .. }
+            | Effect::AcquireLease(_)
+            | Effect::AcquireRead(_)
+            | Effect::AcquireWrite(_)
+            | Effect::UpgradeToWrite(_)
+            | Effect::Send(_)
+            | Effect::Recv(_)
+            | Effect::Put { .. }
+            | Effect::Get { .. } => ProcessStatus::WaitingResource,
+            _ => ProcessStatus::Running,
+        }
+    }
+}
+
+/// The status of a process, since when, and the time it spent in the
+/// previous ones.
+#[derive(Debug, Clone)]
+pub(crate) struct StatusTrack {
+    status: ProcessStatus,
+    since: f64,
+    totals: BTreeMap<ProcessStatus, f64>,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Returns the status of process `id`, or `None` if it was never
+    /// resumed.
+    pub fn process_status(&self, id: ProcessId) -> Option<ProcessStatus> {
+        self.statuses.get(id)?.as_ref().map(|track| track.status)
+    }
+
+    /// Returns the time process `id` spent in each status, from when it
+    /// was first resumed until it completed or until the current time.
+    ///
+    /// A request granted at once, or any other zero-time status, is
+    /// reported with a duration of zero.
+    pub fn process_status_breakdown(&self, id: ProcessId) -> BTreeMap<ProcessStatus, f64> {
+        let track = match self.statuses.get(id) {
+            Some(Some(track)) => track,
+            _ => return BTreeMap::new(),
+        };
+        let mut totals = track.totals.clone();
+        if track.status != ProcessStatus::Completed {
+            *totals.entry(track.status).or_insert(0.0) += self.time - track.since;
+        }
+        totals
+    }
+
+    /// Record, for each process, the effects it yields and when, also
+    /// those of states that are not logged, to be returned by
+    /// `effect_history`.
+    ///
+    /// Stopping the recording discards the effects recorded so far.
+    pub fn record_effect_history(&mut self, record: bool) {
+        self.effect_history = if record { Some(Vec::new()) } else { None };
+    }
+
+    /// Returns the effects yielded by process `id`, in order, with the
+    /// time each one was yielded, since they started being recorded.
+    pub fn effect_history(&self, id: ProcessId) -> Vec<(f64, Effect)> {
+        self.effect_history
+            .as_ref()
+            .and_then(|history| history.get(id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Record the status of `process` after it yielded `effect`.
+    pub(crate) fn record_yield_status(&mut self, process: ProcessId, effect: &Effect) {
+        if let Some(history) = &mut self.effect_history {
+            if history.len() <= process {
+                history.resize(process + 1, Vec::new());
+            }
+            history[process].push((self.time, *effect));
+        }
+        self.record_status(process, ProcessStatus::of(process, effect));
+    }
+
+    pub(crate) fn record_status(&mut self, process: ProcessId, status: ProcessStatus) {
+        let now = self.time;
+        if self.statuses.len() <= process {
+            self.statuses.resize(process + 1, None);
+        }
+        match &mut self.statuses[process] {
+            Some(track) => {
+                *track.totals.entry(track.status).or_insert(0.0) += now - track.since;
+                track.status = status;
+                track.since = now;
+            }
+            None => {
+                self.statuses[process] = Some(StatusTrack {
+                    status,
+                    since: now,
+                    totals: BTreeMap::new(),
+                })
+            }
+        }
+    }
+}
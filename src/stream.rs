@@ -0,0 +1,116 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Asynchronous access to the events processed by a simulation.
+//!
+//! This module is only available with the `async` feature.
+
+use crate::{EndCondition, Event, SimState, Simulation};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio::task::{self, JoinHandle};
+
+/// A stream of the events logged by a simulation running on another thread.
+///
+/// It is returned by `run_streaming` and ends when the simulation
+/// meets its ending condition.
+pub struct EventStream<T> {
+    rx: mpsc::Receiver<(Event<T>, T)>,
+}
+
+impl<T> EventStream<T> {
+    /// Receive the next logged event, or `None` once the simulation ended.
+    pub async fn next(&mut self) -> Option<(Event<T>, T)> {
+        self.rx.recv().await
+    }
+}
+
+impl<T> Stream for EventStream<T> {
+    type Item = (Event<T>, T);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().rx.poll_recv(cx)
+    }
+}
+
+/// Run a simulation on a blocking thread, streaming the logged events.
+///
+/// Since processes are not required to be `Send`, the simulation is
+/// built by `build` directly on the blocking thread spawned with
+/// `tokio::task::spawn_blocking`, and then run until the ending
+/// condition is met.
+///
+/// The stream buffers up to `capacity` events. When the buffer is full
+/// the simulation thread blocks until the consumer catches up, so no event
+/// is ever lost. If the stream is dropped, the simulation is stopped after
+/// the current step. Panics if `capacity` is 0.
+///
+/// ```
+/// use desim::{run_streaming, Effect, EndCondition, Simulation};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let (mut events, handle) = run_streaming(
+///         || {
+///             let mut sim = Simulation::<Effect>::new();
+///             let p = sim.create_async_process(|handle| async move {
+///                 for _ in 0..3 {
+///                     handle.timeout(1.0).await;
+///                 }
+///             });
+///             sim.schedule_event(0.0, p, Effect::TimeOut(0.0));
+///             sim
+///         },
+///         EndCondition::NoEvents,
+///         16,
+///     );
+///
+///     let mut times = Vec::new();
+///     while let Some((event, _state)) = events.next().await {
+///         times.push(event.time());
+///     }
+///     handle.await.unwrap();
+///     assert_eq!(times, vec![0.0, 1.0, 2.0]);
+/// }
+/// ```
+pub fn run_streaming<T, F>(
+    build: F,
+    until: EndCondition<T>,
+    capacity: usize,
+) -> (EventStream<T>, JoinHandle<()>)
+where
+    T: SimState + Clone + Send + 'static,
+    F: FnOnce() -> Simulation<T> + Send + 'static,
+{
+    assert!(
+        capacity > 0,
+        "The capacity of the event stream must be positive"
+    );
+    let (tx, rx) = mpsc::channel(capacity);
+    let handle = task::spawn_blocking(move || {
+        let mut sim = build();
+        let events = sim.subscribe();
+        sim.run_steps(&until, |s| {
+            s.step();
+            // stop if the stream was dropped
+            events
+                .try_iter()
+                .all(|event| tx.blocking_send(event).is_ok())
+        });
+    });
+    (EventStream { rx }, handle)
+}
@@ -0,0 +1,322 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Assertions on the sequence of processed events, for tests.
+//!
+//! `assert_event_sequence!` checks the log of a simulation against the
+//! expected time, process and effect of each event, with `_` for the
+//! fields that do not matter:
+//!
+//! ```
+//! use desim::{assert_event_sequence, Effect, EndCondition, SimContext, Simulation, Step};
+//!
+//! let mut sim = Simulation::new();
+//! let r = sim.create_resource(1);
+//! let mut effects = vec![Effect::Release(r), Effect::TimeOut(7.0), Effect::Request(r)];
+//! let p = sim.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+//!     effects.pop().map_or(Step::Done, Step::Yield)
+//! }));
+//! sim.schedule_event(0.0, p, Effect::TimeOut(0.0));
+//! let sim = sim.run(EndCondition::NoEvents);
+//!
+//! assert_event_sequence!(sim.processed_events(), [
+//!     (0.0, p, Effect::Request(r)),
+//!     (0.0, _, TimeOut),
+//!     (7.0, p, _),
+//! ]);
+//! ```
+//!
+//! The effect is either `_`, the bare name of a variant matching any of its
+//! effects, or an effect compared with the logged one through their `Debug`
+//! output. The time and the process are `_`, a single token or an expression
+//! in parentheses. Times are compared with a tolerance of `1e-9`, that can be
+//! changed with `epsilon = ...` before the list. On a mismatch, the panic
+//! message shows the first differing event with the events around it.
+
+use crate::{Effect, Event, ProcessId, SimState};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// How the effect of an expected event is matched, see `EventSequence`.
+#[derive(Debug, Clone)]
+pub enum EffectMatch {
+    /// Any effect
+    Any,
+    /// Any effect of the variant with this name, e.g. `"TimeOut"`
+    Variant(&'static str),
+    /// An effect with the same `Debug` output as this one
+    Exact(Effect),
+}
+
+impl EffectMatch {
+    fn matches(&self, effect: &Effect) -> bool {
+        match self {
+            EffectMatch::Any => true,
+            EffectMatch::Variant(name) => {
+                let debug = format!("{:?}", effect);
+                debug.strip_prefix(name).is_some_and(|rest| {
+                    rest.is_empty() || rest.starts_with('(') || rest.starts_with(' ')
+                })
+            }
+            EffectMatch::Exact(expected) => format!("{:?}", expected) == format!("{:?}", effect),
+        }
+    }
+}
+
+/// An expected event, with `None` for the fields that do not matter.
+#[derive(Debug, Clone)]
+struct Expected {
+    time: Option<f64>,
+    process: Option<ProcessId>,
+    effect: EffectMatch,
+}
+
+/// Checks the log of processed events against a sequence of expected
+/// events, built by `assert_event_sequence!` or by calling `then`.
+///
+/// The effect of an event is the one logged with it, i.e. the one yielded
+/// by the process.
+pub struct EventSequence<'a, T> {
+    events: &'a [(Event<T>, T)],
+    expected: Vec<Expected>,
+    epsilon: f64,
+}
+
+impl<'a, T: SimState> EventSequence<'a, T> {
+    /// Create an empty sequence of expected events, to check against
+    /// `events`.
+    pub fn new(events: &'a [(Event<T>, T)]) -> EventSequence<'a, T> {
+        EventSequence {
+            events,
+            expected: Vec::new(),
+            epsilon: 1e-9,
+        }
+    }
+
+    /// Set the largest difference between an expected and a logged time
+    /// for them to match.
+    pub fn epsilon(mut self, epsilon: f64) -> EventSequence<'a, T> {
+        self.epsilon = epsilon;
+        self
+    }
+
+    /// Add the next expected event.
+    pub fn then(
+        mut self,
+        time: Option<f64>,
+        process: Option<ProcessId>,
+        effect: EffectMatch,
+    ) -> EventSequence<'a, T> {
+        self.expected.push(Expected {
+            time,
+            process,
+            effect,
+        });
+        self
+    }
+
+    /// Returns a description of the first difference between the logged
+    /// and the expected events, or `None` if they match.
+    pub fn mismatch(&self) -> Option<String> {
+        let differs = |(event, state): &(Event<T>, T), e: &Expected| {
+            e.time
+                .is_some_and(|t| (event.time() - t).abs() > self.epsilon)
+                || e.process.is_some_and(|p| event.process() != p)
+                || !e.effect.matches(&state.effect())
+        };
+        let first = self
+            .events
+            .iter()
+            .zip(self.expected.iter())
+            .position(|(logged, e)| differs(logged, e));
+        let index = match first {
+            Some(i) => i,
+            None if self.events.len() == self.expected.len() => return None,
+            None => self.events.len().min(self.expected.len()),
+        };
+        let mut out = String::new();
+        match self.expected.get(index) {
+            Some(e) => {
+                let field = |f: Option<String>| f.unwrap_or_else(|| String::from("_"));
+                let effect = match &e.effect {
+                    EffectMatch::Any => String::from("_"),
+                    EffectMatch::Variant(name) => String::from(*name),
+                    EffectMatch::Exact(effect) => format!("{:?}", effect),
+                };
+                write!(
+                    out,
+                    "Event {} differs: expected ({}, {}, {})",
+                    index,
+                    field(e.time.map(|t| format!("{} ± {}", t, self.epsilon))),
+                    field(e.process.map(|p| format!("{}", p))),
+                    effect
+                )
+                .unwrap();
+            }
+            None => write!(
+                out,
+                "Expected {} events, but {} were logged",
+                self.expected.len(),
+                self.events.len()
+            )
+            .unwrap(),
+        }
+        if index == self.events.len() {
+            write!(out, ", but only {} events were logged", self.events.len()).unwrap();
+        }
+        let start = index.saturating_sub(2);
+        for (i, (event, state)) in self.events.iter().enumerate().skip(start).take(5) {
+            let marker = if i == index { ">" } else { " " };
+            write!(
+                out,
+                "\n{} {:4}: ({}, {}, {:?})",
+                marker,
+                i,
+                event.time(),
+                event.process(),
+                state.effect()
+            )
+            .unwrap();
+        }
+        Some(out)
+    }
+
+    /// Panic with the description of the first difference, if the logged
+    /// and the expected events differ.
+    pub fn check(&self) {
+        if let Some(mismatch) = self.mismatch() {
+            panic!("{}", mismatch);
+        }
+    }
+}
+
+/// Assert that a log of processed events matches a sequence of expected
+/// `(time, process, effect)` events. See the `testing` module.
+#[macro_export]
+macro_rules! assert_event_sequence {
+    ($events:expr, [$($entry:tt),* $(,)?]) => {
+        $crate::assert_event_sequence!($events, epsilon = 1e-9, [$($entry),*])
+    };
+    ($events:expr, epsilon = $eps:expr, [$(($time:tt, $process:tt, $($effect:tt)+)),* $(,)?]) => {
+        $crate::testing::EventSequence::new($events)
+            .epsilon($eps)
+            $(.then(
+                $crate::__expected_field!($time),
+                $crate::__expected_field!($process),
+                $crate::__expected_effect!($($effect)+),
+            ))*
+            .check()
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __expected_field {
+    (_) => {
+        None
+    };
+    ($value:expr) => {
+        Some($value)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __expected_effect {
+    (_) => {
+        $crate::testing::EffectMatch::Any
+    };
+    ($variant:ident) => {
+        $crate::testing::EffectMatch::Variant(stringify!($variant))
+    };
+    ($effect:expr) => {
+        $crate::testing::EffectMatch::Exact($effect)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EffectMatch, EventSequence};
+    use crate::{Effect, EndCondition, SimContext, Simulation, Step};
+
+    fn run() -> Simulation<Effect> {
+        let mut s = Simulation::new();
+        let r = s.create_resource(1);
+        let cycle = [Effect::Request(r), Effect::TimeOut(7.0), Effect::Release(r)];
+        let mut step = 0;
+        let p = s.create_sm_process(Box::new(move |_: SimContext<Effect>| {
+            step += 1;
+            cycle.get(step - 1).map_or(Step::Done, |&e| Step::Yield(e))
+        }));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.0));
+        s.run(EndCondition::NoEvents)
+    }
+
+    #[test]
+    fn matching_sequence() {
+        let s = run();
+        crate::assert_event_sequence!(
+            s.processed_events(),
+            [
+                (0.0, 0, Effect::Request(0)),
+                (_, (1 - 1), TimeOut),
+                (7.0, _, _),
+            ]
+        );
+        crate::assert_event_sequence!(
+            s.processed_events(),
+            epsilon = 0.5,
+            [(0.1, _, _), (0.0, _, _), (7.4, _, Release),]
+        );
+    }
+
+    #[test]
+    fn mismatches() {
+        let s = run();
+        let seq = EventSequence::new(s.processed_events())
+            .then(Some(0.0), Some(0), EffectMatch::Any)
+            .then(None, None, EffectMatch::Variant("Time"))
+            .then(None, None, EffectMatch::Any);
+        assert_eq!(
+            seq.mismatch().unwrap(),
+            "Event 1 differs: expected (_, _, Time)\n\
+             \u{20}    0: (0, 0, Request(0))\n\
+             >    1: (0, 0, TimeOut(7.0))\n\
+             \u{20}    2: (7, 0, Release(0))"
+        );
+        let short = EventSequence::new(s.processed_events()).then(None, None, EffectMatch::Any);
+        assert_eq!(
+            short.mismatch().unwrap().lines().next(),
+            Some("Expected 1 events, but 3 were logged")
+        );
+        let long = EventSequence::new(&s.processed_events()[..1])
+            .then(None, None, EffectMatch::Any)
+            .then(Some(7.0), None, EffectMatch::Exact(Effect::Release(0)));
+        assert_eq!(
+            long.mismatch().unwrap(),
+            "Event 1 differs: expected (7 ± 0.000000001, _, Release(0)), but only 1 events were logged\n\
+             \u{20}    0: (0, 0, Request(0))"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Event 2 differs: expected (6 ± 0.000000001, _, _)")]
+    fn assert_panics() {
+        let s = run();
+        crate::assert_event_sequence!(s.processed_events(), [(_, _, _), (_, _, _), (6.0, _, _)]);
+    }
+}
@@ -0,0 +1,2006 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+#[cfg(feature = "nightly")]
+pub(crate) mod generators;
+
+#[test]
+fn it_works() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        let mut a = 0.0;
+        loop {
+            a += 1.0;
+
+            handle.yield_state(Effect::TimeOut(a)).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.step();
+    s.step();
+    assert_eq!(s.time(), 1.0);
+    s.step();
+    assert_eq!(s.time(), 3.0);
+    s.step();
+    assert_eq!(s.time(), 6.0);
+}
+
+#[test]
+fn run() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        let tik = 0.7;
+        loop {
+            println!("tik");
+            handle.yield_state(Effect::TimeOut(tik)).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let s = s.run(EndCondition::Time(10.0));
+    println!("{}", s.time());
+    assert!(s.time() >= 10.0);
+}
+
+#[test]
+fn resource() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+
+    // simple process that lock the resource for 7 time units
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    // simple process that holds the resource for 3 time units
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(3.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+
+    // let p1 start immediately...
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    // let p2 start after 2 t.u., when r is not available
+    s.schedule_event(2.0, p2, Effect::TimeOut(2.));
+    // p2 will wait r to be free (time 7.0) and its timeout
+    // of 3.0 t.u. The simulation will end at time 10.0
+
+    let s = s.run(NoEvents);
+    println!("{:?}", s.processed_events());
+    assert_eq!(s.time(), 10.0);
+}
+
+#[test]
+fn resource_hold_log() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(3.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(2.0, p2, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+    // p2 waits from 2.0 and only holds the resource after p1 released it
+    assert_eq!(s.resource_hold_log(r), &[(0.0, 7.0, p1), (7.0, 10.0, p2)]);
+}
+
+#[test]
+fn wait_time_cdf() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    s.record_wait_samples(r, true);
+    for i in 0..4 {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(2.0)).await;
+            handle.yield_state(Effect::Release(r)).await;
+        });
+        s.schedule_event(i as f64, p, Effect::TimeOut(0.));
+    }
+    let s = s.run(NoEvents);
+    // granted at 0, 2, 4 and 6 after arriving at 0, 1, 2 and 3
+    assert_eq!(
+        s.wait_time_cdf(r),
+        vec![(0.0, 0.25), (1.0, 0.5), (2.0, 0.75), (3.0, 1.0)]
+    );
+}
+
+/// An M/M/1/2 queue: a request arriving while one customer is served
+/// and another waits is rejected.
+#[cfg(feature = "std")]
+#[test]
+fn queue_cap_blocking() {
+    use crate::rng::Rng;
+    use crate::{Effect, EndCondition::NoEvents, JitterSpec, SimContext, Simulation};
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    let (lambda, mu, customers) = (1.0, 2.0, 20000);
+    let mut s = Simulation::new();
+    s.set_seed(3);
+    let r = s.create_resource_with_queue_cap(1, 1);
+    let blocked = Rc::new(Cell::new(0));
+    let mut rng = Rng::new(5);
+    let mut arrival = 0.0;
+    for _ in 0..customers {
+        let blocked = blocked.clone();
+        let p = s.create_async_process(move |handle| async move {
+            let ctx: SimContext<Effect> = handle.yield_state(Effect::Request(r)).await;
+            if ctx.rejected() {
+                blocked.set(blocked.get() + 1);
+                return;
+            }
+            handle
+                .yield_state(Effect::TimeOutJitter {
+                    base: 0.0,
+                    dist: JitterSpec::Exponential(1.0 / mu),
+                })
+                .await;
+            handle.yield_state(Effect::Release(r)).await;
+        });
+        arrival += JitterSpec::Exponential(1.0 / lambda).sample(0.0, || rng.next_f64());
+        s.schedule_event(arrival, p, Effect::TimeOut(0.));
+    }
+    let s = s.run(NoEvents);
+    assert_eq!(s.resource_hold_log(r).len() + blocked.get(), customers);
+    let rho: f64 = lambda / mu;
+    let expected = (1.0 - rho) * rho.powi(2) / (1.0 - rho.powi(3));
+    let measured = blocked.get() as f64 / customers as f64;
+    assert!(
+        (measured - expected).abs() < 0.01,
+        "{} {}",
+        measured,
+        expected
+    );
+}
+
+#[test]
+fn rewind_to_step() {
+    use crate::{Effect, Simulation};
+
+    fn build() -> Simulation<Effect> {
+        let mut s = Simulation::new();
+        for delay in [1.0, 1.5] {
+            let p = s.create_async_process(move |handle| async move {
+                loop {
+                    handle.yield_state(Effect::TimeOut(delay)).await;
+                }
+            });
+            s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        }
+        s
+    }
+
+    let mut s = build();
+    s.set_rebuild(Box::new(build));
+    s.set_replay_check_interval(Some(2));
+    let mut times = Vec::new();
+    for _ in 0..10 {
+        s.step();
+        times.push(s.time());
+    }
+    s.rewind_to_step(5);
+    assert_eq!(s.time(), times[4]);
+    assert_eq!(s.processed_events().len(), 5);
+    // the replay continues like the original run
+    for &time in times[5..].iter() {
+        s.step();
+        assert_eq!(s.time(), time);
+    }
+    s.rewind_to_step(0);
+    assert_eq!(s.time(), 0.0);
+}
+
+#[test]
+fn reset() {
+    use crate::{Effect, EndCondition::Time, Simulation};
+
+    fn build() -> Simulation<Effect> {
+        let mut s = Simulation::new();
+        let p = s.create_async_process(move |handle| async move {
+            loop {
+                handle.yield_state(Effect::TimeOut(1.0)).await;
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s
+    }
+
+    let mut s = build();
+    s.set_rebuild(Box::new(build));
+    let mut s = s.run(Time(10.0));
+    assert_eq!(s.time(), 10.0);
+    s.reset();
+    assert_eq!(s.time(), 0.0);
+    assert_eq!(s.total_simulated_time(), 10.0);
+    let s = s.run(Time(5.0));
+    assert_eq!(s.time(), 5.0);
+    assert_eq!(s.total_simulated_time(), 15.0);
+}
+
+#[test]
+fn resource_contention_lookups() {
+    use crate::{Effect, EndCondition::NSteps, ProcessId, ResourceId, Simulation};
+
+    let mut s = Simulation::new();
+    let r1 = s.create_resource(1);
+    let r2 = s.create_resource(1);
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r1)).await;
+        handle.yield_state(Effect::Request(r2)).await;
+        handle.yield_state(Effect::TimeOut(5.0)).await;
+        handle.yield_state(Effect::Release(r2)).await;
+        handle.yield_state(Effect::Release(r1)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r1)).await;
+        handle.yield_state(Effect::Release(r1)).await;
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(1.0, p2, Effect::TimeOut(0.));
+    // p1 holds both resources and p2 is waiting for the first one
+    let mut s = s.run(NSteps(4));
+    assert_eq!(s.resources_held_by(p1), vec![r1, r2]);
+    assert_eq!(s.resources_held_by(p2), Vec::<ResourceId>::new());
+    assert_eq!(s.processes_waiting_on(r1), vec![p2]);
+    assert_eq!(s.processes_waiting_on(r2), Vec::<ProcessId>::new());
+    // after the releases p2 takes the first resource
+    s.step();
+    s.step();
+    assert_eq!(s.resources_held_by(p1), Vec::<ResourceId>::new());
+    assert_eq!(s.resources_held_by(p2), vec![r1]);
+    assert_eq!(s.processes_waiting_on(r1), Vec::<ProcessId>::new());
+}
+
+#[test]
+fn dedup_wakeups() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let sleeper = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    let mut sources = Vec::new();
+    for _ in 0..2 {
+        sources.push(s.create_async_process(move |handle| async move {
+            handle
+                .yield_state(Effect::Event {
+                    time: 5.0,
+                    process: sleeper,
+                })
+                .await;
+        }));
+    }
+    s.set_dedup_wakeups(true);
+    s.schedule_event(0.0, sleeper, Effect::TimeOut(0.));
+    for &source in sources.iter() {
+        s.schedule_event(0.0, source, Effect::TimeOut(0.));
+    }
+    let s = s.run(NoEvents);
+    let activations: Vec<f64> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| e.process() == sleeper)
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(activations, vec![0.0, 5.0]);
+}
+
+#[test]
+fn suspend_and_resume_process() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    let machine = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(10.0)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    s.schedule_event(0.0, machine, Effect::TimeOut(0.));
+    s.advance_bounded(3.0);
+    // powered off with 7 time units left until the timeout
+    s.suspend_process(machine);
+    assert_eq!(s.advance_bounded(20.0).steps, 0);
+    s.resume_process(machine);
+    s.step();
+    assert_eq!(s.time(), 27.0);
+    let times: Vec<f64> = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(times, vec![0.0, 27.0]);
+}
+
+#[test]
+fn tuple_states() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        handle
+            .yield_state((Effect::TimeOut(1.0), "first", true))
+            .await;
+        handle
+            .yield_state((Effect::TimeOut(1.0), "hidden", false))
+            .await;
+        handle
+            .yield_state((Effect::TimeOut(1.0), "last", true))
+            .await;
+    });
+    s.schedule_event(0.0, p, (Effect::TimeOut(0.), "start", true));
+    let s = s.run(NoEvents);
+    let labels: Vec<&str> = s.processed_events().iter().map(|(_, y)| y.1).collect();
+    assert_eq!(labels, vec!["first", "last"]);
+    assert_eq!(s.time(), 3.0);
+}
+
+#[test]
+fn run_collecting() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        for _ in 0..4 {
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let times = |events: &[(crate::Event<Effect>, Effect)]| -> Vec<f64> {
+        events.iter().map(|(e, _)| e.time()).collect()
+    };
+    let first = s.run_collecting(EndCondition::Time(1.5));
+    assert_eq!(times(&first), vec![0.0, 1.0, 2.0]);
+    let second = s.run_collecting(EndCondition::NoEvents);
+    assert_eq!(times(&second), vec![3.0]);
+    assert_eq!(s.processed_events().len(), 4);
+}
+
+#[test]
+fn all_processes_completed() {
+    use crate::{Effect, EndCondition::AllProcessesCompleted, Simulation};
+
+    let mut s = Simulation::new();
+    // a passive helper, never scheduled
+    s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    let instant = s.create_async_process(move |handle| async move {
+        if false {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    let worker = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(2.0)).await;
+    });
+    s.schedule_event(0.0, instant, Effect::TimeOut(0.));
+    s.schedule_event(0.0, worker, Effect::TimeOut(0.));
+    let s = s.run(AllProcessesCompleted);
+    assert_eq!(s.time(), 2.0);
+    assert_eq!(s.live_process_count(), 1);
+}
+
+#[test]
+fn round_robin_cpu() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let cpu = s.create_cpu(2.0);
+    let mut jobs = Vec::new();
+    for _ in 0..2 {
+        jobs.push(s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(cpu)).await;
+            handle.yield_state(Effect::TimeOut(5.0)).await;
+            handle.yield_state(Effect::Release(cpu)).await;
+        }));
+    }
+    for &job in jobs.iter() {
+        s.schedule_event(0.0, job, Effect::TimeOut(0.));
+    }
+    let s = s.run(NoEvents);
+    // the jobs take turns every 2 time units until they are done
+    assert_eq!(
+        s.resource_hold_log(cpu),
+        &[
+            (0.0, 2.0, jobs[0]),
+            (2.0, 4.0, jobs[1]),
+            (4.0, 6.0, jobs[0]),
+            (6.0, 8.0, jobs[1]),
+            (8.0, 9.0, jobs[0]),
+            (9.0, 10.0, jobs[1])
+        ]
+    );
+}
+
+#[test]
+fn logged_events_condition() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    fn model() -> Simulation<Effect> {
+        let mut s = Simulation::new();
+        let p = s.create_async_process(move |handle| async move {
+            loop {
+                handle.yield_state(Effect::TimeOut(1.0)).await;
+                handle.yield_state(Effect::Trace).await;
+            }
+        });
+        // only the timeouts are observations
+        s.set_log_filter(|_, y| matches!(y, Effect::TimeOut(_)));
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s
+    }
+
+    let s = model().run(EndCondition::LoggedEvents(10));
+    assert_eq!(s.processed_events().len(), 10);
+    assert_eq!(s.time(), 9.0);
+    // capped by time
+    let s = model().run(EndCondition::Any(vec![
+        EndCondition::LoggedEvents(10),
+        EndCondition::Time(4.5),
+    ]));
+    assert_eq!(s.processed_events().len(), 5);
+    let s = model().run(EndCondition::All(vec![
+        EndCondition::LoggedEvents(8),
+        EndCondition::Time(4.5),
+    ]));
+    assert_eq!(s.time(), 7.0);
+}
+
+#[test]
+fn no_pending_after_clean_run() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    for _ in 0..2 {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+            handle.yield_state(Effect::Release(r)).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    s.run(NoEvents).assert_no_pending();
+}
+
+#[test]
+#[should_panic(expected = "process 1 waiting for resource 0")]
+fn pending_after_deadlock() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r1 = s.create_resource(1);
+    let r2 = s.create_resource(1);
+    // the processes take the resources in opposite orders
+    for &(first, second) in [(r1, r2), (r2, r1)].iter() {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(first)).await;
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+            handle.yield_state(Effect::Request(second)).await;
+            handle.yield_state(Effect::Release(second)).await;
+            handle.yield_state(Effect::Release(first)).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    s.run(NoEvents).assert_no_pending();
+}
+
+#[test]
+#[should_panic(expected = "released more times than it was requested")]
+fn double_release() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::Release(r)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.run(NoEvents);
+}
+
+#[test]
+#[should_panic(expected = "scheduled at infinite time")]
+fn infinite_timeout_rejected() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(f64::INFINITY)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.run(NoEvents);
+}
+
+#[test]
+fn infinite_timeout_skipped() {
+    use crate::{Effect, EndCondition::NoEvents, InfiniteTimePolicy, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(f64::MAX)).await;
+        // overflows when added to the current time
+        handle.yield_state(Effect::TimeOut(f64::MAX)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    let other = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(f64::INFINITY)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    s.set_infinite_time_policy(InfiniteTimePolicy::Skip);
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.schedule_event(0.0, other, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+    // the processes are not resumed after the infinite timeouts
+    assert_eq!(s.time(), f64::MAX);
+    assert_eq!(s.processed_events().len(), 3);
+}
+
+#[test]
+#[should_panic(
+    expected = "Resource 0 is held by process 0, that does not exist or completed, after resuming process 0 at time 1"
+)]
+fn invariants_completed_holder() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    s.enable_invariant_checks(true);
+    let r = s.create_resource(1);
+    // the process completes without releasing the resource
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.run(NoEvents);
+}
+
+#[test]
+fn invariants_hold() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    s.enable_invariant_checks(true);
+    let r = s.create_resource(2);
+    for i in 0..5 {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(i as f64)).await;
+            handle.yield_state(Effect::Release(r)).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    s.run(NoEvents).assert_no_pending();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "has completed process")]
+fn invariants_queue_completed_process() {
+    use crate::{Effect, Event, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.step();
+    s.step();
+    // corrupt the state: enqueue the completed process on the resource
+    s.resources[r].queue.push_back(Event {
+        time: 0.0,
+        process: p,
+        state: Effect::Request(r),
+        key: (0, 0),
+        seq: 0,
+        wake: crate::WakeReason::Scheduled,
+        label: None,
+    });
+    s.step();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn subscribe() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let rx = s.subscribe();
+    let bounded = s.subscribe_bounded(1);
+    let s = s.run(NoEvents);
+
+    let received: Vec<_> = rx.try_iter().map(|(e, _)| e.time()).collect();
+    let logged: Vec<_> = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(received, logged);
+    // the bounded receiver was never read: only the first event fits
+    assert_eq!(bounded.try_iter().count(), 1);
+    assert_eq!(s.dropped_events(), logged.len() - 1);
+}
+
+#[test]
+#[cfg(feature = "std")]
+#[should_panic(expected = "The bound of a subscription must be positive")]
+fn subscribe_bounded_to_nothing() {
+    let mut s = crate::Simulation::<crate::Effect>::new();
+    s.subscribe_bounded(0);
+}
+
+#[test]
+fn run_until() {
+    use crate::{Effect, Simulation, StopCondition, StopReason};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let conditions: Vec<StopCondition<Effect>> = vec![
+        (
+            StopReason("late".into()),
+            Box::new(|s: &Simulation<Effect>| s.time() >= 10.0),
+        ),
+        (
+            StopReason("three events".into()),
+            Box::new(|s: &Simulation<Effect>| s.processed_events().len() >= 3),
+        ),
+    ];
+    let reason = s.run_until(conditions);
+    assert_eq!(reason, StopReason("three events".into()));
+    assert_eq!(s.time(), 2.0);
+}
+
+#[test]
+fn run_until_no_events() {
+    use crate::{Effect, Simulation, StopCondition, StopReason};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let conditions: Vec<StopCondition<Effect>> = vec![(
+        StopReason("never".into()),
+        Box::new(|s: &Simulation<Effect>| s.time() >= 10.0),
+    )];
+    let reason = s.run_until(conditions);
+    assert_eq!(reason, StopReason::no_events());
+    assert_eq!(s.time(), 1.0);
+}
+
+#[test]
+fn shift_process_events() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(5.0)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.step();
+    s.shift_process_events(p, 10.0);
+    s.step();
+    assert_eq!(s.time(), 15.0);
+}
+
+#[test]
+fn scale_future_times() {
+    use crate::{Effect, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.step();
+    s.step();
+    s.step();
+    // the next event is pending at 3.0
+    assert_eq!(s.time(), 2.0);
+    s.scale_future_times(2.0);
+    let mut times = Vec::new();
+    for _ in 0..3 {
+        s.step();
+        times.push(s.time());
+    }
+    assert_eq!(times, vec![4.0, 6.0, 8.0]);
+}
+
+#[test]
+fn resource_failure() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let failure = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(5.0)).await;
+        handle.yield_state(Effect::FailResource(r)).await;
+        handle.yield_state(Effect::TimeOut(5.0)).await;
+        handle.yield_state(Effect::RepairResource(r)).await;
+    });
+    let user = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, failure, Effect::TimeOut(0.));
+    s.schedule_event(6.0, user, Effect::TimeOut(6.));
+    let s = s.run(NoEvents);
+
+    // the request made at t=6 is granted only after the repair at t=10
+    let user_events: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| e.process() == user)
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(user_events, vec![6.0, 10.0, 11.0]);
+    assert_eq!(s.time(), 11.0);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn resume_profile() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        for _ in 0..3 {
+            std::hint::black_box((0..10_000u64).sum::<u64>());
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(0.0, p2, Effect::TimeOut(0.));
+    assert!(s.resume_profile().is_empty());
+    s.set_resume_profiling(true);
+    let s = s.run(NoEvents);
+
+    let profile = s.resume_profile();
+    assert_eq!(profile.len(), 2);
+    let count = |p| profile.iter().find(|(id, _)| *id == p).unwrap().1.count;
+    // every yield plus the final resume that completes the process
+    assert_eq!(count(p1), 2);
+    assert_eq!(count(p2), 4);
+    assert!(profile[0].1.total >= profile[1].1.total);
+    assert!(profile.iter().all(|(_, st)| st.max <= st.total));
+}
+
+#[test]
+fn same_time_tiebreak() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation, TieBreak};
+
+    fn order(tiebreak: TieBreak, seed: u64) -> Vec<usize> {
+        let mut s = Simulation::new();
+        s.set_seed(seed);
+        s.set_same_time_tiebreak(tiebreak);
+        s.set_process_priority(2, 10);
+        for _ in 0..3 {
+            let p = s.create_async_process(move |handle| async move {
+                handle.yield_state(Effect::Trace).await;
+            });
+            s.schedule_event(1.0, p, Effect::TimeOut(1.));
+        }
+        let s = s.run(NoEvents);
+        s.processed_events()
+            .iter()
+            .map(|(e, _)| e.process())
+            .collect()
+    }
+
+    assert_eq!(order(TieBreak::Fifo, 0), vec![0, 1, 2]);
+    assert_eq!(order(TieBreak::Priority, 0), vec![2, 0, 1]);
+    for seed in 0..10 {
+        assert_eq!(order(TieBreak::Random, seed), order(TieBreak::Random, seed));
+    }
+    assert!((0..10).any(|seed| order(TieBreak::Random, seed) != vec![0, 1, 2]));
+}
+
+#[test]
+fn tiebreak_at_construction() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation, TieBreak};
+
+    fn order(tiebreak: TieBreak, seed: u64) -> Vec<usize> {
+        let mut s = Simulation::with_tiebreak(tiebreak);
+        s.set_seed(seed);
+        let mut processes = Vec::new();
+        for _ in 0..4 {
+            processes.push(s.create_async_process(move |handle| async move {
+                handle.yield_state(Effect::Trace).await;
+            }));
+        }
+        // scheduled from the last process to the first
+        for &p in processes.iter().rev() {
+            s.schedule_event(1.0, p, Effect::TimeOut(1.));
+        }
+        s.run(NoEvents)
+            .processed_events()
+            .iter()
+            .map(|(e, _)| e.process())
+            .collect()
+    }
+
+    assert_eq!(order(TieBreak::Fifo, 0), vec![3, 2, 1, 0]);
+    assert_eq!(order(TieBreak::ProcessId, 0), vec![0, 1, 2, 3]);
+    assert_eq!(order(TieBreak::Random, 7), order(TieBreak::Random, 7));
+    assert!((0..10).any(|seed| order(TieBreak::Random, seed) != vec![3, 2, 1, 0]));
+}
+
+#[test]
+fn log_filter() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    for _ in 0..2 {
+        let p = s.create_async_process(move |handle| async move {
+            for _ in 0..5 {
+                handle.yield_state(Effect::TimeOut(1.0)).await;
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    s.set_log_filter(|e, _| e.process() == 1 && e.time() >= 2.0);
+    let s = s.run(NoEvents);
+    let logged: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, _)| (e.process(), e.time()))
+        .collect();
+    assert_eq!(logged, vec![(1, 2.0), (1, 3.0), (1, 4.0)]);
+}
+
+#[test]
+fn force_log_all() {
+    use crate::{Effect, EndCondition::NoEvents, SimState, Simulation};
+
+    #[derive(Clone)]
+    struct Quiet(Effect);
+    impl SimState for Quiet {
+        fn effect(&self) -> alloc::borrow::Cow<'_, Effect> {
+            alloc::borrow::Cow::Borrowed(&self.0)
+        }
+        fn set_effect(&mut self, effect: Effect) {
+            self.0 = effect;
+        }
+        fn should_log(&self) -> bool {
+            false
+        }
+    }
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        for _ in 0..3 {
+            handle.yield_state(Quiet(Effect::TimeOut(1.0))).await;
+        }
+    });
+    s.schedule_event(0.0, p, Quiet(Effect::TimeOut(0.)));
+    s.set_force_log_all(true);
+    let s = s.run(NoEvents);
+    assert!(s.processed_events().is_empty());
+    let times: Vec<_> = s.debug_events().iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(times, vec![0.0, 1.0, 2.0]);
+}
+
+#[test]
+fn process_log_level() {
+    use crate::{Effect, EndCondition::NoEvents, LogLevel, Simulation};
+
+    let mut s = Simulation::new();
+    for _ in 0..3 {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Trace).await;
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    s.set_process_log_level(0, LogLevel::Off);
+    s.set_process_log_level(1, LogLevel::Effects);
+    let s = s.run(NoEvents);
+    let logged: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, state)| (e.process(), state.code()))
+        .collect();
+    let timeout = Effect::TimeOut(1.0).code();
+    let trace = Effect::Trace.code();
+    assert_eq!(logged, vec![(2, trace), (1, timeout), (2, timeout)]);
+}
+
+#[test]
+fn lease() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    for (start, hold) in [(0.0, 2.0), (1.0, 5.0), (2.0, 1.0)].iter().copied() {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::AcquireLease(r)).await;
+            handle.yield_state(Effect::TimeOut(hold)).await;
+            handle.yield_state(Effect::ReleaseLease(r)).await;
+        });
+        s.schedule_event(start, p, Effect::TimeOut(start));
+    }
+    let requester = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.5, requester, Effect::TimeOut(0.5));
+    let s = s.run(NoEvents);
+
+    // the unit is returned only when the last holder releases, at t=6
+    let requester_events: Vec<_> = s
+        .processed_events()
+        .iter()
+        .filter(|(e, _)| e.process() == requester)
+        .map(|(e, _)| e.time())
+        .collect();
+    assert_eq!(requester_events, vec![0.5, 6.0]);
+}
+
+#[test]
+fn absolute_time() {
+    use crate::{Effect, SimContext, Simulation};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut s = Simulation::new();
+    let at = Rc::new(Cell::new(0.0));
+    let at_p = at.clone();
+    let p = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::TimeOut(20.0)).await;
+        at_p.set(ctx.now_plus(5.0));
+    });
+    let q = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Wait).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.step();
+    s.step();
+    assert_eq!(s.time(), 20.0);
+    assert_eq!(at.get(), 25.0);
+
+    s.schedule_event_at(at.get(), q, Effect::TimeOut(0.));
+    // relative to the current time: fires at 20.0 + 10.0
+    s.schedule_event(10.0, q, Effect::TimeOut(0.));
+    s.step();
+    assert_eq!(s.time(), 25.0);
+    assert_eq!(s.processed_events().last().unwrap().0.process(), q);
+    s.step();
+    assert_eq!(s.time(), 30.0);
+}
+
+#[test]
+fn event_source() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut s = Simulation::new();
+    let arrivals = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    let other = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Wait).await;
+    });
+    let pulled = Rc::new(Cell::new(0));
+    let pulled_src = pulled.clone();
+    s.set_event_source(Box::new(
+        [1.0, 2.0, 3.0, 3.0, 5.0]
+            .iter()
+            .map(move |&t| (t, arrivals, Effect::TimeOut(0.)))
+            .inspect(move |_| pulled_src.set(pulled_src.get() + 1)),
+    ));
+    s.schedule_event(2.5, other, Effect::TimeOut(0.));
+    assert_eq!(pulled.get(), 1);
+    s.step();
+    assert_eq!(pulled.get(), 2);
+
+    let s = s.run(NoEvents);
+    assert_eq!(pulled.get(), 5);
+    let log: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect();
+    assert_eq!(
+        log,
+        vec![
+            (1.0, arrivals),
+            (2.0, arrivals),
+            (2.5, other),
+            (3.0, arrivals),
+            (3.0, arrivals),
+            (5.0, arrivals)
+        ]
+    );
+}
+
+#[test]
+fn probe() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    for &life in [3.0, 8.0, 12.0].iter() {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::TimeOut(life)).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    let probe = s.add_probe(5.0, Box::new(|s| s.live_process_count() as f64));
+    let s = s.run(NoEvents);
+    assert_eq!(
+        s.probe_series(probe),
+        &[(0.0, 3.0), (5.0, 2.0), (10.0, 1.0)]
+    );
+    assert_eq!(s.time(), 12.0);
+}
+
+#[test]
+fn time_display() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation, TimeDisplay};
+
+    assert_eq!(TimeDisplay::Raw.format(5400.0), "5400");
+    assert_eq!(TimeDisplay::Unit("s").format(12.5), "12.5 s");
+    let minutes = TimeDisplay::HoursMinutesSeconds {
+        seconds_per_unit: 60.0,
+    };
+    assert_eq!(minutes.format(90.5), "01:30:30.000");
+    assert_eq!(minutes.format(-1.0), "-00:01:00.000");
+    let seconds = TimeDisplay::HoursMinutesSeconds {
+        seconds_per_unit: 1.0,
+    };
+    assert_eq!(seconds.format(59.9996), "00:01:00.000");
+    assert_eq!(seconds.format(3599.9996), "01:00:00.000");
+    assert_eq!(seconds.format(3599.9994), "00:59:59.999");
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(90.0)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let mut s = s.run(NoEvents);
+    s.set_time_display(minutes);
+    assert_eq!(s.dump_log(), "00:00:00.000\tprocess 0\tTimeOut(90.0)\n");
+}
+
+#[test]
+fn wait_time_quantile() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    for &q in [0.25, 0.5, 0.95].iter() {
+        s.track_wait_time_quantile(r, q);
+    }
+    // 101 processes requesting at t=0 and holding for 1:
+    // they wait 0, 1, ..., 100
+    for _ in 0..101 {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+            handle.yield_state(Effect::Release(r)).await;
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    let s = s.run(NoEvents);
+    for &(q, expected) in [(0.25, 25.0), (0.5, 50.0), (0.95, 95.0)].iter() {
+        let estimate = s.wait_time_quantile(r, q).unwrap();
+        assert!((estimate - expected).abs() < 3.0, "q{}: {}", q, estimate);
+    }
+    assert_eq!(s.wait_time_quantile(r, 0.99), None);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_round_trip() {
+    use crate::{Effect, EndCondition, Event, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let s = s.run(EndCondition::NoEvents);
+
+    let json = serde_json::to_string(s.processed_events()).unwrap();
+    let log: Vec<(Event<Effect>, Effect)> = serde_json::from_str(&json).unwrap();
+    assert_eq!(log.len(), s.processed_events().len());
+    for ((e1, s1), (e2, s2)) in log.iter().zip(s.processed_events()) {
+        assert_eq!((e1.time(), e1.process()), (e2.time(), e2.process()));
+        assert_eq!(s1.code(), s2.code());
+    }
+    assert_eq!(serde_json::to_string(&log).unwrap(), json);
+
+    // these representations are stored in files and must not change
+    assert_eq!(
+        serde_json::to_string(&Effect::Request(2)).unwrap(),
+        r#"{"request":2}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&Effect::Event {
+            time: 1.5,
+            process: 3
+        })
+        .unwrap(),
+        r#"{"event":{"time":1.5,"process":3}}"#
+    );
+    assert_eq!(serde_json::to_string(&Effect::Wait).unwrap(), r#""wait""#);
+    assert_eq!(
+        serde_json::to_string(&EndCondition::<Effect>::NSteps(4)).unwrap(),
+        r#"{"n_steps":4}"#
+    );
+    let until: EndCondition = serde_json::from_str(r#"{"time":10.0}"#).unwrap();
+    assert!(matches!(until, EndCondition::Time(t) if t == 10.0));
+}
+
+#[test]
+fn timeout_jitter() {
+    use crate::{Effect, EndCondition::NoEvents, JitterSpec, Simulation};
+
+    fn delays(dist: JitterSpec, seed: u64) -> Vec<f64> {
+        let mut s = Simulation::new();
+        s.set_seed(seed);
+        let p = s.create_async_process(move |handle| async move {
+            for _ in 0..20 {
+                handle
+                    .yield_state(Effect::TimeOutJitter { base: 1.0, dist })
+                    .await;
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        let s = s.run(NoEvents);
+        let log = s.processed_events();
+        // the log holds the realized delays, that resumed the process
+        for pair in log.windows(2) {
+            match pair[0].1 {
+                Effect::TimeOut(d) => assert_eq!(pair[1].0.time(), pair[0].0.time() + d),
+                _ => panic!("The jitter was not realized"),
+            }
+        }
+        log.iter()
+            .map(|(_, state)| match state {
+                Effect::TimeOut(d) => *d,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    // with a noise wider than the base delay, negative delays are clamped
+    let uniform = delays(JitterSpec::Uniform(2.0), 1);
+    assert!(uniform.iter().all(|&d| (0.0..=3.0).contains(&d)));
+    assert!(uniform.contains(&0.0));
+    assert_eq!(uniform, delays(JitterSpec::Uniform(2.0), 1));
+    assert_ne!(uniform, delays(JitterSpec::Uniform(2.0), 2));
+    assert!(delays(JitterSpec::Exponential(0.5), 1)
+        .iter()
+        .all(|&d| d >= 1.0));
+    let normal = delays(JitterSpec::Normal(2.0), 1);
+    assert!(normal.iter().all(|&d| d >= 0.0));
+    assert!(normal.iter().any(|&d| d < 1.0) && normal.iter().any(|&d| d > 1.0));
+}
+
+#[test]
+fn least_loaded_resource() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let resources = [
+        s.create_resource(1),
+        s.create_resource(1),
+        s.create_resource(1),
+    ];
+    // keep each resource busy, with 2, 0 and 1 processes waiting for it
+    for (&r, users) in resources.iter().zip([3, 1, 2].iter()) {
+        for _ in 0..*users {
+            let p = s.create_async_process(move |handle| async move {
+                handle.yield_state(Effect::Request(r)).await;
+                handle.yield_state(Effect::TimeOut(10.0)).await;
+                handle.yield_state(Effect::Release(r)).await;
+            });
+            s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        }
+    }
+    let balancer = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::TimeOut(1.0)).await;
+        let snapshot = ctx.resources_snapshot(&resources);
+        assert_eq!(snapshot, vec![(0, 2), (0, 0), (0, 1)]);
+        let (chosen, _) = snapshot
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &(available, queued))| queued as isize - available as isize)
+            .unwrap();
+        let r = resources[chosen];
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, balancer, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    assert!(s
+        .resource_hold_log(resources[1])
+        .iter()
+        .any(|&(start, _, p)| p == balancer && start == 10.0));
+}
+
+#[test]
+#[should_panic(expected = "Resource 1 does not exist")]
+fn resources_snapshot_of_unknown_resource() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::TimeOut(1.0)).await;
+        ctx.resources_snapshot(&[r, r + 1]);
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.run(NoEvents);
+}
+
+#[test]
+#[should_panic(
+    expected = "Process 0 yielded 101 effects at time 0 without the clock advancing, the last one being Trace"
+)]
+fn zero_time_livelock() {
+    use crate::{Effect, EndCondition::Time, Simulation};
+
+    let mut s = Simulation::new();
+    s.set_livelock_limit(Some(100));
+    let p = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Trace).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.run(Time(10.0));
+}
+
+#[test]
+fn simultaneous_bursts() {
+    use crate::{Effect, EndCondition::NSteps, Simulation};
+
+    let mut s = Simulation::new();
+    s.set_livelock_limit(Some(100));
+    let p = s.create_async_process(move |handle| async move {
+        loop {
+            for _ in 0..99 {
+                handle.yield_state(Effect::TimeOut(0.0)).await;
+            }
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let s = s.run(NSteps(1000));
+    assert_eq!(s.time(), 9.0);
+}
+
+#[test]
+fn replay_rng_draws() {
+    use crate::{Effect, EndCondition::NoEvents, JitterSpec, Simulation};
+
+    fn run(seed: u64, replay: Option<Vec<f64>>) -> (Vec<f64>, Vec<f64>) {
+        let mut s = Simulation::new();
+        s.set_seed(seed);
+        s.record_rng_draws(true);
+        if let Some(draws) = replay {
+            s.replay_rng(draws);
+        }
+        let p = s.create_async_process(move |handle| async move {
+            for _ in 0..5 {
+                handle
+                    .yield_state(Effect::TimeOutJitter {
+                        base: 2.0,
+                        dist: JitterSpec::Uniform(1.0),
+                    })
+                    .await;
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        let s = s.run(NoEvents);
+        let times = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+        (times, s.rng_log().to_vec())
+    }
+
+    let (times, draws) = run(1, None);
+    assert_eq!(draws.len(), 5);
+    assert_ne!(times, run(2, None).0);
+    // the recorded draws override the seed
+    assert_eq!(run(2, Some(draws.clone())), (times, draws));
+}
+
+#[test]
+fn releaser_and_waiter_order() {
+    use crate::{Effect, EndCondition::NoEvents, ReleaseOrder, Simulation};
+
+    fn order(release_order: Option<ReleaseOrder>) -> Vec<usize> {
+        let mut s = Simulation::new();
+        if let Some(release_order) = release_order {
+            s.set_release_order(release_order);
+        }
+        let r = s.create_resource(1);
+        let releaser = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+            handle.yield_state(Effect::Release(r)).await;
+            handle.yield_state(Effect::Trace).await;
+        });
+        let waiter = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Request(r)).await;
+            handle.yield_state(Effect::Trace).await;
+        });
+        s.schedule_event(0.0, releaser, Effect::TimeOut(0.));
+        s.schedule_event(0.5, waiter, Effect::TimeOut(0.));
+        // the processes resumed at time 1, after the release
+        s.run(NoEvents)
+            .processed_events()
+            .iter()
+            .filter(|(e, state)| e.time() == 1.0 && matches!(state, Effect::Trace))
+            .map(|(e, _)| e.process())
+            .collect()
+    }
+
+    assert_eq!(order(None), vec![1, 0]);
+    assert_eq!(order(Some(ReleaseOrder::WaiterFirst)), vec![1, 0]);
+    assert_eq!(order(Some(ReleaseOrder::ReleaserFirst)), vec![0, 1]);
+}
+
+#[test]
+fn grant_wake_reason() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation, WakeReason};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let holder = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Request(r)).await;
+        assert_eq!(
+            ctx.wake_reason(),
+            WakeReason::ResourceAcquired {
+                resource: r,
+                unit: 0,
+                waited: 0.0
+            }
+        );
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::TimeOut(3.0)).await;
+        assert_eq!(ctx.wake_reason(), WakeReason::Scheduled);
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let waiter = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Request(r)).await;
+        // resumed by the grant, with the state of the request
+        assert!(matches!(ctx.state(), Effect::Request(_)));
+        assert_eq!(
+            ctx.wake_reason(),
+            WakeReason::ResourceAcquired {
+                resource: r,
+                unit: 0,
+                waited: 2.0
+            }
+        );
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, holder, Effect::TimeOut(0.));
+    s.schedule_event(1.0, waiter, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    let (grant, _) = s
+        .processed_events()
+        .iter()
+        .find(|(e, _)| e.process() == waiter && e.time() == 3.0)
+        .unwrap();
+    assert!(matches!(grant.state(), Effect::Request(_)));
+    assert!(matches!(
+        grant.wake_reason(),
+        WakeReason::ResourceAcquired { waited, .. } if waited == 2.0
+    ));
+}
+
+#[test]
+fn effect_middleware() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::TimeOut(2.0)).await;
+        handle.yield_state(Effect::Trace).await;
+    });
+    s.add_effect_middleware(Box::new(|_, effect| match effect {
+        Effect::TimeOut(t) => Effect::TimeOut(t + 1.0),
+        effect => effect,
+    }));
+    // chained after the first one
+    s.add_effect_middleware(Box::new(|_, effect| match effect {
+        Effect::TimeOut(t) => Effect::TimeOut(t * 10.0),
+        effect => effect,
+    }));
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    let log: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, state)| (e.time(), state.code()))
+        .collect();
+    // the log keeps the requested delays, that fire later
+    assert!(matches!(s.processed_events()[0].1, Effect::TimeOut(t) if t == 1.0));
+    assert_eq!(log, vec![(0.0, 0), (20.0, 0), (50.0, 5)]);
+}
+
+#[test]
+fn events_for_process() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    let mut s = Simulation::new();
+    for i in 0..3 {
+        let p = s.create_async_process(move |handle| async move {
+            for _ in 0..=i {
+                handle.yield_state(Effect::TimeOut(1.0)).await;
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    }
+    let s = s.run(NoEvents);
+    for p in 0..3 {
+        let indexed: Vec<_> = s.events_for_process(p).collect();
+        let scanned: Vec<_> = s
+            .processed_events()
+            .iter()
+            .filter(|(e, _)| e.process() == p)
+            .collect();
+        assert_eq!(indexed.len(), p + 1);
+        assert!(indexed
+            .iter()
+            .zip(scanned.iter())
+            .all(|(a, b)| std::ptr::eq(*a, *b)));
+    }
+    assert_eq!(s.events_for_process(7).count(), 0);
+    assert!(s.log_index_memory() >= 6 * std::mem::size_of::<u32>());
+}
+
+#[test]
+fn process_status_breakdown() {
+    use crate::{Effect, EndCondition::NoEvents, ProcessStatus, Simulation};
+
+    let mut s = Simulation::new();
+    let worker = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(7.0)).await;
+        handle.yield_state(Effect::Wait).await;
+    });
+    let waker = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::TimeOut(10.0)).await;
+        handle
+            .yield_state(Effect::Event {
+                time: 0.0,
+                process: worker,
+            })
+            .await;
+    });
+    s.schedule_event(0.0, worker, Effect::TimeOut(0.));
+    s.schedule_event(0.0, waker, Effect::TimeOut(0.));
+
+    let s = s.run(NoEvents);
+    let breakdown = s.process_status_breakdown(worker);
+    assert_eq!(breakdown[&ProcessStatus::Running], 7.0);
+    assert_eq!(breakdown[&ProcessStatus::Waiting], 3.0);
+    assert_eq!(breakdown.values().sum::<f64>(), 10.0);
+    assert_eq!(s.process_status(worker), Some(ProcessStatus::Completed));
+}
+
+#[test]
+fn event_selector() {
+    use crate::{Effect, EndCondition::NoEvents, PendingEvent, Simulation};
+
+    let mut s = Simulation::new();
+    for _ in 0..3 {
+        let p = s.create_async_process(move |handle| async move {
+            handle.yield_state(Effect::Trace).await;
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        });
+        s.schedule_event(1.0, p, Effect::TimeOut(1.));
+    }
+    let offered = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let log = offered.clone();
+    // always pick the event scheduled last
+    s.set_event_selector(move |ready: &mut [PendingEvent]| {
+        log.borrow_mut().push(ready.len());
+        ready.len() - 1
+    });
+
+    let s = s.run(NoEvents);
+    let order: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, _)| (e.time(), e.process()))
+        .collect();
+    // the process chosen first yields a trace, scheduled at the same time,
+    // that is chosen next
+    assert_eq!(
+        order,
+        vec![(1.0, 2), (1.0, 2), (1.0, 1), (1.0, 1), (1.0, 0), (1.0, 0)]
+    );
+    // a single event is not offered
+    assert_eq!(*offered.borrow(), vec![3, 3, 2, 2, 3, 2]);
+}
+
+#[test]
+fn peek_events() {
+    use crate::{Effect, EndCondition::NSteps, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    for i in 0..3 {
+        let seen = seen.clone();
+        let p = s.create_async_process(move |handle| async move {
+            let ctx: SimContext<Effect> = handle.yield_state(Effect::TimeOut(10.0)).await;
+            let upcoming: Vec<_> = ctx.upcoming_events().iter().map(|e| e.process).collect();
+            seen.borrow_mut().push(upcoming);
+        });
+        s.schedule_event(3.0 - i as f64, p, Effect::TimeOut(0.));
+    }
+    let peek = |s: &Simulation<Effect>, k| -> Vec<_> {
+        s.peek_events(k)
+            .iter()
+            .map(|e| (e.time, e.process))
+            .collect()
+    };
+    assert_eq!(peek(&s, 2), vec![(1.0, 2), (2.0, 1)]);
+    assert_eq!(peek(&s, 5), vec![(1.0, 2), (2.0, 1), (3.0, 0)]);
+    assert!(peek(&s, 0).is_empty());
+
+    s.set_context_lookahead(2);
+    let s = s.run(NSteps(4));
+    // process 2, resumed again at time 11, saw the two events after it
+    assert_eq!(peek(&s, 3), vec![(12.0, 1), (13.0, 0)]);
+    assert_eq!(*seen.borrow(), vec![vec![1, 0]]);
+}
+
+#[test]
+fn request_affinity() {
+    use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(2);
+    let granted = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let log = granted.clone();
+    let a = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Request(r)).await;
+        log.borrow_mut().push(("a", ctx.granted_unit()));
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let log = granted.clone();
+    let b = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Request(r)).await;
+        let unit = ctx.granted_unit();
+        log.borrow_mut().push(("b", unit));
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        // both units are free, the previous one is granted again
+        let ctx: SimContext<Effect> = handle
+            .yield_state(Effect::RequestAffinity {
+                resource: r,
+                prefer_unit: unit,
+            })
+            .await;
+        log.borrow_mut().push(("b", ctx.granted_unit()));
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let log = granted.clone();
+    let c = s.create_async_process(move |handle| async move {
+        // the preferred unit is held by b, another one is granted
+        let ctx: SimContext<Effect> = handle
+            .yield_state(Effect::RequestAffinity {
+                resource: r,
+                prefer_unit: Some(1),
+            })
+            .await;
+        log.borrow_mut().push(("c", ctx.granted_unit()));
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::TimeOut(1.0)).await;
+        assert_eq!(ctx.granted_unit(), None);
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(0.0, a, Effect::TimeOut(0.));
+    s.schedule_event(0.0, b, Effect::TimeOut(0.));
+    s.schedule_event(2.5, c, Effect::TimeOut(0.));
+
+    s.run(NoEvents).assert_no_pending();
+    assert_eq!(
+        *granted.borrow(),
+        vec![
+            ("a", Some(0)),
+            ("b", Some(1)),
+            ("b", Some(1)),
+            ("c", Some(0))
+        ]
+    );
+}
+
+#[test]
+fn event_labels() {
+    use crate::{Effect, EndCondition::Time, Simulation};
+
+    let mut s = Simulation::new();
+    let arrival = s.intern_label("arrival");
+    let audit = s.intern_label("audit");
+    assert_eq!(s.intern_label("arrival"), arrival);
+    assert_eq!(s.label_name(audit), "audit");
+    let p = s.create_async_process(move |handle| async move {
+        loop {
+            handle
+                .yield_state(Effect::LabeledTimeOut {
+                    time: 1.0,
+                    label: audit,
+                })
+                .await;
+        }
+    });
+    s.schedule_labeled(0.0, p, Effect::TimeOut(0.), arrival);
+    s.schedule_event(0.5, p, Effect::TimeOut(0.));
+    let s = s.run(Time(2.0));
+    let labels: Vec<_> = s
+        .processed_events()
+        .iter()
+        .map(|(e, _)| (e.time(), e.label()))
+        .collect();
+    assert_eq!(
+        labels,
+        vec![
+            (0.0, Some(arrival)),
+            (0.5, None),
+            (1.0, Some(audit)),
+            (1.5, Some(audit)),
+            (2.0, Some(audit))
+        ]
+    );
+    assert_eq!(s.log().with_label(audit).count(), 3);
+    let counts = s.log().count_by_label();
+    assert_eq!(counts.get(&arrival), Some(&1));
+    assert_eq!(counts.len(), 2);
+}
+
+#[test]
+fn effect_history() {
+    use crate::{Effect, EndCondition::NoEvents, SimState, Simulation};
+
+    #[derive(Debug, Clone)]
+    struct Quiet(Effect);
+    impl SimState for Quiet {
+        fn effect(&self) -> alloc::borrow::Cow<'_, Effect> {
+            alloc::borrow::Cow::Borrowed(&self.0)
+        }
+        fn set_effect(&mut self, effect: Effect) {
+            self.0 = effect;
+        }
+        fn should_log(&self) -> bool {
+            !matches!(self.0, Effect::Wait)
+        }
+    }
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Quiet(Effect::Request(r))).await;
+        handle.yield_state(Quiet(Effect::TimeOut(2.0))).await;
+        handle.yield_state(Quiet(Effect::Release(r))).await;
+        handle.yield_state(Quiet(Effect::Wait)).await;
+    });
+    s.record_effect_history(true);
+    s.schedule_event(1.0, p, Quiet(Effect::TimeOut(0.)));
+    let s = s.run(NoEvents);
+
+    let history: Vec<_> = s
+        .effect_history(p)
+        .into_iter()
+        .map(|(t, e)| (t, e.code()))
+        .collect();
+    assert_eq!(history, vec![(1.0, 2), (1.0, 0), (3.0, 3), (3.0, 4)]);
+    // the wait is not logged
+    assert_eq!(s.processed_events().len(), 3);
+    assert!(s.effect_history(p + 1).is_empty());
+}
+
+#[test]
+fn sleep_is_timeout() {
+    use crate::{Effect, EndCondition::NoEvents, Simulation};
+
+    fn run(sleep: fn(f64) -> Effect) -> Vec<(f64, usize)> {
+        let mut s = Simulation::new();
+        let r = s.create_resource(1);
+        for i in 0..3 {
+            let p = s.create_async_process(move |handle| async move {
+                handle.yield_state(sleep(i as f64 * 0.5)).await;
+                handle.yield_state(Effect::Request(r)).await;
+                // a cooperative handle.yield_state().await, resumed after the other events now
+                handle.yield_state(sleep(0.0)).await;
+                handle.yield_state(sleep(1.0)).await;
+                handle.yield_state(Effect::Release(r)).await;
+            });
+            s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        }
+        s.scale_future_times(2.0);
+        let s = s.run(NoEvents);
+        s.processed_events()
+            .iter()
+            .map(|(e, _)| (e.time(), e.process()))
+            .collect()
+    }
+    assert_eq!(run(Effect::Sleep), run(Effect::TimeOut));
+}
+
+#[test]
+fn watchdog() {
+    use crate::{Effect, EndCondition::Time, Simulation};
+
+    let mut s = Simulation::new();
+    let stuck = s.create_async_process(move |handle| async move {
+        loop {
+            // resumed every time unit, but never times out nor releases anything
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    let busy = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    let quiet = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::Wait).await;
+        }
+    });
+    s.set_watchdog(Some(3));
+    s.set_process_watchdog(quiet, None);
+    let hooked = std::rc::Rc::new(std::cell::Cell::new(0));
+    let count = hooked.clone();
+    s.set_watchdog_hook(Box::new(move |_| count.set(count.get() + 1)));
+    for t in 0..8 {
+        s.schedule_event(t as f64, stuck, Effect::TimeOut(0.));
+        s.schedule_event(t as f64, quiet, Effect::TimeOut(0.));
+    }
+    s.schedule_event(0.0, busy, Effect::TimeOut(0.));
+    let s = s.run(Time(8.0));
+
+    let reports: Vec<_> = s
+        .watchdog_reports()
+        .iter()
+        .map(|r| (r.process, r.time, r.last_progress, r.resumes))
+        .collect();
+    assert_eq!(reports, vec![(stuck, 3.0, 0.0, 4), (stuck, 7.0, 0.0, 4)]);
+    assert_eq!(s.watchdog_reports()[1].recent.len(), 8);
+    assert_eq!(hooked.get(), 2);
+}
+
+#[test]
+fn start_time() {
+    use crate::{Effect, EndCondition::Time, JitterSpec, Simulation};
+
+    fn run(start: f64) -> Simulation<Effect> {
+        let mut s = Simulation::with_start_time(start);
+        s.set_seed(3);
+        let r = s.create_resource(1);
+        for _ in 0..3 {
+            let p = s.create_async_process(move |handle| async move {
+                loop {
+                    handle.yield_state(Effect::Request(r)).await;
+                    handle
+                        .yield_state(Effect::TimeOutJitter {
+                            base: 2.0,
+                            dist: JitterSpec::Uniform(1.0),
+                        })
+                        .await;
+                    handle.yield_state(Effect::Release(r)).await;
+                    handle.yield_state(Effect::TimeOut(1.0)).await;
+                }
+            });
+            s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        }
+        s.run(Time(start + 100.0))
+    }
+
+    let (early, late) = (run(0.0), run(1000.0));
+    assert_eq!(late.start_time(), 1000.0);
+    assert!((late.time() - 1000.0 - early.time()).abs() < 1e-9);
+    let relative = |s: &Simulation<Effect>| -> Vec<_> {
+        s.processed_events()
+            .iter()
+            .map(|(e, state)| (e.time() - s.start_time(), e.process(), state.code()))
+            .collect()
+    };
+    let log = relative(&early);
+    assert!(log.len() > 50);
+    for (a, b) in log.iter().zip(relative(&late).iter()) {
+        assert!((a.0 - b.0).abs() < 1e-9, "{:?} {:?}", a, b);
+        assert_eq!((a.1, a.2), (b.1, b.2));
+    }
+    assert_eq!(log.len(), late.processed_events().len());
+    let (u, v) = (early.resource_utilization(0), late.resource_utilization(0));
+    assert!(u > 0.9 && (u - v).abs() < 1e-9, "{} {}", u, v);
+    let counts = |s: &Simulation<Effect>| -> Vec<usize> {
+        s.log()
+            .bucketed(10.0)
+            .count()
+            .into_iter()
+            .map(|(_, c)| c)
+            .collect()
+    };
+    assert_eq!(counts(&early), counts(&late));
+    assert_eq!(late.log().bucketed(10.0).count()[0].0, 1000.0);
+}
+
+#[test]
+fn expected_events() {
+    use crate::{Effect, EndCondition::NoEvents, Event, Simulation, WakeReason};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(2.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    s.schedule_event(1.0, p, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+
+    let expected = vec![
+        Event::new(1.0, p, Effect::TimeOut(0.)),
+        Event::new(1.0, p, Effect::Request(r)).with_wake_reason(WakeReason::ResourceAcquired {
+            resource: r,
+            unit: 0,
+            waited: 0.0,
+        }),
+        Event::new(3.0, p, Effect::TimeOut(2.0)),
+    ];
+    let logged: Vec<_> = s.processed_events().iter().map(|(e, _)| *e).collect();
+    assert_eq!(logged, expected);
+    let same_effect = |a: &Effect, b: &Effect| a.code() == b.code();
+    for (e, expected) in logged.iter().zip(expected.iter()) {
+        assert!(e.eq_by(expected, same_effect));
+        assert_eq!(e.wake_reason(), expected.wake_reason());
+    }
+    // same time, different process
+    assert_ne!(logged[0], Event::new(1.0, p + 1, Effect::TimeOut(0.)));
+    assert!(!logged[0].eq_by(&expected[1], same_effect));
+}
+
+#[test]
+fn stop_on_last_event() {
+    use crate::{Effect, EndCondition, Simulation};
+    use alloc::sync::Arc;
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p = s.create_async_process(move |handle| async move {
+        loop {
+            handle.yield_state(Effect::TimeOut(1.0)).await;
+        }
+    });
+    let q = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(2.5)).await;
+        handle.yield_state(Effect::Release(r)).await;
+        handle.yield_state(Effect::TimeOut(5.0)).await;
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    s.schedule_event(0.0, q, Effect::TimeOut(0.));
+    let released = EndCondition::LastEvent(Arc::new(move |e, state| {
+        e.process() == q && matches!(state, Effect::Release(_))
+    }));
+    let s = s.run(EndCondition::Any(vec![EndCondition::Time(100.0), released]));
+
+    assert_eq!(s.time(), 2.5);
+    let (event, state) = s.processed_events().last().unwrap();
+    assert_eq!(event.process(), q);
+    assert!(matches!(state, Effect::Release(_)));
+    let s = s.run(EndCondition::All(vec![
+        EndCondition::Time(4.0),
+        EndCondition::LastEvent(Arc::new(move |e, _| e.process() == p)),
+    ]));
+    assert_eq!(s.time(), 4.0);
+    assert_eq!(s.processed_events().last().unwrap().0.process(), p);
+}
+
+#[test]
+fn time_exact() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    fn build() -> Simulation<Effect> {
+        let mut s = Simulation::new();
+        let p = s.create_async_process(move |handle| async move {
+            loop {
+                handle.yield_state(Effect::TimeOut(3.0)).await;
+            }
+        });
+        s.schedule_event(0.0, p, Effect::TimeOut(0.));
+        s
+    }
+    let s = build().run(EndCondition::Time(10.0));
+    assert_eq!(s.time(), 12.0);
+
+    // the time is left at the last event before the target
+    let s = build().run(EndCondition::TimeExact(10.0));
+    assert_eq!(s.time(), 9.0);
+    let times: Vec<_> = s.processed_events().iter().map(|(e, _)| e.time()).collect();
+    assert_eq!(times, vec![0.0, 3.0, 6.0, 9.0]);
+    // without an event before the target, the time moves to it
+    let s = s.run(EndCondition::TimeExact(11.0));
+    assert_eq!(s.processed_events().len(), 4);
+    assert_eq!(s.time(), 11.0);
+    // an event at the target is processed
+    let s = s.run(EndCondition::TimeExact(12.0));
+    assert_eq!(s.processed_events().last().unwrap().0.time(), 12.0);
+    assert_eq!(s.time(), 12.0);
+}
+
+#[test]
+fn time_exact_runs_internal_events() {
+    use crate::{Effect, EndCondition, Simulation};
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    s.set_resource_schedule(r, vec![(0.0, 0), (5.0, 1)], None);
+    let waiter = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let late = s.create_async_process(|handle| async move {
+        handle.yield_state(Effect::TimeOut(1.0)).await;
+    });
+    s.schedule_event(0.0, waiter, Effect::TimeOut(0.));
+    s.schedule_event(20.0, late, Effect::TimeOut(0.));
+    // the capacity change at 5 is an internal event, before the target
+    // and the next process event: it runs and lets the waiter go on
+    let s = s.run(EndCondition::TimeExact(10.0));
+    assert_eq!(s.resource_hold_log(r), &[(5.0, 6.0, waiter)]);
+    assert_eq!(s.time(), 6.0);
+}
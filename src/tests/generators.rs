@@ -0,0 +1,109 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! The tests of the generator processes, that need the nightly toolchain.
+
+/// Create a process yielding `effects` in order, as a generator, for the
+/// tests run with each process backend.
+pub(crate) fn generator_script(
+    s: &mut crate::Simulation<crate::Effect>,
+    effects: Vec<crate::Effect>,
+) -> crate::ProcessId {
+    s.create_process(Box::new(move |_| {
+        for effect in effects {
+            yield effect;
+        }
+    }))
+}
+
+#[test]
+fn process_pool() {
+    use crate::{Effect, EndCondition::NoEvents, ResourceId, SimContext, Simulation};
+    use core::ops::Generator;
+
+    fn customer(
+        r: ResourceId,
+        hold: f64,
+    ) -> impl Generator<SimContext<Effect>, Yield = Effect, Return = ()> + Unpin {
+        move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(hold);
+            yield Effect::Release(r);
+        }
+    }
+
+    let mut pooled = Simulation::new();
+    let r = pooled.create_resource(1);
+    let ids = pooled.create_process_pool([7.0, 3.0].iter().map(|&hold| customer(r, hold)));
+    assert_eq!(ids, 0..2);
+    let mut boxed = Simulation::new();
+    let r = boxed.create_resource(1);
+    for &hold in [7.0, 3.0].iter() {
+        boxed.create_process(Box::new(customer(r, hold)));
+    }
+    let mut inline = Simulation::with_static_processes();
+    let r = inline.create_resource(1);
+    for &hold in [7.0, 3.0].iter() {
+        inline.create_static_process(customer(r, hold));
+    }
+    for s in [&mut pooled, &mut boxed].iter_mut() {
+        s.schedule_event(0.0, 0, Effect::TimeOut(0.));
+        s.schedule_event(2.0, 1, Effect::TimeOut(0.));
+    }
+    inline.schedule_event(0.0, 0, Effect::TimeOut(0.));
+    inline.schedule_event(2.0, 1, Effect::TimeOut(0.));
+
+    // the pooled and inline processes behave like the boxed ones
+    let pooled = pooled.run(NoEvents);
+    let boxed = boxed.run(NoEvents);
+    let inline = inline.run(NoEvents);
+    assert_eq!(pooled.resource_hold_log(r), boxed.resource_hold_log(r));
+    assert_eq!(inline.resource_hold_log(r), boxed.resource_hold_log(r));
+    assert_eq!(inline.live_process_count(), 0);
+    assert_eq!(
+        pooled.resource_hold_log(r),
+        &[(0.0, 7.0, 0), (7.0, 10.0, 1)]
+    );
+    assert_eq!(pooled.live_process_count(), 0);
+}
+
+#[test]
+fn process_templates() {
+    use crate::{Effect, EndCondition::NoEvents, ResourceId, SimGen, Simulation};
+
+    fn customer(r: ResourceId, i: usize) -> Box<SimGen<Effect>> {
+        let hold = 1.0 + i as f64;
+        Box::new(move |_| {
+            yield Effect::Request(r);
+            yield Effect::TimeOut(hold);
+            yield Effect::Release(r);
+        })
+    }
+
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let first =
+        s.create_scheduled_processes("customer", 3, |c| customer(r, c.index), |i| 10.0 * i as f64);
+    let second = s.create_processes("idle", 2, |c| customer(r, c.index));
+    assert_eq!((first.clone(), second.clone()), (0..3, 3..5));
+    let s = s.run(NoEvents);
+    // only the scheduled ones ran, each at its start time
+    assert_eq!(
+        s.resource_hold_log(r),
+        &[(0.0, 1.0, 0), (10.0, 12.0, 1), (20.0, 23.0, 2)]
+    );
+    assert_eq!(s.process_name(2), Some("customer#2"));
+    assert_eq!(s.process_name(4), Some("idle#1"));
+}
@@ -0,0 +1,126 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Timers set, restarted and canceled by the processes.
+
+use crate::{Event, ProcessId, Queued, SimState, Simulation, StaticProcess, WakeReason};
+use alloc::vec::Vec;
+
+/// Identifies a timer set by a process yielding `Effect::SetTimer`,
+/// returned by `SimContext::timer_set`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimerId(u64);
+
+/// A timer and the process that set it.
+#[derive(Debug, Clone)]
+pub(crate) struct Timer {
+    owner: ProcessId,
+    /// Whether the timer is still to expire
+    armed: bool,
+}
+
+/// What a process is told about the timer effect it yielded.
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum TimerReply {
+    /// A timer was set
+    Set(TimerId),
+    /// A timer was canceled or restarted, and whether it was armed
+    Reset { armed: bool },
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Set a timer of `process` expiring after `duration` time units,
+    /// resuming the process with `state`.
+    pub(crate) fn set_timer(&mut self, process: ProcessId, duration: f64, state: T) {
+        let timer = TimerId(self.timers.len() as u64);
+        self.timers.push(Timer {
+            owner: process,
+            armed: false,
+        });
+        self.arm_timer(timer, duration, state.clone());
+        self.timer_replies.insert(process, TimerReply::Set(timer));
+        self.schedule_at(self.time, process, state);
+    }
+
+    /// Cancel `timer`, if it is still to expire, and restart it when
+    /// `restart` is the duration after which it expires again.
+    pub(crate) fn reset_timer(
+        &mut self,
+        process: ProcessId,
+        timer: TimerId,
+        restart: Option<f64>,
+        state: T,
+    ) {
+        let owner = self.timers.get(timer.0 as usize).map(|t| t.owner);
+        assert!(
+            owner == Some(process),
+            "Process {} reset timer {}, that it did not set",
+            process,
+            timer.0
+        );
+        let armed = self.timers[timer.0 as usize].armed;
+        if armed {
+            self.disarm_timer(timer);
+        }
+        if let Some(duration) = restart {
+            self.arm_timer(timer, duration, state.clone());
+        }
+        self.timer_replies
+            .insert(process, TimerReply::Reset { armed });
+        self.schedule_at(self.time, process, state);
+    }
+
+    /// Record that `timer` expired, resuming its process now.
+    pub(crate) fn expire_timer(&mut self, timer: TimerId) {
+        self.timers[timer.0 as usize].armed = false;
+    }
+
+    fn arm_timer(&mut self, timer: TimerId, duration: f64, state: T) {
+        assert!(
+            duration >= 0.0,
+            "Timer {} was set to expire after the negative duration {}",
+            timer.0,
+            duration
+        );
+        let t = &mut self.timers[timer.0 as usize];
+        t.armed = true;
+        let owner = t.owner;
+        let wake = WakeReason::TimerExpired { timer };
+        self.schedule_woken(self.time + duration * self.time_scale, owner, state, wake);
+    }
+
+    fn disarm_timer(&mut self, timer: TimerId) {
+        self.timers[timer.0 as usize].armed = false;
+        let expiry = |e: &Event<T>| e.wake == WakeReason::TimerExpired { timer };
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .take_future_events()
+            .into_iter()
+            .partition(|Queued(e)| expiry(e));
+        self.future_events = kept.into();
+        self.track_pending_wakeups();
+        let mut dropped: Vec<u64> = removed.iter().map(|Queued(e)| e.seq).collect();
+        for events in self.suspended.values_mut() {
+            dropped.extend(events.iter().filter(|(_, e)| expiry(e)).map(|(_, e)| e.seq));
+            events.retain(|(_, e)| !expiry(e));
+        }
+        for seq in dropped {
+            self.cancel_event(seq);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,69 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, EndCondition::NoEvents, SimContext, Simulation, WakeReason};
+
+#[test]
+fn timers() {
+    let mut s = Simulation::new();
+    let expired = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+    let log = expired.clone();
+    let p = s.create_async_process(move |handle| async move {
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::SetTimer(5.0)).await;
+        let retransmit = ctx.timer_set().unwrap();
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::SetTimer(2.0)).await;
+        let ack = ctx.timer_set().unwrap();
+        assert_ne!(retransmit, ack);
+        // canceled before expiring
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::CancelTimer(ack)).await;
+        assert!(ctx.timer_was_armed());
+        assert_eq!(ctx.timer_set(), None);
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::CancelTimer(ack)).await;
+        assert!(!ctx.timer_was_armed());
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::TimeOut(1.0)).await;
+        assert_eq!(ctx.wake_reason(), WakeReason::Scheduled);
+        // the retransmission timer now expires at 4
+        let ctx: SimContext<Effect> = handle
+            .yield_state(Effect::RestartTimer {
+                timer: retransmit,
+                duration: 3.0,
+            })
+            .await;
+        assert!(ctx.timer_was_armed());
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Wait).await;
+        log.borrow_mut().push((ctx.time(), ctx.wake_reason()));
+        // restarting an expired timer sets it again
+        let ctx: SimContext<Effect> = handle
+            .yield_state(Effect::RestartTimer {
+                timer: retransmit,
+                duration: 1.0,
+            })
+            .await;
+        assert!(!ctx.timer_was_armed());
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::Wait).await;
+        log.borrow_mut().push((ctx.time(), ctx.wake_reason()));
+        let ctx: SimContext<Effect> = handle.yield_state(Effect::CancelTimer(retransmit)).await;
+        assert!(!ctx.timer_was_armed());
+    });
+    s.schedule_event(0.0, p, Effect::TimeOut(0.));
+    let s = s.run(NoEvents);
+    s.assert_no_pending();
+
+    let retransmit = expired.borrow();
+    assert_eq!(retransmit.len(), 2);
+    assert_eq!((retransmit[0].0, retransmit[1].0), (4.0, 5.0));
+    assert_eq!(retransmit[0].1, retransmit[1].1);
+    assert!(matches!(retransmit[0].1, WakeReason::TimerExpired { .. }));
+}
@@ -0,0 +1,227 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Detailed traces of single steps, for interactive debugging.
+
+use crate::{Effect, ProcessId, ResourceId, SimState, Simulation, StaticProcess, TimeDisplay};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// An event identified by its time, its process and the order in which
+/// it was scheduled.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TracedEvent {
+    /// Time at which the event occurs
+    pub time: f64,
+    /// Process resumed by the event
+    pub process: ProcessId,
+    /// Sequence number assigned to the event when it was scheduled
+    pub seq: u64,
+}
+
+/// What the process resumed by a step did.
+#[derive(Debug, Copy, Clone)]
+pub enum StepOutcome {
+    /// The process yielded a state with this effect.
+    Yielded(Effect),
+    /// The process completed.
+    Completed,
+}
+
+/// The state of a resource, as seen by a `StepTrace`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResourceSnapshot {
+    /// Number of units not in use
+    pub available: usize,
+    /// Number of processes waiting for a unit
+    pub queue_length: usize,
+    /// Number of processes sharing the current lease
+    pub lease_holders: usize,
+    /// Number of processes waiting for a lease
+    pub lease_queue_length: usize,
+    /// Whether the resource is failed
+    pub failed: bool,
+}
+
+/// A resource whose state was changed by a step.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResourceChange {
+    /// The resource that changed
+    pub resource: ResourceId,
+    /// Its state before the step
+    pub before: ResourceSnapshot,
+    /// Its state after the step
+    pub after: ResourceSnapshot,
+}
+
+/// A detailed account of one step of the simulation, returned by
+/// `Simulation::step_debug`.
+#[derive(Debug, Clone)]
+pub struct StepTrace {
+    /// Number of the step, starting from 1
+    pub step: usize,
+    /// Simulation time after the step
+    pub time: f64,
+    /// The event that was processed and what its process did, or `None`
+    /// if no event was scheduled.
+    pub event: Option<(TracedEvent, StepOutcome)>,
+    /// Events scheduled during the step, in the order they were scheduled.
+    pub scheduled: Vec<TracedEvent>,
+    /// Resources whose state was changed during the step.
+    pub resources: Vec<ResourceChange>,
+    /// Number of events still scheduled after the step
+    pub pending_events: usize,
+    /// Number of events in the log after the step
+    pub logged_events: usize,
+    /// How the times are formatted when the trace is displayed, as set
+    /// with `Simulation::set_time_display`
+    pub time_display: TimeDisplay,
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Proceed in the simulation by 1 step, like `step`, and return a
+    /// detailed trace of what happened.
+    ///
+    /// This is meant for debugging, stepping through a simulation
+    /// interactively: it is slower than `step`, since it has to compare
+    /// the state of the simulation before and after the step.
+    pub fn step_debug(&mut self) -> StepTrace {
+        let first_seq = self.next_seq;
+        let before = self.resource_snapshots();
+        self.step();
+
+        let mut scheduled: Vec<TracedEvent> = self
+            .pending_events()
+            .filter(|e| e.seq >= first_seq)
+            .map(|e| TracedEvent {
+                time: e.time,
+                process: e.process,
+                seq: e.seq,
+            })
+            .collect();
+        scheduled.sort_by_key(|e| e.seq);
+
+        let resources = self
+            .resource_snapshots()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(resource, after)| {
+                // resources created during the step start from their capacity
+                let before = before.get(resource).copied().unwrap_or(ResourceSnapshot {
+                    available: self.resources[resource].allocated,
+                    queue_length: 0,
+                    lease_holders: 0,
+                    lease_queue_length: 0,
+                    failed: false,
+                });
+                if before == after {
+                    None
+                } else {
+                    Some(ResourceChange {
+                        resource,
+                        before,
+                        after,
+                    })
+                }
+            })
+            .collect();
+
+        StepTrace {
+            step: self.steps,
+            time: self.time,
+            event: self.last_step,
+            scheduled,
+            resources,
+            pending_events: self.pending_event_count(),
+            logged_events: self.processed_events.len(),
+            time_display: self.time_display,
+        }
+    }
+
+    fn resource_snapshots(&self) -> Vec<ResourceSnapshot> {
+        self.resources
+            .iter()
+            .map(|r| ResourceSnapshot {
+                available: r.available,
+                queue_length: r.queue.len(),
+                lease_holders: r.lease_holders,
+                lease_queue_length: r.lease_queue.len(),
+                failed: r.failed,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for StepTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = |t| self.time_display.format(t);
+        writeln!(f, "step {} at time {}", self.step, time(self.time))?;
+        match &self.event {
+            None => writeln!(f, "  no event scheduled")?,
+            Some((event, outcome)) => {
+                writeln!(
+                    f,
+                    "  popped event seq {} for process {} at time {}",
+                    event.seq,
+                    event.process,
+                    time(event.time)
+                )?;
+                match outcome {
+                    StepOutcome::Yielded(effect) => {
+                        writeln!(f, "  process {} yielded {:?}", event.process, effect)?
+                    }
+                    StepOutcome::Completed => writeln!(f, "  process {} completed", event.process)?,
+                }
+            }
+        }
+        for event in self.scheduled.iter() {
+            writeln!(
+                f,
+                "  scheduled event seq {} for process {} at time {}",
+                event.seq,
+                event.process,
+                time(event.time)
+            )?;
+        }
+        for change in self.resources.iter() {
+            let (b, a) = (&change.before, &change.after);
+            write!(f, "  resource {}:", change.resource)?;
+            write!(f, " available {} -> {},", b.available, a.available)?;
+            write!(f, " queue {} -> {},", b.queue_length, a.queue_length)?;
+            write!(
+                f,
+                " lease holders {} -> {},",
+                b.lease_holders, a.lease_holders
+            )?;
+            write!(
+                f,
+                " lease queue {} -> {}",
+                b.lease_queue_length, a.lease_queue_length
+            )?;
+            if b.failed != a.failed {
+                write!(f, ", {}", if a.failed { "failed" } else { "repaired" })?;
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "  {} pending events, {} logged events",
+            self.pending_events, self.logged_events
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,79 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+use crate::{Effect, Simulation, StepOutcome, TimeDisplay};
+
+#[test]
+fn step_debug() {
+    let mut s = Simulation::new();
+    let r = s.create_resource(1);
+    let p1 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+        handle.yield_state(Effect::TimeOut(5.0)).await;
+        handle.yield_state(Effect::Release(r)).await;
+    });
+    let p2 = s.create_async_process(move |handle| async move {
+        handle.yield_state(Effect::Request(r)).await;
+    });
+    s.schedule_event(0.0, p1, Effect::TimeOut(0.));
+    s.schedule_event(1.0, p2, Effect::TimeOut(0.));
+
+    // p1 requests the resource and gets it immediately
+    let trace = s.step_debug();
+    let (event, outcome) = trace.event.unwrap();
+    assert_eq!((event.time, event.process, event.seq), (0.0, p1, 0));
+    assert!(matches!(outcome, StepOutcome::Yielded(Effect::Request(_))));
+    assert_eq!(trace.scheduled.len(), 1);
+    assert_eq!(
+        (trace.scheduled[0].process, trace.scheduled[0].seq),
+        (p1, 2)
+    );
+    assert_eq!(trace.resources.len(), 1);
+    assert_eq!(trace.resources[0].before.available, 1);
+    assert_eq!(trace.resources[0].after.available, 0);
+    assert_eq!(trace.pending_events, 2);
+
+    // p1 yields a timeout
+    let trace = s.step_debug();
+    assert_eq!(trace.scheduled[0].time, 5.0);
+    assert!(trace.resources.is_empty());
+
+    // p2 is enqueued on the resource
+    let trace = s.step_debug();
+    assert_eq!(trace.event.unwrap().0.process, p2);
+    assert!(trace.scheduled.is_empty());
+    assert_eq!(trace.resources[0].after.queue_length, 1);
+    let text = trace.to_string();
+    assert!(text.contains("process 1 yielded Request(0)"), "{}", text);
+    assert!(text.contains("queue 0 -> 1"), "{}", text);
+
+    // p1 releases: p2 is granted the resource
+    let trace = s.step_debug();
+    assert!(matches!(
+        trace.event.unwrap().1,
+        StepOutcome::Yielded(Effect::Release(_))
+    ));
+    assert_eq!(trace.scheduled.len(), 2);
+    assert_eq!(trace.resources[0].after.queue_length, 0);
+
+    s.set_time_display(TimeDisplay::Unit("s"));
+    let trace = s.step_debug();
+    let text = trace.to_string();
+    assert!(text.contains("at time 5 s"), "{}", text);
+
+    while s.step_debug().event.is_some() {}
+    let trace = s.step_debug();
+    assert!(trace.to_string().contains("no event scheduled"));
+}
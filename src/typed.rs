@@ -0,0 +1,197 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Typed identifiers of processes and resources, that cannot be mixed up.
+//!
+//! The identifiers at the root of the crate are plain `usize`s, so passing
+//! a process where a resource is expected compiles and silently misbehaves.
+//! The identifiers of this module are distinct types instead, returned by
+//! the typed constructors of `Simulation`, and converted to and from the
+//! plain ones with `From`. Converting a plain identifier, like `unchecked`
+//! does, trusts that it names a process or a resource respectively:
+//!
+//! ```compile_fail
+//! use desim::typed::{ProcessId, ResourceId};
+//! use desim::{Effect, Simulation};
+//!
+//! let mut sim = Simulation::<Effect>::new();
+//! let r: ResourceId = sim.create_typed_resource(1);
+//! let p: ProcessId = r; // does not compile
+//! ```
+//!
+//! They will replace the plain identifiers in the next major version.
+
+use crate::{Effect, EventId, SimState, Simulation, StateMachineProcess, StaticProcess};
+use alloc::boxed::Box;
+use core::fmt;
+
+/// A typed `desim::ProcessId`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ProcessId(usize);
+
+/// A typed `desim::ResourceId`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ResourceId(usize);
+
+impl ProcessId {
+    /// Returns the typed identifier of the process with the plain
+    /// identifier `id`, that the caller must make sure is a process.
+    pub fn unchecked(id: usize) -> ProcessId {
+        ProcessId(id)
+    }
+
+    /// Returns the plain identifier of the process.
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    /// Returns an `Effect::Event` resuming the process after `time`.
+    pub fn event(self, time: f64) -> Effect {
+        Effect::Event {
+            time,
+            process: self.0,
+        }
+    }
+}
+
+impl ResourceId {
+    /// Returns the typed identifier of the resource with the plain
+    /// identifier `id`, that the caller must make sure is a resource.
+    pub fn unchecked(id: usize) -> ResourceId {
+        ResourceId(id)
+    }
+
+    /// Returns the plain identifier of the resource.
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    /// Returns an `Effect::Request` of the resource.
+    pub fn request(self) -> Effect {
+        Effect::Request(self.0)
+    }
+
+    /// Returns an `Effect::Release` of the resource.
+    pub fn release(self) -> Effect {
+        Effect::Release(self.0)
+    }
+}
+
+/// Like `ProcessId::unchecked`: the caller must make sure that `id` is a
+/// process.
+impl From<usize> for ProcessId {
+    fn from(id: usize) -> ProcessId {
+        ProcessId(id)
+    }
+}
+
+impl From<ProcessId> for usize {
+    fn from(id: ProcessId) -> usize {
+        id.0
+    }
+}
+
+/// Like `ResourceId::unchecked`: the caller must make sure that `id` is a
+/// resource.
+impl From<usize> for ResourceId {
+    fn from(id: usize) -> ResourceId {
+        ResourceId(id)
+    }
+}
+
+impl From<ResourceId> for usize {
+    fn from(id: ResourceId) -> usize {
+        id.0
+    }
+}
+
+impl fmt::Display for ProcessId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: SimState + Clone + 'static, G: StaticProcess<T>> Simulation<T, G> {
+    /// Create a resource like `create_resource`, returning its typed
+    /// identifier.
+    pub fn create_typed_resource(&mut self, n: usize) -> ResourceId {
+        ResourceId(self.create_resource(n))
+    }
+
+    /// Create a process like `create_process`, returning its typed
+    /// identifier.
+    #[cfg(feature = "nightly")]
+    pub fn create_typed_process(
+        &mut self,
+        process: Box<
+            dyn core::ops::Generator<crate::SimContext<T>, Yield = T, Return = ()> + Unpin,
+        >,
+    ) -> ProcessId {
+        ProcessId(self.create_process(process))
+    }
+
+    /// Create a process like `create_sm_process`, returning its typed
+    /// identifier.
+    pub fn create_typed_sm_process(
+        &mut self,
+        process: Box<dyn StateMachineProcess<T>>,
+    ) -> ProcessId {
+        ProcessId(self.create_sm_process(process))
+    }
+
+    /// Schedule an event like `schedule_event`, for a process with a
+    /// typed identifier.
+    pub fn schedule_typed(&mut self, time: f64, process: ProcessId, state: T) -> EventId {
+        self.schedule_event(time, process.0, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProcessId, ResourceId};
+    use crate::{Effect, EndCondition, SimContext, Simulation, Step};
+
+    #[test]
+    fn typed_ids() {
+        let mut s = Simulation::new();
+        let r: ResourceId = s.create_typed_resource(1);
+        let cycle = [r.request(), Effect::TimeOut(2.0), r.release()];
+        let mut step = 0;
+        let p: ProcessId = s.create_typed_sm_process(Box::new(move |_: SimContext<Effect>| {
+            step += 1;
+            cycle.get(step - 1).map_or(Step::Done, |&e| Step::Yield(e))
+        }));
+        s.schedule_typed(1.0, p, Effect::TimeOut(0.0));
+        let s = s.run(EndCondition::NoEvents);
+        assert_eq!(s.resource_hold_log(r.into()), &[(1.0, 3.0, p.index())]);
+        // the conversions go through the plain identifiers only
+        assert_eq!(ProcessId::unchecked(usize::from(r)), p);
+        assert_eq!(ResourceId::unchecked(0), r);
+        assert_eq!(ResourceId::from(0), r);
+        let plain: usize = p.into();
+        assert_eq!(ProcessId::from(plain), p);
+        assert!(matches!(p.event(1.0), Effect::Event { process: 0, .. }));
+    }
+}
@@ -0,0 +1,158 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! Detection of processes that are resumed without making progress.
+
+use crate::{Effect, ProcessId, SimState, Simulation, StaticProcess};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+
+/// Number of recent effects kept for each watched process
+const RECENT: usize = 8;
+
+/// A process resumed too many times without making progress, recorded
+/// by the watchdog set with `Simulation::set_watchdog`.
+#[derive(Debug, Clone)]
+pub struct WatchdogReport {
+    /// The process
+    pub process: ProcessId,
+    /// Simulation time of the report
+    pub time: f64,
+    /// Time of the last progress of the process, or of its first resume
+    pub last_progress: f64,
+    /// Number of times the process was resumed since its last progress
+    pub resumes: usize,
+    /// The last effects yielded by the process, the latest last
+    pub recent: Vec<Effect>,
+}
+
+/// The type of the hook set with `Simulation::set_watchdog_hook`
+pub type WatchdogHook = dyn FnMut(&WatchdogReport);
+
+/// The progress of a watched process.
+#[derive(Debug, Clone)]
+struct Track {
+    last_progress: f64,
+    resumes: usize,
+    recent: VecDeque<Effect>,
+}
+
+/// The state of the watchdog of a simulation.
+#[derive(Default)]
+pub(crate) struct Watchdog {
+    threshold: Option<usize>,
+    /// Thresholds of single processes, overriding the global one
+    overrides: BTreeMap<ProcessId, Option<usize>>,
+    tracks: BTreeMap<ProcessId, Track>,
+    reports: Vec<WatchdogReport>,
+    hook: Option<Box<WatchdogHook>>,
+}
+
+impl Watchdog {
+    fn threshold(&self, process: ProcessId) -> Option<usize> {
+        match self.overrides.get(&process) {
+            Some(&threshold) => threshold,
+            None => self.threshold,
+        }
+    }
+}
+
+/// Whether yielding `effect` is progress: waiting for some time, or
+/// releasing what was held.
+fn is_progress(effect: &Effect) -> bool {
+    matches!(
+        effect,
+        Effect::TimeOut(_)
+            | Effect::Sleep(_)
+            | Effect::LabeledTimeOut { .. }
+            | Effect::Release(_)
+            | Effect::ReleaseLease(_)
+            | Effect::ReleaseLock(_)
+    )
+}
+
+impl<T: SimState + Clone, G: StaticProcess<T>> Simulation<T, G> {
+    /// Watch every process, recording a `WatchdogReport` when one is
+    /// resumed more than `threshold` times since its last progress, or
+    /// disable the watchdog with `None`. It is disabled by default.
+    ///
+    /// A process makes progress when it yields a timeout, including
+    /// `Effect::Sleep`, or releases a resource, a lease or a lock. After
+    /// a report, the resumes of the process are counted again from zero.
+    pub fn set_watchdog(&mut self, threshold: Option<usize>) {
+        self.watchdog.threshold = threshold;
+    }
+
+    /// Set the threshold of the watchdog for process `id` only, overriding
+    /// the one set with `set_watchdog`; `None` disables it for the process.
+    pub fn set_process_watchdog(&mut self, id: ProcessId, threshold: Option<usize>) {
+        self.watchdog.overrides.insert(id, threshold);
+    }
+
+    /// Set a hook called with each `WatchdogReport`, when it is recorded.
+    pub fn set_watchdog_hook(&mut self, hook: Box<WatchdogHook>) {
+        self.watchdog.hook = Some(hook);
+    }
+
+    /// Returns the reports of the watchdog, in the order they were recorded.
+    pub fn watchdog_reports(&self) -> &[WatchdogReport] {
+        &self.watchdog.reports
+    }
+
+    /// Count the resume of `process` that yielded `effect`.
+    pub(crate) fn watch_progress(&mut self, process: ProcessId, effect: &Effect) {
+        let threshold = match self.watchdog.threshold(process) {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let now = self.time;
+        let watchdog = &mut self.watchdog;
+        let track = watchdog.tracks.entry(process).or_insert_with(|| Track {
+            last_progress: now,
+            resumes: 0,
+            recent: VecDeque::new(),
+        });
+        if track.recent.len() == RECENT {
+            track.recent.pop_front();
+        }
+        track.recent.push_back(*effect);
+        if is_progress(effect) {
+            track.last_progress = now;
+            track.resumes = 0;
+            return;
+        }
+        track.resumes += 1;
+        if track.resumes > threshold {
+            let report = WatchdogReport {
+                process,
+                time: now,
+                last_progress: track.last_progress,
+                resumes: track.resumes,
+                recent: track.recent.iter().copied().collect(),
+            };
+            track.resumes = 0;
+            if let Some(hook) = &mut watchdog.hook {
+                hook(&report);
+            }
+            watchdog.reports.push(report);
+        }
+    }
+
+    /// Stop watching `process`, that completed.
+    pub(crate) fn unwatch(&mut self, process: ProcessId) {
+        self.watchdog.tracks.remove(&process);
+    }
+}
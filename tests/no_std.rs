@@ -0,0 +1,52 @@
+/* Copyright © 2018 Gianmarco Garrisi
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <http://www.gnu.org/licenses/>. */
+
+//! A `no_std` consumer of the crate, running a basic model.
+//!
+//! Run it without the default features to also build desim without std:
+//! `cargo test --no-default-features --test no_std`.
+
+#![no_std]
+
+extern crate alloc;
+// only needed by the test harness, the model does not use it
+extern crate std;
+
+use alloc::vec::Vec;
+use desim::{Effect, EndCondition, Simulation};
+
+#[test]
+fn basic_model() {
+    let mut sim = Simulation::<Effect>::new();
+    let cpu = sim.create_resource(1);
+    let mut processes = Vec::new();
+    for job in [5.0, 3.0].iter().copied() {
+        processes.push(sim.create_async_process(move |handle| async move {
+            handle.request(cpu).await;
+            handle.timeout(job).await;
+            handle.release(cpu).await;
+        }));
+    }
+    sim.schedule_event(0.0, processes[0], Effect::TimeOut(0.0));
+    sim.schedule_event(1.0, processes[1], Effect::TimeOut(0.0));
+    let sim = sim.run(EndCondition::NoEvents);
+
+    assert_eq!(sim.time(), 8.0);
+    assert_eq!(
+        sim.resource_hold_log(cpu),
+        &[(0.0, 5.0, processes[0]), (5.0, 8.0, processes[1])]
+    );
+    assert_eq!(sim.live_process_count(), 0);
+}